@@ -85,6 +85,7 @@ export interface Instance {
   parent_object_index: null | number;
   depth: number;
   vars: Namespace;
+  members: Record<string, Value>;
   state: InstanceState;
 }
 
@@ -113,6 +114,9 @@ export interface Color {
   a: number;
 }
 
-export type Velocity =
-  | { Cartesian: Vec2; Polar?: never }
-  | { Cartesian?: never; Polar: Vec2 };
+export interface Velocity {
+  hspeed: number;
+  vspeed: number;
+  speed: number;
+  direction: number;
+}
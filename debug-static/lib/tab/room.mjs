@@ -75,12 +75,8 @@ class Room extends LitElement {
           />
         `;
 
-    let velocity = instance.state.velocity;
-    if (velocity.Cartesian) {
-      velocity = `cartesian(${velocity.Cartesian[0]},${velocity.Cartesian[1]})`;
-    } else {
-      velocity = `polar(${velocity.Polar[0]},${velocity.Polar[1]})`;
-    }
+    const { hspeed, vspeed, speed, direction } = instance.state.velocity;
+    const velocity = `(${hspeed},${vspeed}) / ${speed} at ${direction}°`;
 
     return html`
       <d-tab title="${title}">
@@ -101,7 +97,7 @@ class Room extends LitElement {
             <div>Velocity ${velocity}</div>
           </div>
         </div>
-        <gml-namespace .value="${instance.vars}"></gml-namespace>
+        <gml-namespace .value="${{ vars: instance.members }}"></gml-namespace>
       </d-tab>
     `;
   }
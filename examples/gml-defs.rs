@@ -49,15 +49,15 @@ fn main() {
     visitor.def_resources(&content.objects);
     visitor.def_resources(&content.rooms);
 
-    for (_, action) in enum_actions(&content) {
+    for (_, action) in content.iter_actions() {
         if let Some(index) = action_execute_script(action) {
             let (name, _) = content.scripts.item(index);
             visitor.fns.refs.insert(name.into());
         }
     }
 
-    for (id, source) in enum_scripts(&content) {
-        if let ScriptId::Resource(name) = id {
+    for (id, source) in content.iter_code() {
+        if let gmk_file::CodeLocation::Script(name) = id {
             visitor.fns.defs.insert(name.into());
         }
         match gml::parse(&format!("{id:?}"), source) {
@@ -170,59 +170,6 @@ fn main() {
     }
 }
 
-fn enum_scripts(content: &gmk_file::Content) -> impl Iterator<Item = (ScriptId<'_>, &str)> {
-    content
-        .scripts
-        .iter()
-        .map(|(_, name, res)| (ScriptId::Resource(name), res.script.0.as_str()))
-        .chain(content.rooms.iter().flat_map(|(_, name, res)| {
-            Some((ScriptId::RoomInit(name), res.creation_code.0.as_str()))
-                .into_iter()
-                .chain(res.instances.iter().map(|res| {
-                    (
-                        ScriptId::InstanceInit(name, res.id),
-                        res.creation_code.0.as_str(),
-                    )
-                }))
-        }))
-        .chain(
-            enum_actions(content)
-                .filter_map(|(id, a)| action_code(a).map(|code| (ScriptId::Action(id), code))),
-        )
-}
-
-fn enum_actions(
-    content: &gmk_file::Content,
-) -> impl Iterator<Item = (ActionId<'_>, &gmk_file::Action)> {
-    content
-        .objects
-        .iter()
-        .flat_map(|(_, name, res)| {
-            res.events.iter().flat_map(|(id, e)| {
-                e.actions
-                    .iter()
-                    .enumerate()
-                    .map(|(i, a)| (ActionId::ObjectEvent(name, *id, i), a))
-            })
-        })
-        .chain(content.timelines.iter().flat_map(|(_, name, res)| {
-            res.moments.iter().flat_map(|m| {
-                m.actions
-                    .iter()
-                    .enumerate()
-                    .map(|(i, a)| (ActionId::Timeline(name, m.position, i), a))
-            })
-        }))
-}
-
-fn action_code(action: &gmk_file::Action) -> Option<&str> {
-    if action.kind == gmk_file::ActionKind::Code {
-        Some(action.argument_values[0].0.as_str())
-    } else {
-        None
-    }
-}
-
 fn action_execute_script(action: &gmk_file::Action) -> Option<u32> {
     if action.kind == gmk_file::ActionKind::Normal
         && action.exec == gmk_file::ActionExec::Function
@@ -233,17 +180,3 @@ fn action_execute_script(action: &gmk_file::Action) -> Option<u32> {
         None
     }
 }
-
-#[derive(Debug)]
-enum ScriptId<'a> {
-    Resource(&'a str),
-    RoomInit(&'a str),
-    InstanceInit(&'a str, u32),
-    Action(ActionId<'a>),
-}
-
-#[derive(Debug)]
-enum ActionId<'a> {
-    ObjectEvent(&'a str, gmk_file::EventId, usize),
-    Timeline(&'a str, u32, usize),
-}
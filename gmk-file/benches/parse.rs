@@ -0,0 +1,18 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+
+const PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../ref/source code/iji.gmk");
+
+fn parse(c: &mut Criterion) {
+    if !std::path::Path::new(PATH).exists() {
+        eprintln!("skipping, {PATH} not found");
+        return;
+    }
+    let mut group = c.benchmark_group("gmk");
+    // each iteration decrypts and parses the whole file
+    group.sample_size(10);
+    group.bench_function("parse iji.gmk", |b| b.iter(|| gmk_file::parse(PATH)));
+    group.finish();
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
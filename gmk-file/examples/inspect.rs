@@ -0,0 +1,91 @@
+//! Prints what a game file contains and uses, to check before running it.
+//!
+//! `cargo run -p gmk-file --example inspect [path]`
+
+use std::collections::{BTreeMap, BTreeSet};
+
+fn main() {
+    let path = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "ref/source code/iji.gmk".to_string());
+    let content = gmk_file::parse(&path);
+
+    let version = content.version();
+    println!("{path}: {version:?}");
+    let features = [
+        ("smooth edges", version.supports_smooth_edges()),
+        ("background tiling", version.supports_background_tiling()),
+        ("video memory", version.supports_video_memory()),
+        ("help window", version.supports_help_window()),
+    ];
+    for (feature, supported) in features {
+        println!("  {feature}: {}", if supported { "yes" } else { "no" });
+    }
+
+    println!();
+    println!("resources:");
+    let counts = [
+        ("sounds", content.sounds.iter().count()),
+        ("sprites", content.sprites.iter().count()),
+        ("backgrounds", content.backgrounds.iter().count()),
+        ("paths", content.paths.iter().count()),
+        ("scripts", content.scripts.iter().count()),
+        ("fonts", content.fonts.iter().count()),
+        ("timelines", content.timelines.iter().count()),
+        ("objects", content.objects.iter().count()),
+        ("rooms", content.rooms.iter().count()),
+    ];
+    for (kind, count) in counts {
+        println!("  {kind}: {count}");
+    }
+
+    println!();
+    println!("validation errors:");
+    let errors = content.validate();
+    for error in &errors {
+        println!("  {error}");
+    }
+    if errors.is_empty() {
+        println!("  none");
+    }
+
+    let events = content
+        .objects
+        .iter()
+        .flat_map(|(_, _, res)| res.events.keys().map(event_kind))
+        .collect::<BTreeSet<_>>();
+    let mut functions = BTreeMap::<&str, usize>::new();
+    for (_, action) in content.iter_actions() {
+        if action.exec == gmk_file::ActionExec::Function {
+            *functions
+                .entry(action.function_name.0.as_str())
+                .or_default() += 1;
+        }
+    }
+
+    println!();
+    println!("event types:");
+    for kind in events {
+        println!("  {kind}");
+    }
+
+    println!();
+    println!("action functions:");
+    for (name, count) in functions {
+        println!("  {name}: {count}");
+    }
+}
+
+/// The event without the key or object it's for, so each kind is listed once.
+fn event_kind(id: &gmk_file::EventId) -> String {
+    use gmk_file::EventId;
+
+    match id {
+        EventId::Alarm(_) => "Alarm".to_string(),
+        EventId::Collision(_) => "Collision".to_string(),
+        EventId::Keyboard(_) => "Keyboard".to_string(),
+        EventId::KeyPress(_) => "KeyPress".to_string(),
+        EventId::KeyRelease(_) => "KeyRelease".to_string(),
+        id => format!("{id:?}"),
+    }
+}
@@ -0,0 +1,121 @@
+//! Walks every piece of code in a project, for tools that need to look at all of it.
+
+use crate::{Action, ActionKind, Content, EventId};
+
+/// Where a piece of code came from.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum CodeLocation<'a> {
+    Script(&'a str),
+    RoomCreation(&'a str),
+    InstanceCreation { room: &'a str, id: u32 },
+    Action(ActionLocation<'a>),
+}
+
+/// Where an action came from, with its index in the event or moment action list.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ActionLocation<'a> {
+    ObjectEvent {
+        object: &'a str,
+        event: EventId,
+        index: usize,
+    },
+    TimelineMoment {
+        timeline: &'a str,
+        position: u32,
+        index: usize,
+    },
+}
+
+impl Content {
+    /// All scripts, creation code and code actions. Code may be empty.
+    pub fn iter_code(&self) -> impl Iterator<Item = (CodeLocation<'_>, &str)> {
+        self.scripts
+            .iter()
+            .map(|(_, name, res)| (CodeLocation::Script(name), res.script.0.as_str()))
+            .chain(self.rooms.iter().flat_map(|(_, name, res)| {
+                Some((
+                    CodeLocation::RoomCreation(name),
+                    res.creation_code.0.as_str(),
+                ))
+                .into_iter()
+                .chain(res.instances.iter().map(move |res| {
+                    let location = CodeLocation::InstanceCreation {
+                        room: name,
+                        id: res.id,
+                    };
+                    (location, res.creation_code.0.as_str())
+                }))
+            }))
+            .chain(self.iter_actions().filter_map(|(location, action)| {
+                action
+                    .code_text()
+                    .map(|code| (CodeLocation::Action(location), code))
+            }))
+    }
+
+    /// All actions in object events and timeline moments.
+    pub fn iter_actions(&self) -> impl Iterator<Item = (ActionLocation<'_>, &Action)> {
+        self.objects
+            .iter()
+            .flat_map(|(_, name, res)| {
+                res.events.iter().flat_map(move |(&event, e)| {
+                    e.actions.iter().enumerate().map(move |(index, action)| {
+                        let location = ActionLocation::ObjectEvent {
+                            object: name,
+                            event,
+                            index,
+                        };
+                        (location, action)
+                    })
+                })
+            })
+            .chain(self.timelines.iter().flat_map(|(_, name, res)| {
+                res.moments.iter().flat_map(move |moment| {
+                    moment
+                        .actions
+                        .iter()
+                        .enumerate()
+                        .map(move |(index, action)| {
+                            let location = ActionLocation::TimelineMoment {
+                                timeline: name,
+                                position: moment.position,
+                                index,
+                            };
+                            (location, action)
+                        })
+                })
+            }))
+    }
+}
+
+impl Action {
+    /// The code argument of an "execute code" action, not to be confused with the `code` field.
+    pub fn code_text(&self) -> Option<&str> {
+        if self.kind == ActionKind::Code {
+            Some(self.argument_values[0].0.as_str())
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CodeLocation;
+
+    #[test]
+    #[ignore]
+    fn test_iter_code() {
+        let content = crate::parse(crate::tests::FIXTURE);
+        let code = content.iter_code().collect::<Vec<_>>();
+        for (_, name, room) in &content.rooms {
+            assert!(code.contains(&(
+                CodeLocation::RoomCreation(name),
+                room.creation_code.0.as_str()
+            )));
+        }
+        assert!(code
+            .iter()
+            .any(|(location, _)| matches!(location, CodeLocation::Script(_))));
+    }
+}
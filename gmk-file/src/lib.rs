@@ -12,34 +12,43 @@ use nom::number::complete::{le_i32, le_u32};
 use nom_derive::{NomLE, Parse};
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 
+pub use code::*;
+pub use reader::*;
 pub use settings::*;
+pub use spans::*;
+pub use validate::*;
+pub use version::*;
 
+mod code;
+mod reader;
 mod settings;
+mod spans;
+mod validate;
+mod version;
 
 pub fn parse(path: impl AsRef<std::path::Path>) -> Content {
-    return imp(path.as_ref());
-
-    fn imp(path: &std::path::Path) -> Content {
-        let mut data = std::fs::read(path).unwrap();
-        let (header, start) = parse_offset::<FileHeader>(&data, 0);
+    Decrypted::read(path).reader().parse_content()
+}
 
-        // print!("generating decode table from seed {}...", header.crypt.seed);
-        std::io::stdout().flush().unwrap();
-        let decode_table = generate_decode_table(header.crypt.seed);
-        // println!("done");
+/// Reads and decrypts the file, returning the file version, the decrypted data and the
+/// offset of the content.
+fn decode(path: &std::path::Path) -> (u32, Vec<u8>, usize) {
+    let mut data = std::fs::read(path).unwrap();
+    let (header, start) = parse_offset::<FileHeader>(&data, 0);
 
-        // print!("decoding...");
-        std::io::stdout().flush().unwrap();
-        for pos in (start + 1)..data.len() {
-            data[pos] = decode_table[data[pos] as usize].wrapping_sub((pos % 256) as u8);
-        }
-        // println!("done");
+    // print!("generating decode table from seed {}...", header.crypt.seed);
+    std::io::stdout().flush().unwrap();
+    let decode_table = generate_decode_table(header.crypt.seed);
+    // println!("done");
 
-        // reborrow after mutation
-        let (content, _parsed) = parse_offset::<Content>(&data, start);
-        // dbg!(content, parsed, data.len() - parsed);
-        content
+    // print!("decoding...");
+    std::io::stdout().flush().unwrap();
+    for pos in (start + 1)..data.len() {
+        data[pos] = decode_table[data[pos] as usize].wrapping_sub((pos % 256) as u8);
     }
+    // println!("done");
+
+    (header.ver, data, start)
 }
 
 fn parse_offset<'nom, T: Parse<&'nom [u8], nom::error::VerboseError<&'nom [u8]>>>(
@@ -69,7 +78,7 @@ fn parse_offset<'nom, T: Parse<&'nom [u8], nom::error::VerboseError<&'nom [u8]>>
 #[nom(GenericErrors)]
 struct FileHeader {
     _magic: GmkMagic,
-    _ver: u32,
+    ver: u32,
     crypt: GMKrypt,
 }
 
@@ -109,9 +118,12 @@ fn generate_decode_table(seed: u32) -> [u8; 256] {
     table
 }
 
-#[derive(Debug, NomLE)]
-#[nom(GenericErrors)]
+/// Floats compare exactly, as parsing and writing a file should give back the same bits.
+/// The default is an empty game, to build content without a file. Parsed a chunk at a
+/// time by [`ChunkReader::parse_content()`].
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct Content {
+    version: GmVersion,
     pub game_id: u32,
     pub game_guid: Guid,
     pub settings: GameSettings,
@@ -133,10 +145,55 @@ pub struct Content {
     pub information: GameInformation,
     pub library_creation_codes: Chunk<String32>,
     pub room_order: Chunk<u32>,
-    #[nom(Count = "12")]
     pub resource_tree: Vec<ResourceTreeItem>,
 }
 
+impl Content {
+    pub fn version(&self) -> GmVersion {
+        self.version
+    }
+
+    /// See [`ResourceChunk::parents()`].
+    pub fn object_parents(&self, index: u32) -> ObjectParents<'_> {
+        self.objects.parents(index)
+    }
+
+    /// See [`GameInfo`].
+    pub fn game_info(&self) -> GameInfo {
+        let settings = &self.settings;
+        let title = if settings.product.is_empty() {
+            &self.information.caption
+        } else {
+            &settings.product
+        };
+        GameInfo {
+            title: title.to_string(),
+            author: settings.author.to_string(),
+            version: (
+                settings.version_major,
+                settings.version_minor,
+                settings.version_release,
+                settings.version_build,
+            ),
+            game_id: self.game_id,
+            guid: self.game_guid,
+        }
+    }
+}
+
+/// What a launcher shows about a game, without digging through [`GameSettings`].
+#[derive(Debug, Clone)]
+pub struct GameInfo {
+    /// The product name from the version information, or the game information window
+    /// caption if there isn't one.
+    pub title: String,
+    pub author: String,
+    /// Major, minor, release and build numbers.
+    pub version: (u32, u32, u32, u32),
+    pub game_id: u32,
+    pub guid: Guid,
+}
+
 // #[derive(Debug)]
 // struct DebugChunk<T> {
 //     ver: u32,
@@ -174,7 +231,7 @@ pub struct Content {
 //     }
 // }
 
-#[derive(NomLE)]
+#[derive(Clone, PartialEq, NomLE)]
 #[nom(GenericErrors)]
 pub struct ResourceChunk<T> {
     pub ver: u32,
@@ -182,13 +239,79 @@ pub struct ResourceChunk<T> {
     pub items: Vec<Option<ResourceItem<T>>>,
 }
 
+impl<T> Default for ResourceChunk<T> {
+    fn default() -> Self {
+        Self {
+            ver: 0,
+            items: vec![],
+        }
+    }
+}
+
 impl<T> ResourceChunk<T> {
+    pub fn get_item(&self, index: u32) -> Option<(&str, &T)> {
+        let item = self.items.get(index as usize)?.as_ref()?;
+        Some((&item.name.0, &item.data))
+    }
+
     pub fn item(&self, index: u32) -> (&str, &T) {
-        let item = self.items[index as usize].as_ref().unwrap();
-        (&item.name.0, &item.data)
+        self.get_item(index).unwrap()
+    }
+}
+
+impl ResourceChunk<Object> {
+    /// The parents of object `index`, nearest first, up to the root. If the parents loop
+    /// back on themselves, ends with an error instead of repeating.
+    pub fn parents(&self, index: u32) -> ObjectParents<'_> {
+        ObjectParents {
+            objects: self,
+            index,
+            visited: vec![index],
+        }
     }
 }
 
+pub struct ObjectParents<'a> {
+    objects: &'a ResourceChunk<Object>,
+    index: u32,
+    visited: Vec<u32>,
+}
+
+impl Iterator for ObjectParents<'_> {
+    type Item = Result<u32, ObjectParentCycle>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let &current = self.visited.last()?;
+        let parent = self
+            .objects
+            .get_item(current)
+            .and_then(|(_, object)| u32::try_from(object.parent_object_index).ok());
+        let Some(parent) = parent else {
+            self.visited.clear();
+            return None;
+        };
+        if self.visited.contains(&parent) {
+            self.visited.clear();
+            return Some(Err(ObjectParentCycle { index: self.index }));
+        }
+        self.visited.push(parent);
+        Some(Ok(parent))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ObjectParentCycle {
+    pub index: u32,
+}
+
+impl std::fmt::Display for ObjectParentCycle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "object {} has a parent loop", self.index)
+    }
+}
+
+impl std::error::Error for ObjectParentCycle {}
+
 impl<T> std::ops::Index<u32> for ResourceChunk<T> {
     type Output = T;
 
@@ -246,7 +369,7 @@ impl<T> ResourceChunk<T> {
     }
 }
 
-#[derive(Debug, NomLE)]
+#[derive(Clone, Debug, PartialEq, NomLE)]
 #[nom(GenericErrors)]
 pub struct ResourceItem<T> {
     pub name: String32,
@@ -266,19 +389,19 @@ impl<T> std::fmt::Debug for ResourceChunk<T> {
     }
 }
 
-#[derive(Debug, NomLE)]
+#[derive(Clone, Debug, PartialEq, NomLE)]
 #[nom(GenericErrors)]
 pub struct Sound {
     pub ver: u32,
     pub kind: i32,
     pub file_type: String32,
-    // if ver == 440 {
+    // if ver == GM5_SOUND_VER {
     //   if kind != -1 { zlib32 }
     //   _: u64,
     // } else {
     pub file_name: String32,
     pub present: Bool32,
-    pub data: Data32, // deflated in ver == 600
+    pub data: Data32, // deflated in ver == GM6_SOUND_VER
     pub effects: u32,
     pub volume: f64,
     pub pan: f64,
@@ -286,7 +409,7 @@ pub struct Sound {
     pub on_demand: Bool32,
 }
 
-#[derive(Debug, NomLE)]
+#[derive(Clone, Debug, PartialEq, NomLE)]
 #[nom(GenericErrors)]
 pub struct Sprite {
     pub ver: u32,
@@ -296,43 +419,43 @@ pub struct Sprite {
     pub bbox_bottom: i32,
     pub bbox_top: i32,
     pub transparent: Bool32,
-    #[nom(Cond = "ver == 542")]
+    #[nom(Cond = "ver == GM7_SPRITE_VER")]
     pub smooth_edges: Option<Bool32>,
-    #[nom(Cond = "ver == 542")]
+    #[nom(Cond = "ver == GM7_SPRITE_VER")]
     pub preload_texture: Option<Bool32>,
     pub bbox: u32,
     pub precise_collision: Bool32,
-    #[nom(Cond = "ver == 400")]
+    #[nom(Cond = "ver == GM6_SPRITE_VER")]
     pub use_video_memory: Option<Bool32>,
-    #[nom(Cond = "ver == 400")]
+    #[nom(Cond = "ver == GM6_SPRITE_VER")]
     pub on_demand: Option<Bool32>,
     pub origin: I32x2,
     #[nom(LengthCount = "le_u32")]
     pub subimages: Vec<ZlibImage>,
 }
 
-#[derive(Debug, NomLE)]
+#[derive(Clone, Debug, PartialEq, NomLE)]
 #[nom(GenericErrors)]
 pub struct Background {
     pub ver: u32,
     pub size: U32x2,
     pub transparent: Bool32,
-    #[nom(Cond = "ver == 400")]
+    #[nom(Cond = "ver == GM6_BACKGROUND_VER")]
     pub use_video_memory: Option<Bool32>,
-    #[nom(Cond = "ver == 400")]
+    #[nom(Cond = "ver == GM6_BACKGROUND_VER")]
     pub on_demand: Option<Bool32>,
-    #[nom(Cond = "ver == 543")]
+    #[nom(Cond = "ver == GM7_BACKGROUND_VER")]
     pub smooth_edges: Option<Bool32>,
-    #[nom(Cond = "ver == 543")]
+    #[nom(Cond = "ver == GM7_BACKGROUND_VER")]
     pub preload_texture: Option<Bool32>,
-    #[nom(Cond = "ver >= 543")]
+    #[nom(Cond = "ver >= GM7_BACKGROUND_VER")]
     pub tiling: Option<BackgroundTiling>,
     pub image_exists: Bool32,
     #[nom(Cond = "image_exists == Bool32::True")]
     pub image: Option<ZlibImage>,
 }
 
-#[derive(Debug, NomLE)]
+#[derive(Clone, Debug, PartialEq, NomLE)]
 #[nom(GenericErrors)]
 pub struct BackgroundTiling {
     pub enabled: Bool32,
@@ -341,7 +464,7 @@ pub struct BackgroundTiling {
     pub sep: U32x2,
 }
 
-#[derive(Debug, NomLE)]
+#[derive(Clone, Debug, PartialEq, NomLE)]
 #[nom(GenericErrors)]
 pub struct Path {
     pub ver: u32,
@@ -354,21 +477,21 @@ pub struct Path {
     pub points: Vec<Point>,
 }
 
-#[derive(Debug, NomLE)]
+#[derive(Clone, Debug, PartialEq, NomLE)]
 #[nom(GenericErrors)]
 pub struct Point {
     pub position: F64x2,
     pub speed: f64,
 }
 
-#[derive(Debug, NomLE)]
+#[derive(Clone, Debug, PartialEq, NomLE)]
 #[nom(GenericErrors)]
 pub struct Script {
     pub ver: u32,
     pub script: String32,
 }
 
-#[derive(Debug, NomLE)]
+#[derive(Clone, Debug, PartialEq, NomLE)]
 #[nom(GenericErrors)]
 pub struct Font {
     pub ver: u32,
@@ -380,7 +503,7 @@ pub struct Font {
     pub character_range_end: u32,
 }
 
-#[derive(Debug, NomLE)]
+#[derive(Clone, Debug, PartialEq, NomLE)]
 #[nom(GenericErrors)]
 pub struct Timeline {
     pub ver: u32,
@@ -388,7 +511,7 @@ pub struct Timeline {
     pub moments: Vec<Moment>,
 }
 
-#[derive(Debug, NomLE)]
+#[derive(Clone, Debug, PartialEq, NomLE)]
 #[nom(GenericErrors)]
 pub struct Moment {
     pub position: u32,
@@ -397,7 +520,7 @@ pub struct Moment {
     pub actions: Vec<Action>,
 }
 
-#[derive(Debug, NomLE)]
+#[derive(Clone, Debug, PartialEq, NomLE)]
 #[nom(GenericErrors)]
 pub struct Object {
     pub ver: u32,
@@ -703,7 +826,7 @@ pub enum DrawEventId {
     Post = 77,
 }
 
-#[derive(Debug, NomLE)]
+#[derive(Clone, Debug, PartialEq, NomLE)]
 #[nom(GenericErrors)]
 pub struct Event {
     pub ver: u32,
@@ -711,7 +834,7 @@ pub struct Event {
     pub actions: Vec<Action>,
 }
 
-#[derive(Debug, NomLE)]
+#[derive(Clone, Debug, PartialEq, NomLE)]
 #[nom(GenericErrors)]
 pub struct Action {
     pub ver: u32,
@@ -784,7 +907,7 @@ pub enum ArgumentKind {
     FontString,
 }
 
-#[derive(Debug, NomLE)]
+#[derive(Clone, Debug, Default, PartialEq, NomLE)]
 #[nom(GenericErrors)]
 pub struct Room {
     pub ver: u32,
@@ -816,12 +939,12 @@ pub struct Room {
     pub editor_show_views: Bool32,
     pub editor_delete_underlying_objects: Bool32,
     pub editor_delete_underlying_tiles: Bool32,
-    // v520 stuff...
+    // ver == GM5_ROOM_VER stuff...
     pub editor_tab: u32,
     pub editor_scroll: U32x2,
 }
 
-#[derive(Debug, NomLE)]
+#[derive(Clone, Debug, PartialEq, NomLE)]
 #[nom(GenericErrors)]
 pub struct RoomBackground {
     pub visible: Bool32,
@@ -833,11 +956,11 @@ pub struct RoomBackground {
     pub stretch: Bool32,
 }
 
-#[derive(Debug, NomLE)]
+#[derive(Clone, Debug, Default, PartialEq, NomLE)]
 #[nom(GenericErrors)]
 pub struct RoomView {
     pub visible: Bool32,
-    // ver 541+
+    // ver >= GM6_ROOM_VER
     pub view_pos: U32x2,
     pub view_size: U32x2,
     pub port_pos: U32x2,
@@ -847,7 +970,7 @@ pub struct RoomView {
     pub following_index: i32,
 }
 
-#[derive(Debug, NomLE)]
+#[derive(Clone, Debug, PartialEq, NomLE)]
 #[nom(GenericErrors)]
 pub struct RoomInstance {
     pub pos: I32x2,
@@ -857,7 +980,7 @@ pub struct RoomInstance {
     pub locked: Bool32,
 }
 
-#[derive(Debug, NomLE)]
+#[derive(Clone, Debug, PartialEq, NomLE)]
 #[nom(GenericErrors)]
 pub struct RoomTile {
     pub pos: I32x2,
@@ -869,7 +992,7 @@ pub struct RoomTile {
     pub locked: Bool32,
 }
 
-#[derive(Debug, NomLE)]
+#[derive(Clone, Debug, PartialEq, NomLE)]
 #[nom(GenericErrors)]
 pub struct Chunk<T> {
     pub ver: u32,
@@ -877,7 +1000,16 @@ pub struct Chunk<T> {
     pub items: Vec<T>,
 }
 
-#[derive(Debug, NomLE)]
+impl<T> Default for Chunk<T> {
+    fn default() -> Self {
+        Self {
+            ver: 0,
+            items: vec![],
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, NomLE)]
 #[nom(GenericErrors)]
 pub struct Include {
     pub filename: String32,
@@ -894,12 +1026,15 @@ pub struct Include {
     pub remove_at_game_end: Bool32,
 }
 
-#[derive(Debug, NomLE)]
+#[derive(Clone, Debug, Default, PartialEq, NomLE)]
 #[nom(GenericErrors)]
 pub struct GameInformation {
     pub ver: u32,
     pub background_color: Color32,
-    // 800+ help_window: Bool32,
+    /// Whether to show the information in a separate window, see
+    /// [`GmVersion::supports_help_window()`].
+    #[nom(Cond = "ver >= GM8_INFORMATION_VER")]
+    pub help_window: Option<Bool32>,
     pub reuse_main_style: Bool32,
     pub caption: String32,
     pub position: I32x2,
@@ -911,7 +1046,7 @@ pub struct GameInformation {
     pub rtf: String32,
 }
 
-#[derive(Debug, NomLE)]
+#[derive(Clone, Debug, PartialEq, NomLE)]
 #[nom(GenericErrors)]
 pub struct ResourceTreeItem {
     pub status: ResourceTreeStatus,
@@ -923,7 +1058,7 @@ pub struct ResourceTreeItem {
 }
 
 #[repr(u32)]
-#[derive(Debug, NomLE)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, NomLE)]
 #[nom(GenericErrors)]
 pub enum ResourceTreeStatus {
     Primary = 1,
@@ -932,7 +1067,7 @@ pub enum ResourceTreeStatus {
 }
 
 #[repr(u32)]
-#[derive(Debug, NomLE)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, NomLE)]
 #[nom(GenericErrors)]
 pub enum ResourceKind {
     None = 0,
@@ -952,7 +1087,7 @@ pub enum ResourceKind {
     Shader = 14,
 }
 
-#[derive(NomLE)]
+#[derive(Copy, Clone, Default, Eq, PartialEq, NomLE)]
 #[nom(GenericErrors)]
 pub struct Color32(u32);
 
@@ -962,7 +1097,7 @@ impl std::fmt::Debug for Color32 {
     }
 }
 
-#[derive(NomLE)]
+#[derive(Copy, Clone, Default, Eq, PartialEq, NomLE)]
 #[nom(GenericErrors)]
 pub struct Pair<T>(pub T, pub T);
 
@@ -984,7 +1119,7 @@ fn parse_cond32<'nom, T: Parse<&'nom [u8], E>, E: ParseError<&'nom [u8]>>(
     })(input)
 }
 
-#[derive(Copy, Clone, Eq, PartialEq)]
+#[derive(Copy, Clone, Default, Eq, PartialEq)]
 pub struct Guid(pub [u8; 16]);
 
 impl<'nom, E: ParseError<&'nom [u8]>> Parse<&'nom [u8], E> for Guid {
@@ -1005,7 +1140,7 @@ impl std::fmt::Debug for Guid {
     }
 }
 
-#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, NomLE)]
+#[derive(Clone, Default, Ord, PartialOrd, Eq, PartialEq, NomLE)]
 #[nom(GenericErrors)]
 pub struct String32(#[nom(Parse = "parse_string")] pub String);
 
@@ -1042,13 +1177,13 @@ fn parse_inflate<'nom, E: ParseError<&'nom [u8]>>(
 ) -> nom::IResult<&'nom [u8], Vec<u8>, E> {
     let (input, data) = flat_map(le_u32, take)(input)?;
     let data = inflate::inflate_bytes_zlib(data).map_err(|error| {
-        eprintln!("inflate failed: {error}");
+        log::error!("inflate failed: {error}");
         nom::Err::Failure(E::from_error_kind(data, nom::error::ErrorKind::Verify))
     })?;
     Ok((input, data))
 }
 
-#[derive(NomLE)]
+#[derive(Clone, Default, Eq, PartialEq, NomLE)]
 #[nom(GenericErrors)]
 pub struct Data32 {
     length: u32,
@@ -1064,7 +1199,7 @@ impl std::fmt::Debug for Data32 {
     }
 }
 
-#[derive(NomLE)]
+#[derive(Clone, NomLE)]
 #[nom(GenericErrors)]
 pub struct ZlibImage {
     _present: i32,
@@ -1080,6 +1215,23 @@ impl std::fmt::Debug for ZlibImage {
     }
 }
 
+/// No image.
+impl Default for ZlibImage {
+    fn default() -> Self {
+        Self {
+            _present: -1,
+            data: None,
+        }
+    }
+}
+
+/// Compares the inflated data, as that's all that's kept of the image.
+impl PartialEq for ZlibImage {
+    fn eq(&self, other: &Self) -> bool {
+        self.data == other.data
+    }
+}
+
 impl ZlibImage {
     pub fn parse(
         &self,
@@ -1089,13 +1241,21 @@ impl ZlibImage {
 }
 
 #[repr(u32)]
-#[derive(Copy, Clone, Eq, PartialEq, Debug, NomLE)]
-#[nom(GenericErrors)]
+#[derive(Copy, Clone, Default, Eq, PartialEq, Debug)]
 pub enum Bool32 {
+    #[default]
     False,
     True,
 }
 
+/// Like GM, any nonzero value is true.
+impl<'nom, E: ParseError<&'nom [u8]>> Parse<&'nom [u8], E> for Bool32 {
+    fn parse(i: &'nom [u8]) -> nom::IResult<&'nom [u8], Self, E> {
+        let (i, value) = le_u32(i)?;
+        Ok((i, if value != 0 { Self::True } else { Self::False }))
+    }
+}
+
 impl From<Bool32> for bool {
     fn from(value: Bool32) -> bool {
         value == Bool32::True
@@ -1124,3 +1284,177 @@ pub struct ImageData<'a> {
     #[nom(MoveAbs = "_data_offset", Take = "_data_size")]
     pub data: &'a [u8],
 }
+
+#[cfg(test)]
+mod tests {
+    use nom::error::VerboseError;
+
+    use super::*;
+
+    /// The game file the ignored tests read, which isn't in the repo, see README.
+    pub(crate) const FIXTURE: &str =
+        concat!(env!("CARGO_MANIFEST_DIR"), "/../ref/source code/iji.gmk");
+
+    pub(crate) fn objects(parents: &[i32]) -> ResourceChunk<Object> {
+        let items = parents
+            .iter()
+            .enumerate()
+            .map(|(index, &parent_object_index)| {
+                Some(ResourceItem {
+                    name: String32(format!("obj_{index}")),
+                    data: Object {
+                        ver: 430,
+                        sprite_index: -1,
+                        solid: Bool32::False,
+                        visible: Bool32::True,
+                        depth: 0,
+                        persistent: Bool32::False,
+                        parent_object_index,
+                        mask_sprite_index: -1,
+                        events: BTreeMap::new(),
+                    },
+                })
+            });
+        ResourceChunk {
+            ver: 400,
+            items: items.collect(),
+        }
+    }
+
+    #[test]
+    fn test_object_parents() {
+        // 2 -> 1 -> 0
+        let chunk = objects(&[-1, 0, 1]);
+        assert_eq!(chunk.parents(2).collect::<Vec<_>>(), [Ok(1), Ok(0)]);
+        assert_eq!(chunk.parents(0).count(), 0);
+
+        // 2 -> 0 -> 1 -> 0
+        let chunk = objects(&[1, 0, 0]);
+        assert_eq!(
+            chunk.parents(2).collect::<Vec<_>>(),
+            [Ok(0), Ok(1), Err(ObjectParentCycle { index: 2 })]
+        );
+        assert!(chunk.parents(1).collect::<Result<Vec<_>, _>>().is_err());
+    }
+
+    #[test]
+    fn test_game_info() {
+        let mut content = Content {
+            game_id: 1234,
+            game_guid: Guid([7; 16]),
+            ..Default::default()
+        };
+        content.settings.author = String32("Someone".to_string());
+        content.settings.version_major = 1;
+        content.settings.version_build = 5;
+        content.information.caption = String32("Game Information".to_string());
+
+        let info = content.game_info();
+        assert_eq!(info.game_id, 1234);
+        assert!(info.guid == Guid([7; 16]));
+        assert_eq!(info.author, "Someone");
+        assert_eq!(info.version, (1, 0, 0, 5));
+        // falls back to the information caption without a product name
+        assert_eq!(info.title, "Game Information");
+        content.settings.product = String32("Game".to_string());
+        assert_eq!(content.game_info().title, "Game");
+    }
+
+    #[test]
+    #[ignore]
+    fn test_game_info_fixture() {
+        let content = parse(FIXTURE);
+        let info = content.game_info();
+        assert_eq!(info.game_id, content.game_id);
+        assert!(info.guid == content.game_guid);
+        assert_ne!(info.version, (0, 0, 0, 0));
+        assert!(!info.title.is_empty());
+    }
+
+    #[test]
+    fn test_parse_gm8_information() {
+        fn u32s(bytes: &mut Vec<u8>, values: &[u32]) {
+            for value in values {
+                bytes.extend(value.to_le_bytes());
+            }
+        }
+        fn string(bytes: &mut Vec<u8>, value: &str) {
+            u32s(bytes, &[value.len() as u32]);
+            bytes.extend(value.as_bytes());
+        }
+
+        let mut bytes = vec![];
+        // ver, background_color, help_window, reuse_main_style
+        u32s(&mut bytes, &[GM8_INFORMATION_VER, 0xffffe1, 1, 0]);
+        string(&mut bytes, "Game Information");
+        // position, size, border, resizable, topmost, pause
+        u32s(&mut bytes, &[u32::MAX, u32::MAX, 600, 400, 1, 1, 0, 1]);
+        string(&mut bytes, "{\\rtf1}");
+        // library_creation_codes
+        u32s(&mut bytes, &[500, 1]);
+        string(&mut bytes, "code");
+        // room_order
+        u32s(&mut bytes, &[700, 2, 3, 0]);
+
+        let (information, offset) = parse_offset::<GameInformation>(&bytes, 0);
+        assert_eq!(information.help_window, Some(Bool32::True));
+        assert_eq!(information.caption.0, "Game Information");
+        assert_eq!((information.position.0, information.position.1), (-1, -1));
+        assert_eq!(information.rtf.0, "{\\rtf1}");
+        let (codes, offset) = parse_offset::<Chunk<String32>>(&bytes, offset);
+        assert_eq!(codes.items.len(), 1);
+        assert_eq!(codes.items[0].0, "code");
+        let (room_order, offset) = parse_offset::<Chunk<u32>>(&bytes, offset);
+        assert_eq!(room_order.items, [3, 0]);
+        assert_eq!(offset, bytes.len());
+
+        // GM 7 doesn't have help_window
+        let mut bytes = vec![];
+        u32s(&mut bytes, &[GM7_INFORMATION_VER, 0xffffe1, 0]);
+        string(&mut bytes, "");
+        u32s(&mut bytes, &[0, 0, 600, 400, 1, 1, 0, 1]);
+        string(&mut bytes, "");
+        let (information, offset) = parse_offset::<GameInformation>(&bytes, 0);
+        assert_eq!(information.help_window, None);
+        assert_eq!((information.size.0, information.size.1), (600, 400));
+        assert_eq!(offset, bytes.len());
+    }
+
+    #[test]
+    fn test_parse_bool32() {
+        let parse = |value: u32| {
+            let bytes = value.to_le_bytes();
+            Bool32::parse(&bytes[..])
+                .map(|(_, value)| value)
+                .map_err(|_: nom::Err<VerboseError<_>>| ())
+        };
+        assert_eq!(parse(0), Ok(Bool32::False));
+        assert_eq!(parse(1), Ok(Bool32::True));
+        assert_eq!(parse(2), Ok(Bool32::True));
+        assert_eq!(parse(u32::MAX), Ok(Bool32::True));
+    }
+
+    #[test]
+    fn test_eq() {
+        let chunk = objects(&[-1, 0]);
+        assert_eq!(chunk, chunk.clone());
+        let mut changed = chunk.clone();
+        changed.items[1].as_mut().unwrap().data.depth = 10;
+        assert_ne!(chunk, changed);
+
+        // only the inflated data is compared
+        let image = |present, data: Option<&[u8]>| ZlibImage {
+            _present: present,
+            data: data.map(Vec::from),
+        };
+        assert_eq!(image(10, Some(b"BM")), image(12, Some(b"BM")));
+        assert_ne!(image(10, Some(b"BM")), image(-1, None));
+    }
+
+    #[test]
+    #[ignore]
+    fn test_eq_fixture() {
+        let content = parse(FIXTURE);
+        assert_eq!(content, content.clone());
+    }
+}
@@ -0,0 +1,309 @@
+use std::ops::Range;
+
+use nom::error::VerboseError;
+use nom_derive::Parse;
+
+use crate::*;
+
+/// The decrypted data of a game file, to read a chunk at a time with [`ChunkReader`].
+pub struct Decrypted {
+    pub version: GmVersion,
+    pub data: Vec<u8>,
+    start: usize,
+}
+
+impl Decrypted {
+    pub fn read(path: impl AsRef<std::path::Path>) -> Self {
+        let (ver, data, start) = decode(path.as_ref());
+        Self {
+            version: GmVersion::from_file_version(ver),
+            data,
+            start,
+        }
+    }
+
+    pub fn reader(&self) -> ChunkReader<'_> {
+        ChunkReader {
+            version: self.version,
+            data: &self.data,
+            offset: self.start,
+            next: Stage::Header,
+            spans: ResourceSpans::default(),
+        }
+    }
+}
+
+/// The parts of the file, in order.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+enum Stage {
+    Header,
+    Sounds,
+    Sprites,
+    Backgrounds,
+    Paths,
+    Scripts,
+    Fonts,
+    Timelines,
+    Objects,
+    Rooms,
+    End,
+}
+
+/// Parses [`Content`] one chunk at a time, so tools that only need some resources don't
+/// hold the rest in memory. Chunks can only be read in file order: reading a chunk skips
+/// any earlier chunks that weren't read, and panics if it was already passed. Skipping
+/// still has to parse each item to find where the next starts, but only holds one at a time.
+pub struct ChunkReader<'a> {
+    version: GmVersion,
+    data: &'a [u8],
+    offset: usize,
+    next: Stage,
+    spans: ResourceSpans,
+}
+
+impl<'a> ChunkReader<'a> {
+    /// The whole file, which must not have been read from yet. [`parse()`] is this.
+    pub fn parse_content(&mut self) -> Content {
+        let (game_id, game_guid, settings) = self.parse_header();
+        Content {
+            version: self.version,
+            game_id,
+            game_guid,
+            settings,
+            sounds: self.parse_sounds(),
+            sprites: self.parse_sprites(),
+            backgrounds: self.parse_backgrounds(),
+            paths: self.parse_paths(),
+            scripts: self.parse_scripts(),
+            fonts: self.parse_fonts(),
+            timelines: self.parse_timelines(),
+            objects: self.parse_objects(),
+            rooms: self.parse_rooms(),
+            last_instance_id: read_u32(self.data, &mut self.offset),
+            last_tile_id: read_u32(self.data, &mut self.offset),
+            includes: read(self.data, &mut self.offset),
+            extensions: read(self.data, &mut self.offset),
+            information: read(self.data, &mut self.offset),
+            library_creation_codes: read(self.data, &mut self.offset),
+            room_order: read(self.data, &mut self.offset),
+            resource_tree: (0..12).map(|_| read(self.data, &mut self.offset)).collect(),
+        }
+    }
+
+    /// Where the items of each chunk that was parsed or skipped are.
+    pub fn into_spans(self) -> ResourceSpans {
+        self.spans
+    }
+
+    /// The game id, GUID and settings at the start of the file.
+    pub fn parse_header(&mut self) -> (u32, Guid, GameSettings) {
+        self.seek(Stage::Header);
+        let game_id = read_u32(self.data, &mut self.offset);
+        let game_guid = read(self.data, &mut self.offset);
+        let settings = read(self.data, &mut self.offset);
+        self.next = Stage::Sounds;
+        (game_id, game_guid, settings)
+    }
+
+    pub fn parse_sounds(&mut self) -> ResourceChunk<Sound> {
+        self.parse_chunk(Stage::Sounds)
+    }
+
+    pub fn parse_sprites(&mut self) -> ResourceChunk<Sprite> {
+        self.parse_chunk(Stage::Sprites)
+    }
+
+    pub fn parse_backgrounds(&mut self) -> ResourceChunk<Background> {
+        self.parse_chunk(Stage::Backgrounds)
+    }
+
+    pub fn parse_paths(&mut self) -> ResourceChunk<Path> {
+        self.parse_chunk(Stage::Paths)
+    }
+
+    pub fn parse_scripts(&mut self) -> ResourceChunk<Script> {
+        self.parse_chunk(Stage::Scripts)
+    }
+
+    pub fn parse_fonts(&mut self) -> ResourceChunk<Font> {
+        self.parse_chunk(Stage::Fonts)
+    }
+
+    pub fn parse_timelines(&mut self) -> ResourceChunk<Timeline> {
+        self.parse_chunk(Stage::Timelines)
+    }
+
+    pub fn parse_objects(&mut self) -> ResourceChunk<Object> {
+        self.parse_chunk(Stage::Objects)
+    }
+
+    pub fn parse_rooms(&mut self) -> ResourceChunk<Room> {
+        self.parse_chunk(Stage::Rooms)
+    }
+
+    pub fn skip_sounds(&mut self) {
+        self.skip_chunk::<Sound>(Stage::Sounds);
+    }
+
+    pub fn skip_sprites(&mut self) {
+        self.skip_chunk::<Sprite>(Stage::Sprites);
+    }
+
+    pub fn skip_backgrounds(&mut self) {
+        self.skip_chunk::<Background>(Stage::Backgrounds);
+    }
+
+    pub fn skip_paths(&mut self) {
+        self.skip_chunk::<Path>(Stage::Paths);
+    }
+
+    pub fn skip_scripts(&mut self) {
+        self.skip_chunk::<Script>(Stage::Scripts);
+    }
+
+    pub fn skip_fonts(&mut self) {
+        self.skip_chunk::<Font>(Stage::Fonts);
+    }
+
+    pub fn skip_timelines(&mut self) {
+        self.skip_chunk::<Timeline>(Stage::Timelines);
+    }
+
+    pub fn skip_objects(&mut self) {
+        self.skip_chunk::<Object>(Stage::Objects);
+    }
+
+    pub fn skip_rooms(&mut self) {
+        self.skip_chunk::<Room>(Stage::Rooms);
+    }
+
+    /// Skips the earlier chunks up to `stage`, which must not have been passed.
+    fn seek(&mut self, stage: Stage) {
+        assert!(self.next <= stage, "{stage:?} was already read");
+        while self.next < stage {
+            match self.next {
+                Stage::Header => {
+                    self.parse_header();
+                }
+                Stage::Sounds => self.skip_sounds(),
+                Stage::Sprites => self.skip_sprites(),
+                Stage::Backgrounds => self.skip_backgrounds(),
+                Stage::Paths => self.skip_paths(),
+                Stage::Scripts => self.skip_scripts(),
+                Stage::Fonts => self.skip_fonts(),
+                Stage::Timelines => self.skip_timelines(),
+                Stage::Objects => self.skip_objects(),
+                Stage::Rooms => self.skip_rooms(),
+                Stage::End => unreachable!(),
+            }
+        }
+    }
+
+    fn parse_chunk<T>(&mut self, stage: Stage) -> ResourceChunk<T>
+    where
+        ResourceItem<T>: Parse<&'a [u8], VerboseError<&'a [u8]>>,
+    {
+        let mut items = vec![];
+        let ver = self.read_chunk(stage, |item| items.push(item));
+        ResourceChunk { ver, items }
+    }
+
+    fn skip_chunk<T>(&mut self, stage: Stage)
+    where
+        ResourceItem<T>: Parse<&'a [u8], VerboseError<&'a [u8]>>,
+    {
+        self.read_chunk::<T>(stage, drop);
+    }
+
+    /// Passes each item of the chunk for `stage` to `item` as it's parsed, recording its
+    /// span, and returns the chunk version.
+    fn read_chunk<T>(&mut self, stage: Stage, mut item: impl FnMut(Option<ResourceItem<T>>)) -> u32
+    where
+        ResourceItem<T>: Parse<&'a [u8], VerboseError<&'a [u8]>>,
+    {
+        self.seek(stage);
+        let ver = read_u32(self.data, &mut self.offset);
+        let count = read_u32(self.data, &mut self.offset);
+        let mut spans = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let start = self.offset;
+            let present: Bool32 = read(self.data, &mut self.offset);
+            item((present == Bool32::True).then(|| read(self.data, &mut self.offset)));
+            spans.push(start..self.offset);
+        }
+        *self.spans.get_mut(stage) = spans;
+        self.next = next_stage(stage);
+        ver
+    }
+}
+
+impl ResourceSpans {
+    fn get_mut(&mut self, stage: Stage) -> &mut Vec<Range<usize>> {
+        match stage {
+            Stage::Sounds => &mut self.sounds,
+            Stage::Sprites => &mut self.sprites,
+            Stage::Backgrounds => &mut self.backgrounds,
+            Stage::Paths => &mut self.paths,
+            Stage::Scripts => &mut self.scripts,
+            Stage::Fonts => &mut self.fonts,
+            Stage::Timelines => &mut self.timelines,
+            Stage::Objects => &mut self.objects,
+            Stage::Rooms => &mut self.rooms,
+            Stage::Header | Stage::End => unreachable!("{stage:?} isn't a chunk"),
+        }
+    }
+}
+
+fn read<'nom, T: Parse<&'nom [u8], VerboseError<&'nom [u8]>>>(
+    data: &'nom [u8],
+    offset: &mut usize,
+) -> T {
+    let (value, end) = parse_offset(data, *offset);
+    *offset = end;
+    value
+}
+
+fn read_u32(data: &[u8], offset: &mut usize) -> u32 {
+    let value = u32::from_le_bytes(data[*offset..*offset + 4].try_into().unwrap());
+    *offset += 4;
+    value
+}
+
+fn next_stage(stage: Stage) -> Stage {
+    match stage {
+        Stage::Header => Stage::Sounds,
+        Stage::Sounds => Stage::Sprites,
+        Stage::Sprites => Stage::Backgrounds,
+        Stage::Backgrounds => Stage::Paths,
+        Stage::Paths => Stage::Scripts,
+        Stage::Scripts => Stage::Fonts,
+        Stage::Fonts => Stage::Timelines,
+        Stage::Timelines => Stage::Objects,
+        Stage::Objects => Stage::Rooms,
+        Stage::Rooms | Stage::End => Stage::End,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[ignore]
+    fn test_parse_rooms_only() {
+        let path = crate::tests::FIXTURE;
+        let decrypted = Decrypted::read(path);
+        let mut reader = decrypted.reader();
+        let rooms = reader.parse_rooms();
+
+        let content = parse(path);
+        assert_eq!(rooms.items.len(), content.rooms.items.len());
+        let names = |chunk: &ResourceChunk<Room>| {
+            chunk
+                .iter()
+                .map(|(_, name, _)| name.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(&rooms), names(&content.rooms));
+    }
+}
@@ -1,11 +1,11 @@
 use nom_derive::NomLE;
 
-use crate::{Bool32, Data32, String32, ZlibImage};
+use crate::{Bool32, Data32, String32, ZlibImage, GM7_SETTINGS_VER, GM8_SETTINGS_VER};
 
-#[derive(Debug, NomLE)]
+#[derive(Clone, Debug, Default, PartialEq, NomLE)]
 #[nom(GenericErrors)]
 pub struct GameSettings {
-    #[nom(Verify = "*ver == 702")]
+    #[nom(Verify = "*ver == GM7_SETTINGS_VER || *ver == GM8_SETTINGS_VER")]
     pub ver: u32,
     pub fullscreen: Bool32,
     pub interpolate: Bool32,
@@ -21,7 +21,8 @@ pub struct GameSettings {
     pub frequency: Frequency,
     pub dont_show_buttons: Bool32,
     pub sync: Bool32, // actually, software vertex processing in high bit.
-    // if ver >= 800 disable_screensavers: Bool32,
+    #[nom(Cond = "ver >= GM8_SETTINGS_VER")]
+    pub disable_screensavers: Option<Bool32>,
     pub let_f4_fullscreen: Bool32,
     pub let_f1_help: Bool32,
     pub let_escape_end_game: Bool32,
@@ -62,18 +63,20 @@ pub struct GameSettings {
 }
 
 #[repr(u32)]
-#[derive(Copy, Clone, Eq, PartialEq, Debug, NomLE)]
+#[derive(Copy, Clone, Default, Eq, PartialEq, Debug, NomLE)]
 #[nom(GenericErrors)]
 pub enum ColorDepth {
+    #[default]
     NoChange = 0,
     _16 = 1,
     _32 = 2,
 }
 
 #[repr(u32)]
-#[derive(Copy, Clone, Eq, PartialEq, Debug, NomLE)]
+#[derive(Copy, Clone, Default, Eq, PartialEq, Debug, NomLE)]
 #[nom(GenericErrors)]
 pub enum Resolution {
+    #[default]
     NoChange = 0,
     _640x480 = 1,
     _800x600 = 2,
@@ -83,9 +86,10 @@ pub enum Resolution {
 }
 
 #[repr(u32)]
-#[derive(Copy, Clone, Eq, PartialEq, Debug, NomLE)]
+#[derive(Copy, Clone, Default, Eq, PartialEq, Debug, NomLE)]
 #[nom(GenericErrors)]
 pub enum Frequency {
+    #[default]
     NoChange = 0,
     _60 = 1,
     _70 = 2,
@@ -95,24 +99,26 @@ pub enum Frequency {
 }
 
 #[repr(u32)]
-#[derive(Copy, Clone, Eq, PartialEq, Debug, NomLE)]
+#[derive(Copy, Clone, Default, Eq, PartialEq, Debug, NomLE)]
 #[nom(GenericErrors)]
 pub enum Priority {
+    #[default]
     Normal,
     High,
     Highest,
 }
 
 #[repr(u32)]
-#[derive(Copy, Clone, Eq, PartialEq, Debug, NomLE)]
+#[derive(Copy, Clone, Default, Eq, PartialEq, Debug, NomLE)]
 #[nom(GenericErrors)]
 pub enum ProgressBar {
+    #[default]
     None,
     Default,
     Custom,
 }
 
-#[derive(Debug, NomLE)]
+#[derive(Clone, Debug, PartialEq, NomLE)]
 #[nom(GenericErrors)]
 pub struct Constant {
     pub name: String32,
@@ -0,0 +1,61 @@
+use std::ops::Range;
+
+use crate::*;
+
+/// Byte ranges of each resource item in the decrypted data, in the same order as the
+/// chunk items. Each range starts at the item's "present" flag, so deleted items are
+/// just the flag, and ranges can be parsed with the same `Option<ResourceItem<T>>`
+/// format as the file.
+#[derive(Debug, Default)]
+pub struct ResourceSpans {
+    pub sounds: Vec<Range<usize>>,
+    pub sprites: Vec<Range<usize>>,
+    pub backgrounds: Vec<Range<usize>>,
+    pub paths: Vec<Range<usize>>,
+    pub scripts: Vec<Range<usize>>,
+    pub fonts: Vec<Range<usize>>,
+    pub timelines: Vec<Range<usize>>,
+    pub objects: Vec<Range<usize>>,
+    pub rooms: Vec<Range<usize>>,
+}
+
+/// Like [`parse()`], but also returns where each resource is in the decrypted data,
+/// which is returned last.
+pub fn parse_with_spans(path: impl AsRef<std::path::Path>) -> (Content, ResourceSpans, Vec<u8>) {
+    let decrypted = Decrypted::read(path);
+    let mut reader = decrypted.reader();
+    let content = reader.parse_content();
+    let spans = reader.into_spans();
+    (content, spans, decrypted.data)
+}
+
+#[cfg(test)]
+mod tests {
+    use nom::error::VerboseError;
+
+    use super::*;
+
+    #[test]
+    #[ignore]
+    fn test_parse_with_spans() {
+        let (content, spans, data) = parse_with_spans(crate::tests::FIXTURE);
+        // spans don't change what's parsed
+        assert!(content == parse(crate::tests::FIXTURE));
+        assert_eq!(spans.scripts.len(), content.scripts.items.len());
+        assert_eq!(spans.rooms.len(), content.rooms.items.len());
+
+        for (span, item) in spans.scripts.iter().zip(&content.scripts.items) {
+            let (rest, reparsed) =
+                parse_cond32::<ResourceItem<Script>, VerboseError<_>>(&data[span.clone()]).unwrap();
+            assert!(rest.is_empty());
+            match (reparsed, item) {
+                (Some(reparsed), Some(item)) => {
+                    assert_eq!(reparsed.name.0, item.name.0);
+                    assert_eq!(reparsed.data.script.0, item.data.script.0);
+                }
+                (None, None) => {}
+                _ => panic!("span at {span:?} parsed differently"),
+            }
+        }
+    }
+}
@@ -0,0 +1,167 @@
+//! Checks for references a game would trip over when it runs, without running it.
+
+use crate::{Content, EventId, ObjectParentCycle, ResourceChunk};
+
+/// A problem [`Content::validate()`] found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    ObjectParentCycle(ObjectParentCycle),
+    /// `from` refers to a `kind` of resource at `index`, which doesn't exist or was deleted.
+    MissingResource {
+        from: String,
+        kind: &'static str,
+        index: i32,
+    },
+}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ObjectParentCycle(error) => error.fmt(f),
+            Self::MissingResource { from, kind, index } => {
+                write!(f, "{from} refers to missing {kind} {index}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+impl Content {
+    /// Parent loops, and indices of resources that don't exist. Negative indices mean "none",
+    /// so aren't errors.
+    pub fn validate(&self) -> Vec<ValidationError> {
+        let mut errors = vec![];
+
+        for (index, name, object) in &self.objects {
+            let from = || format!("object {name}");
+            errors.extend(check(&self.sprites, "sprite", object.sprite_index, from));
+            errors.extend(check(
+                &self.sprites,
+                "sprite",
+                object.mask_sprite_index,
+                from,
+            ));
+            let parent = object.parent_object_index;
+            errors.extend(check(&self.objects, "object", parent, from));
+            for id in object.events.keys() {
+                if let &EventId::Collision(other) = id {
+                    errors.extend(check(&self.objects, "object", other, from));
+                }
+            }
+            if let Some(Err(error)) = self.object_parents(index).find(Result::is_err) {
+                errors.push(ValidationError::ObjectParentCycle(error));
+            }
+        }
+
+        for (_, name, room) in &self.rooms {
+            let from = || format!("room {name}");
+            for background in &room.backgrounds {
+                let index = background.background_image_index;
+                errors.extend(check(&self.backgrounds, "background", index, from));
+            }
+            for view in &room.views {
+                errors.extend(check(&self.objects, "object", view.following_index, from));
+            }
+            for instance in &room.instances {
+                let index = instance.object_index as i32;
+                errors.extend(check(&self.objects, "object", index, from));
+            }
+            for tile in &room.tiles {
+                let index = tile.background_index as i32;
+                errors.extend(check(&self.backgrounds, "background", index, from));
+            }
+        }
+
+        for &index in &self.room_order.items {
+            let from = || "the room order".to_string();
+            errors.extend(check(&self.rooms, "room", index as i32, from));
+        }
+
+        errors
+    }
+}
+
+/// An error from `from` if `index` isn't "none", and isn't in `chunk`.
+fn check<T>(
+    chunk: &ResourceChunk<T>,
+    kind: &'static str,
+    index: i32,
+    from: impl FnOnce() -> String,
+) -> Option<ValidationError> {
+    let missing = u32::try_from(index).is_ok_and(|index| chunk.get_item(index).is_none());
+    missing.then(|| ValidationError::MissingResource {
+        from: from(),
+        kind,
+        index,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Bool32, Pair, ResourceItem, Room, RoomInstance, String32};
+
+    #[test]
+    fn test_validate() {
+        let mut content = Content {
+            objects: crate::tests::objects(&[-1, 2, 1, 9]),
+            ..Default::default()
+        };
+        let player = &mut content.objects.items[0].as_mut().unwrap().data;
+        player.sprite_index = 3;
+        let event = crate::Event {
+            ver: 400,
+            actions: vec![],
+        };
+        player.events.insert(EventId::Collision(7), event);
+        let instance = RoomInstance {
+            pos: Pair(0, 0),
+            object_index: 5,
+            id: 100001,
+            creation_code: String32::default(),
+            locked: Bool32::False,
+        };
+        let room = Room {
+            instances: vec![instance],
+            ..Default::default()
+        };
+        content.rooms.items.push(Some(ResourceItem {
+            name: String32("rm_test".to_string()),
+            data: room,
+        }));
+        content.room_order.items = vec![0, 1];
+
+        let missing = |from: &str, kind, index| ValidationError::MissingResource {
+            from: from.to_string(),
+            kind,
+            index,
+        };
+        assert_eq!(
+            content.validate(),
+            [
+                missing("object obj_0", "sprite", 3),
+                missing("object obj_0", "object", 7),
+                ValidationError::ObjectParentCycle(ObjectParentCycle { index: 1 }),
+                ValidationError::ObjectParentCycle(ObjectParentCycle { index: 2 }),
+                missing("object obj_3", "object", 9),
+                missing("room rm_test", "object", 5),
+                missing("the room order", "room", 1),
+            ]
+        );
+
+        // deleted resources leave a hole, which is as missing as past the end
+        content.objects.items[1] = None;
+        content.objects.items[2] = None;
+        content.objects.items[3] = None;
+        content.room_order.items = vec![0];
+        assert_eq!(
+            content.validate(),
+            [
+                missing("object obj_0", "sprite", 3),
+                missing("object obj_0", "object", 7),
+                missing("room rm_test", "object", 5),
+            ]
+        );
+    }
+}
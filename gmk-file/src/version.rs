@@ -0,0 +1,108 @@
+/// The Game Maker release that wrote a file, inferred from the file header version.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum GmVersion {
+    Gm53,
+    Gm60,
+    #[default]
+    Gm70,
+    Gm80,
+    Gm81,
+    /// A file header version no release is known to write. The chunks have their own
+    /// versions, so the file may still parse.
+    Unknown(u32),
+}
+
+impl GmVersion {
+    pub fn from_file_version(ver: u32) -> Self {
+        match ver {
+            530 => Self::Gm53,
+            600 => Self::Gm60,
+            701 | 702 => Self::Gm70,
+            800 => Self::Gm80,
+            810 => Self::Gm81,
+            ver => Self::Unknown(ver),
+        }
+    }
+
+    /// The file header version, like `GM_version`: 530, 600, 700, 800 or 810.
+    pub fn number(self) -> u32 {
+        match self {
+            Self::Gm53 => 530,
+            Self::Gm60 => 600,
+            Self::Gm70 => 700,
+            Self::Gm80 => 800,
+            Self::Gm81 => 810,
+            Self::Unknown(ver) => ver,
+        }
+    }
+
+    /// Sprites and backgrounds have smooth edges and preload texture flags.
+    pub fn supports_smooth_edges(self) -> bool {
+        matches!(self, Self::Gm70 | Self::Gm80 | Self::Gm81)
+    }
+
+    /// Backgrounds have tiling settings.
+    pub fn supports_background_tiling(self) -> bool {
+        matches!(self, Self::Gm70 | Self::Gm80 | Self::Gm81)
+    }
+
+    /// Sprites and backgrounds have video memory and load on demand flags.
+    pub fn supports_video_memory(self) -> bool {
+        matches!(self, Self::Gm53 | Self::Gm60)
+    }
+
+    /// Game information has the "show in separate window" flag.
+    pub fn supports_help_window(self) -> bool {
+        matches!(self, Self::Gm80 | Self::Gm81)
+    }
+}
+
+// Chunk versions used to branch parsing. These are per-resource, so they don't map
+// directly onto a GmVersion, but are named here so the conditions are readable.
+
+pub const GM5_SOUND_VER: u32 = 440;
+pub const GM6_SOUND_VER: u32 = 600;
+
+pub const GM6_SPRITE_VER: u32 = 400;
+pub const GM7_SPRITE_VER: u32 = 542;
+
+pub const GM6_BACKGROUND_VER: u32 = 400;
+pub const GM7_BACKGROUND_VER: u32 = 543;
+
+pub const GM5_ROOM_VER: u32 = 520;
+pub const GM6_ROOM_VER: u32 = 541;
+
+pub const GM7_SETTINGS_VER: u32 = 702;
+pub const GM8_SETTINGS_VER: u32 = 800;
+
+pub const GM7_INFORMATION_VER: u32 = 600;
+pub const GM8_INFORMATION_VER: u32 = 800;
+
+#[cfg(test)]
+mod tests {
+    use super::GmVersion;
+
+    #[test]
+    fn test_from_file_version() {
+        assert_eq!(GmVersion::from_file_version(530), GmVersion::Gm53);
+        assert_eq!(GmVersion::from_file_version(600), GmVersion::Gm60);
+        assert_eq!(GmVersion::from_file_version(702), GmVersion::Gm70);
+        assert_eq!(GmVersion::from_file_version(810), GmVersion::Gm81);
+        assert_eq!(GmVersion::from_file_version(123), GmVersion::Unknown(123));
+        assert_eq!(GmVersion::Gm70.number(), 700);
+        assert_eq!(GmVersion::Unknown(123).number(), 123);
+
+        assert!(GmVersion::Gm70.supports_smooth_edges());
+        assert!(!GmVersion::Gm60.supports_smooth_edges());
+        assert!(!GmVersion::Gm70.supports_help_window());
+        assert!(!GmVersion::Unknown(123).supports_smooth_edges());
+    }
+
+    #[test]
+    #[ignore]
+    fn test_iji_version() {
+        let content = crate::parse(crate::tests::FIXTURE);
+        assert_eq!(content.version(), GmVersion::Gm70);
+    }
+}
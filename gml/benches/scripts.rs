@@ -0,0 +1,57 @@
+use std::rc::Rc;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use gml::eval::{Context, Namespace, ObjectId, SimpleGlobal};
+
+const PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../ref/source code/iji.gmk");
+
+/// Parses every script resource, like `define_scripts()` but on one thread so the
+/// results are comparable between machines.
+fn parse_scripts(c: &mut Criterion) {
+    if !std::path::Path::new(PATH).exists() {
+        eprintln!("skipping, {PATH} not found");
+        return;
+    }
+    let content = gmk_file::parse(PATH);
+    c.bench_function("parse all scripts", |b| {
+        b.iter(|| {
+            for (_, name, script) in &content.scripts {
+                black_box(gml::parse(name, &script.script.0).unwrap());
+            }
+        })
+    });
+}
+
+/// Movement-style code like the step events run for every instance each frame: locals,
+/// instance members, arithmetic, branches and builtin calls.
+const STEP_SCRIPT: &str = r#"
+var i, total;
+total = 0;
+for (i = 0; i < 10; i += 1) {
+    hspeed += 0.5;
+    if (abs(hspeed) > 4) hspeed = 4 * (hspeed / abs(hspeed));
+    x += hspeed;
+    if (x > 320 || x < 0) { hspeed = -hspeed; }
+    total += x mod 16;
+}
+return total;
+"#;
+
+fn eval_script(c: &mut Criterion) {
+    let script = gml::parse("step", STEP_SCRIPT).unwrap();
+    let global = SimpleGlobal::default();
+    let instance = Rc::<Namespace>::default();
+    instance.insert("x", 0);
+    instance.insert("hspeed", 0);
+    let mut context = Context::new(&global, ObjectId(100000), instance);
+    c.bench_function("eval step script x100", |b| {
+        b.iter(|| {
+            for _ in 0..100 {
+                black_box(context.exec_script(&script, &[]).unwrap());
+            }
+        })
+    });
+}
+
+criterion_group!(benches, parse_scripts, eval_script);
+criterion_main!(benches);
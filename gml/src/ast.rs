@@ -1,11 +1,15 @@
 pub use assign::{Assign, AssignOp};
-pub use expr::{BinaryOp, Expr, UnaryOp};
+pub use expr::{BinaryOp, Builtin, Expr, UnaryOp};
 pub use pos::Pos;
 pub use script::Script;
 pub use stmt::Stmt;
 pub use var::Var;
 pub use visitor::Visitor;
 
+/// Bump when a change to the AST or parser would parse a script differently, so saved parses
+/// are discarded.
+pub const VERSION: u32 = 1;
+
 mod assign;
 mod expr;
 mod pos;
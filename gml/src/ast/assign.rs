@@ -1,10 +1,10 @@
 use std::fmt::{Display, Formatter};
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use super::{Expr, Visitor};
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Assign {
     pub lhs: Box<Expr>,
     pub op: AssignOp,
@@ -26,7 +26,7 @@ impl Display for Assign {
         write!(f, "{} {} {}", self.lhs, self.op, self.rhs)
     }
 }
-#[derive(Copy, Clone, Debug, Serialize)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum AssignOp {
     Assign,
     AddAssign,
@@ -1,9 +1,9 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter};
 
 use super::{Pos, Var, Visitor};
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum Expr {
     Var(Var),
     Int(i32),
@@ -38,6 +38,8 @@ pub enum Expr {
         pos: Pos,
         name: String,
         args: Vec<Box<Expr>>,
+        /// Set if `name` is one the evaluator runs itself.
+        builtin: Option<Builtin>,
     },
 }
 
@@ -91,12 +93,40 @@ impl Display for Expr {
             Expr::Member { lhs, name } => write!(f, "{lhs}.{name}"),
             Expr::Index { lhs, indices } => write!(f, "{lhs}[{}]", CommaSep(&indices)),
             Expr::InstanceProperty { id, name: member } => write!(f, "({id}).{member}"),
-            Expr::Call { pos: _, name, args } => write!(f, "{name}({})", CommaSep(&args)),
+            Expr::Call { name, args, .. } => write!(f, "{name}({})", CommaSep(&args)),
         }
     }
 }
 
-#[derive(Copy, Clone, Debug, Serialize)]
+/// Pure functions common enough in step events that the evaluator runs them directly,
+/// instead of through `Global::call()`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Builtin {
+    Abs,
+    Floor,
+    Min,
+    Max,
+    Clamp,
+    Power,
+    Sqr,
+}
+
+impl Builtin {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "abs" => Some(Self::Abs),
+            "floor" => Some(Self::Floor),
+            "min" => Some(Self::Min),
+            "max" => Some(Self::Max),
+            "clamp" => Some(Self::Clamp),
+            "power" => Some(Self::Power),
+            "sqr" => Some(Self::Sqr),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum UnaryOp {
     Not,
     Pos,
@@ -123,7 +153,7 @@ impl Display for UnaryOp {
     }
 }
 
-#[derive(Copy, Clone, Debug, Serialize)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum BinaryOp {
     And,
     Or,
@@ -137,6 +167,8 @@ pub enum BinaryOp {
     Gt,
     Ne,
     Eq,
+    /// `=` used as a comparison, usually meant to be `==`.
+    AssignEq,
     Add,
     Sub,
     Mul,
@@ -160,6 +192,7 @@ impl Display for BinaryOp {
             BinaryOp::Gt => ">",
             BinaryOp::Ne => "!=",
             BinaryOp::Eq => "==",
+            BinaryOp::AssignEq => "=",
             BinaryOp::Add => "+",
             BinaryOp::Sub => "-",
             BinaryOp::Mul => "*",
@@ -1,10 +1,11 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use super::{Assign, Expr, Pos, Visitor};
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum Stmt {
-    Var(String),
+    /// `var a, b = 1;` declares locals, with optional initial values.
+    Var(Vec<(String, Option<Box<Expr>>)>),
     Assign {
         pos: Pos,
         assign: Assign,
@@ -19,14 +20,17 @@ pub enum Stmt {
         alt: Option<Box<Stmt>>,
     },
     Repeat {
+        pos: Pos,
         count: Box<Expr>,
         body: Box<Stmt>,
     },
     While {
+        pos: Pos,
         cond: Box<Expr>,
         body: Box<Stmt>,
     },
     For {
+        pos: Pos,
         assign: Assign,
         cond: Box<Expr>,
         update: Assign,
@@ -36,10 +40,15 @@ pub enum Stmt {
         obj: Box<Expr>,
         body: Box<Stmt>,
     },
+    /// `return;` returns undefined.
     Return {
-        expr: Box<Expr>,
+        expr: Option<Box<Expr>>,
     },
     Exit,
+    /// Ends the innermost loop or `with`.
+    Break,
+    /// Goes to the next iteration of the innermost loop or `with`.
+    Continue,
     Block {
         stmts: Vec<Box<Stmt>>,
     },
@@ -47,12 +56,30 @@ pub enum Stmt {
 }
 
 impl Stmt {
+    /// Where the statement starts, if the parser records it for this kind of statement.
+    pub fn pos(&self) -> Option<Pos> {
+        match self {
+            Self::Assign { pos, .. }
+            | Self::Expr { pos, .. }
+            | Self::Repeat { pos, .. }
+            | Self::While { pos, .. }
+            | Self::For { pos, .. } => Some(*pos),
+            _ => None,
+        }
+    }
+
     pub fn visit<V: Visitor>(&self, visitor: &mut V) {
         if !visitor.stmt(self) {
             return;
         }
         match self {
-            Self::Var(_) => {}
+            Self::Var(decls) => {
+                for (_, init) in decls {
+                    if let Some(init) = init {
+                        init.visit(visitor);
+                    }
+                }
+            }
             Self::Assign { assign, .. } => {
                 assign.visit(visitor);
             }
@@ -66,11 +93,11 @@ impl Stmt {
                     alt.visit(visitor);
                 }
             }
-            Self::Repeat { count, body } => {
+            Self::Repeat { count, body, .. } => {
                 count.visit(visitor);
                 body.visit(visitor);
             }
-            Self::While { cond, body } => {
+            Self::While { cond, body, .. } => {
                 cond.visit(visitor);
                 body.visit(visitor);
             }
@@ -79,6 +106,7 @@ impl Stmt {
                 cond,
                 update,
                 body,
+                ..
             } => {
                 assign.visit(visitor);
                 cond.visit(visitor);
@@ -90,9 +118,11 @@ impl Stmt {
                 body.visit(visitor);
             }
             Self::Return { expr } => {
-                expr.visit(visitor);
+                if let Some(expr) = expr {
+                    expr.visit(visitor);
+                }
             }
-            Self::Exit => {}
+            Self::Exit | Self::Break | Self::Continue => {}
             Self::Block { stmts } => {
                 for stmt in stmts {
                     stmt.visit(visitor);
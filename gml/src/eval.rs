@@ -7,6 +7,10 @@ use thiserror::Error;
 
 use super::ast;
 
+pub use simple_global::SimpleGlobal;
+
+mod simple_global;
+
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("{0}\n  at {}:{}", .1.line, .1.column)]
@@ -17,13 +21,19 @@ pub enum Error {
     Custom(String),
     #[error("unexpected exit")]
     Exit,
+    #[error("unexpected break")]
+    Break,
+    #[error("unexpected continue")]
+    Continue,
+    #[error("{0} outside of a loop")]
+    OutsideLoop(&'static str),
     #[error("unexpected return {0:?}")]
     Return(Value),
     #[error("attempted to assign to value expression")]
     AssignToValue,
     #[error("function {0:?} has no definition")]
     UndefinedFunction(String),
-    #[error("invalid operands {0} {0}")]
+    #[error("invalid operands {0} {1}")]
     InvalidOperands(Value, Value),
     #[error("invalid bool {0}")]
     InvalidBool(Value),
@@ -41,6 +51,16 @@ pub enum Error {
     InvalidCount(Value),
     #[error("invalid condition {0:?}")]
     InvalidCondition(Value),
+    #[error("implicit conversion of {0:?} in strict mode")]
+    StrictConversion(Value),
+    #[error("loop exceeded the iteration limit of {0}")]
+    IterationLimit(u32),
+    #[error("negative array index {0}")]
+    NegativeIndex(i32),
+    #[error("array index {index} is over the limit of {limit}")]
+    IndexLimit { index: usize, limit: usize },
+    #[error("division by zero")]
+    DivisionByZero,
 }
 
 pub type Result<T = (), E = Error> = std::result::Result<T, E>;
@@ -80,6 +100,7 @@ impl PartialEq for Value {
             (Self::Bool(lhs), Self::Bool(rhs)) => lhs == rhs,
             (Self::Int(lhs), Self::Int(rhs)) => lhs == rhs,
             (Self::Float(lhs), rhs) => lhs == &rhs.to_float(),
+            (lhs, Self::Float(rhs)) => &lhs.to_float() == rhs,
             _ => false,
         }
     }
@@ -93,6 +114,7 @@ impl PartialOrd for Value {
             (Self::Bool(lhs), Self::Bool(rhs)) => lhs.partial_cmp(rhs),
             (Self::Int(lhs), Self::Int(rhs)) => lhs.partial_cmp(rhs),
             (Self::Float(lhs), rhs) => lhs.partial_cmp(&rhs.to_float()),
+            (lhs, Self::Float(rhs)) => lhs.to_float().partial_cmp(rhs),
             _ => None,
         }
     }
@@ -167,7 +189,7 @@ impl Value {
             Self::Bool(value) => *value as i32,
             Self::Int(value) => *value,
             Self::Float(value) => *value as i32,
-            Self::String(value) => value.parse().unwrap_or_default(),
+            Self::String(value) => parse_number_prefix(value) as i32,
         }
     }
 
@@ -177,7 +199,7 @@ impl Value {
             Self::Bool(value) => *value as i32 as f64,
             Self::Int(value) => *value as f64,
             Self::Float(value) => *value,
-            Self::String(value) => value.parse().unwrap_or_default(),
+            Self::String(value) => parse_number_prefix(value),
         }
     }
 
@@ -239,12 +261,20 @@ impl From<ObjectId> for Value {
     }
 }
 
+/// GM stores all numbers as doubles, so integer results too big for an `i32` become floats
+/// rather than wrapping.
+fn int_or_float(value: Option<i32>, float: impl FnOnce() -> f64) -> Value {
+    value.map_or_else(|| float().into(), Value::from)
+}
+
 impl std::ops::Add for Value {
     type Output = Result<Value>;
 
     fn add(self, rhs: Self) -> Self::Output {
         match (self, rhs) {
-            (Self::Int(lhs), Self::Int(rhs)) => Ok((lhs + rhs).into()),
+            (Self::Int(lhs), Self::Int(rhs)) => Ok(int_or_float(lhs.checked_add(rhs), || {
+                f64::from(lhs) + f64::from(rhs)
+            })),
             (lhs, Self::String(value)) => Ok((lhs.to_str() + &value).into()),
             (lhs @ Self::String(_), rhs) => Err(Error::InvalidOperands(lhs, rhs)),
             (lhs, rhs) => Ok((lhs.to_float() + rhs.to_float()).into()),
@@ -257,7 +287,9 @@ impl std::ops::Sub for Value {
 
     fn sub(self, rhs: Self) -> Self::Output {
         match (self, rhs) {
-            (Self::Int(lhs), Self::Int(rhs)) => Ok((lhs - rhs).into()),
+            (Self::Int(lhs), Self::Int(rhs)) => Ok(int_or_float(lhs.checked_sub(rhs), || {
+                f64::from(lhs) - f64::from(rhs)
+            })),
             (lhs, rhs @ Self::String(_)) | (lhs @ Self::String(_), rhs) => {
                 Err(Error::InvalidOperands(lhs, rhs))
             }
@@ -271,7 +303,9 @@ impl std::ops::Mul for Value {
 
     fn mul(self, rhs: Self) -> Self::Output {
         match (self, rhs) {
-            (Self::Int(lhs), Self::Int(rhs)) => Ok((lhs * rhs).into()),
+            (Self::Int(lhs), Self::Int(rhs)) => Ok(int_or_float(lhs.checked_mul(rhs), || {
+                f64::from(lhs) * f64::from(rhs)
+            })),
             (lhs @ Self::Int(_) | lhs @ Self::Float(_), Self::String(rhs)) => {
                 let count = lhs.to_int().try_into().unwrap_or_default();
                 Ok(rhs.repeat(count).into())
@@ -285,12 +319,13 @@ impl std::ops::Mul for Value {
 impl std::ops::Div for Value {
     type Output = Result<Value>;
 
+    /// Always a float like GM, even for ints, use `div` for integer division.
     fn div(self, rhs: Self) -> Self::Output {
         match (self, rhs) {
-            (Self::Int(lhs), Self::Int(rhs)) => Ok((lhs / rhs).into()),
             (lhs @ Self::String(_), rhs) | (lhs, rhs @ Self::String(_)) => {
                 Err(Error::InvalidOperands(lhs, rhs))
             }
+            (_, rhs) if rhs.to_float() == 0.0 => Err(Error::DivisionByZero),
             (lhs, rhs) => Ok((lhs.to_float() / rhs.to_float()).into()),
         }
     }
@@ -301,15 +336,28 @@ impl std::ops::Rem for Value {
 
     fn rem(self, rhs: Self) -> Self::Output {
         match (self, rhs) {
-            (Self::Int(lhs), Self::Int(rhs)) => Ok((lhs % rhs).into()),
             (lhs @ Self::String(_), rhs) | (lhs, rhs @ Self::String(_)) => {
                 Err(Error::InvalidOperands(lhs, rhs))
             }
+            (_, rhs) if rhs.to_float() == 0.0 => Err(Error::DivisionByZero),
+            // only `i32::MIN mod -1` overflows
+            (Self::Int(lhs), Self::Int(rhs)) => Ok(lhs.checked_rem(rhs).unwrap_or(0).into()),
             (lhs, rhs) => Ok((lhs.to_float() % rhs.to_float()).into()),
         }
     }
 }
 
+/// `lhs div rhs`, truncating towards zero.
+fn int_div(lhs: i32, rhs: i32) -> Result<Value> {
+    if rhs == 0 {
+        return Err(Error::DivisionByZero);
+    }
+    // only `i32::MIN div -1` overflows
+    Ok(int_or_float(lhs.checked_div(rhs), || {
+        f64::from(lhs) / f64::from(rhs)
+    }))
+}
+
 #[derive(Debug)]
 pub enum Place {
     Value(Value),
@@ -362,13 +410,36 @@ pub trait Global: 'static {
 
     fn set(&self, name: &str, value: Value) -> Result;
 
-    fn instances_all(&self, id: ObjectId) -> Vec<Rc<dyn Object>>;
+    /// The instances `with (id)` runs for: every instance of an object, or just `id` if it's
+    /// an instance.
+    fn instance_ids(&self, id: ObjectId) -> Vec<ObjectId>;
 
     fn instance(&self, id: ObjectId) -> Option<Rc<dyn Object>>;
 
     fn new_instance(&self, object: Rc<dyn Object>) -> ObjectId;
 
     fn call(&self, context: &mut Context<'_>, id: &str, args: Vec<Value>) -> Result<Value>;
+
+    /// Whether new contexts should error on implicit string conversions, see [`Context::strict`].
+    fn strict(&self) -> bool {
+        false
+    }
+
+    /// The most times a loop may run before erroring, see [`Context::iteration_limit`].
+    fn iteration_limit(&self) -> Option<u32> {
+        None
+    }
+
+    /// Whether `name` is a built-in global variable like `room` or `score`, which unprefixed
+    /// assignments set instead of an instance variable.
+    fn builtin_var(&self, name: &str) -> bool {
+        false
+    }
+
+    /// The length arrays can grow to, see [`Context::array_len_limit`].
+    fn array_len_limit(&self) -> usize {
+        DEFAULT_ARRAY_LEN_LIMIT
+    }
 }
 
 #[allow(unused_variables)]
@@ -389,6 +460,12 @@ pub trait Object: 'static {
         None
     }
 
+    /// The keys and values of indexed objects, for debugging. Unlike
+    /// [`Self::debug_index_length()`], keys don't have to be a range of numbers.
+    fn debug_entries(&self) -> Option<Vec<(String, Value)>> {
+        None
+    }
+
     fn index(&self, args: &[Value]) -> Result<Option<Value>> {
         Ok(None)
     }
@@ -396,6 +473,11 @@ pub trait Object: 'static {
     fn set_index(&self, args: &[Value], value: Value) -> Result {
         Ok(())
     }
+
+    /// For built-in objects that are used as their concrete type, like data structures.
+    fn as_any(&self) -> Option<&dyn std::any::Any> {
+        None
+    }
 }
 
 #[derive(Default, Serialize)]
@@ -411,6 +493,13 @@ impl Namespace {
     pub fn insert(&self, name: impl Into<String>, value: impl Into<Value>) {
         self.vars.borrow_mut().insert(name.into(), value.into());
     }
+
+    /// A new namespace with a copy of the current values.
+    pub fn clone_values(&self) -> Self {
+        Self {
+            vars: RefCell::new(self.vars.borrow().clone()),
+        }
+    }
 }
 
 impl std::fmt::Debug for Namespace {
@@ -434,9 +523,28 @@ impl Object for Namespace {
     }
 }
 
-#[derive(Default)]
+/// The default [`Global::array_len_limit`], the same as GM 8's.
+pub const DEFAULT_ARRAY_LEN_LIMIT: usize = 32000;
+
 pub struct Array {
     items: RefCell<Vec<Value>>,
+    /// Setting an index at or past this errors rather than growing the array.
+    len_limit: usize,
+}
+
+impl Array {
+    pub fn new(len_limit: usize) -> Self {
+        Self {
+            items: RefCell::default(),
+            len_limit,
+        }
+    }
+}
+
+impl Default for Array {
+    fn default() -> Self {
+        Self::new(DEFAULT_ARRAY_LEN_LIMIT)
+    }
 }
 
 impl Object for Array {
@@ -444,6 +552,11 @@ impl Object for Array {
         Some(self.items.borrow().len())
     }
 
+    fn debug_entries(&self) -> Option<Vec<(String, Value)>> {
+        let items = self.items.borrow();
+        Some(index_entries(items.iter().cloned()))
+    }
+
     fn index(&self, args: &[Value]) -> Result<Option<Value>> {
         let index = args.get(0).cloned().unwrap_or_default().to_int();
         Ok(index
@@ -453,9 +566,16 @@ impl Object for Array {
     }
 
     fn set_index(&self, args: &[Value], value: Value) -> Result {
-        let Ok(index) = args.get(0).cloned().unwrap_or_default().to_int().try_into() else {
-            return Ok(());
+        let index = args.get(0).cloned().unwrap_or_default().to_int();
+        let Ok(index) = usize::try_from(index) else {
+            return Err(Error::NegativeIndex(index));
         };
+        if index >= self.len_limit {
+            return Err(Error::IndexLimit {
+                index,
+                limit: self.len_limit,
+            });
+        }
         let mut items = self.items.borrow_mut();
 
         if items.len() <= index {
@@ -466,6 +586,15 @@ impl Object for Array {
     }
 }
 
+/// [`Object::debug_entries()`] for a list of values.
+pub fn index_entries(values: impl IntoIterator<Item = Value>) -> Vec<(String, Value)> {
+    values
+        .into_iter()
+        .enumerate()
+        .map(|(index, value)| (index.to_string(), value))
+        .collect()
+}
+
 type FunctionImpl = dyn Fn(&mut Context, Vec<Value>) -> Result<Value>;
 
 pub struct Function(Rc<FunctionImpl>);
@@ -482,11 +611,43 @@ impl Clone for Function {
     }
 }
 
+/// What [`Context::trace`] is called with.
+#[derive(Clone, Copy, Debug)]
+pub enum Trace<'s> {
+    /// Before running a statement. `pos` is `None` for statements the parser doesn't record a
+    /// position for, like blocks and `if`.
+    Stmt {
+        pos: Option<ast::Pos>,
+        stmt: &'s ast::Stmt,
+    },
+    /// Before evaluating the arguments of a call.
+    Call { pos: ast::Pos, name: &'s str },
+}
+
+pub type TraceFn = Rc<dyn Fn(Trace<'_>)>;
+
 pub struct Context<'a> {
     pub global: &'a dyn Global,
     pub instance_id: ObjectId,
     pub instance: Rc<dyn Object>,
+    /// The instance `other` refers to: the instance that ran `with`, or the instance
+    /// collided with in a collision event. Initially the same as `instance_id`.
+    pub other_id: ObjectId,
     pub locals: Namespace,
+    /// Error instead of silently converting strings to numbers, to help find type bugs.
+    pub strict: bool,
+    /// Error if a single loop runs more than this many times, instead of hanging on an
+    /// accidental infinite loop. Unlimited if `None`.
+    pub iteration_limit: Option<u32>,
+    /// The length arrays created by assigning to an index can grow to, so a bad index
+    /// errors instead of allocating huge arrays.
+    pub array_len_limit: usize,
+    /// The GM event type and number of the event being run, read by `event_type` and
+    /// `event_number`.
+    pub event: Option<(i32, i32)>,
+    /// Called before each statement and call if set, so a debugger can log or step through
+    /// scripts.
+    pub trace: Option<TraceFn>,
 }
 
 impl<'a> Context<'a> {
@@ -495,11 +656,30 @@ impl<'a> Context<'a> {
             global,
             instance_id,
             instance,
+            other_id: instance_id,
             locals: Namespace::default(),
+            strict: global.strict(),
+            iteration_limit: global.iteration_limit(),
+            array_len_limit: global.array_len_limit(),
+            event: None,
+            trace: None,
+        }
+    }
+
+    /// Replaces `self` and `other` with the ids they refer to.
+    pub fn resolve_id(&self, id: ObjectId) -> ObjectId {
+        match id {
+            ObjectId::SELF => self.instance_id,
+            ObjectId::OTHER => self.other_id,
+            id => id,
         }
     }
 
     fn instance(&self, id: ObjectId) -> Result<Rc<dyn Object>> {
+        let id = self.resolve_id(id);
+        if id == self.instance_id {
+            return Ok(self.instance.clone());
+        }
         self.global
             .instance(id)
             .ok_or(Error::InvalidObject(id.into()))
@@ -537,6 +717,15 @@ impl<'a> Context<'a> {
                 if let Some(value) = self.locals.member(id)? {
                     return Ok(value);
                 }
+                // arguments that weren't passed
+                if is_argument(id) {
+                    return Ok(0.into());
+                }
+                match (id.as_str(), self.event) {
+                    ("event_type", Some((event_type, _))) => return Ok(event_type.into()),
+                    ("event_number", Some((_, event_number))) => return Ok(event_number.into()),
+                    _ => {}
+                }
                 if let Some(value) = self.instance.member(id)? {
                     return Ok(value);
                 }
@@ -550,15 +739,18 @@ impl<'a> Context<'a> {
     }
 
     pub fn set_var(&mut self, var: &ast::Var, value: Value) -> Result {
-        // Local sets script local if it exists, otherwise it sets on active instance.
-        // It will not fall back to a global.
+        // Local sets script local if it exists, otherwise it sets on active instance, which
+        // in a `with` body is the target, like GM. It will not fall back to a global, other
+        // than built-in globals.
         match var {
             ast::Var::Global(id) => {
                 self.global.set(id, value)?;
             }
             ast::Var::Local(id) => {
-                if self.locals.get(id).is_some() {
+                if self.locals.get(id).is_some() || is_argument(id) {
                     self.locals.set_member(id, value)?;
+                } else if self.global.builtin_var(id) {
+                    self.global.set(id, value)?;
                 } else {
                     self.instance.set_member(id, value)?;
                 }
@@ -576,6 +768,10 @@ impl<'a> Context<'a> {
                 let object = self.get(*id, name)?;
                 Ok(object.unwrap_or_default())
             }
+            Place::Index(lhs, indices) if is_argument_array(lhs) => match argument_var(indices) {
+                Some(var) => self.var(&var),
+                None => Ok(0.into()),
+            },
             Place::Index(lhs, indices) => {
                 let lhs = self.place_value(lhs)?;
                 match lhs {
@@ -608,12 +804,19 @@ impl<'a> Context<'a> {
             Place::Property(id, name) => {
                 self.set(*id, name, value)?;
             }
+            Place::Index(lhs_place, indices) if is_argument_array(lhs_place) => {
+                if let Some(var) = argument_var(indices) {
+                    self.set_var(&var, value)?;
+                }
+            }
             Place::Index(lhs_place, indices) => {
                 // need to be careful here, in `foo[123] = bar`
                 // foo may not be defined.
                 let lhs_value = self.place_value(lhs_place)?;
                 let lhs_id = if matches!(lhs_value, Value::Undefined) {
-                    let id = self.global.new_instance(Rc::<Array>::default());
+                    let id = self
+                        .global
+                        .new_instance(Rc::new(Array::new(self.array_len_limit)));
                     self.set_place(lhs_place, id.0.into())?;
                     id
                 } else {
@@ -627,8 +830,17 @@ impl<'a> Context<'a> {
         Ok(())
     }
 
+    /// Runs `script` with its own locals. An error, such as calling an undefined function,
+    /// ends the script there, but the caller's locals are still restored so it can keep
+    /// running other scripts.
     pub fn exec_script(&mut self, script: &ast::Script, arguments: &[Value]) -> Result<Value> {
         let old_locals = std::mem::take(&mut self.locals);
+        let result = self.exec_script_stmts(script, arguments);
+        self.locals = old_locals;
+        result
+    }
+
+    fn exec_script_stmts(&mut self, script: &ast::Script, arguments: &[Value]) -> Result<Value> {
         for (index, value) in arguments.iter().enumerate() {
             self.locals
                 .set_member(&format!("argument{index}"), value.clone())?;
@@ -637,21 +849,58 @@ impl<'a> Context<'a> {
             match self.exec(stmt) {
                 Err(Error::Exit) => break,
                 Err(Error::Return(value)) => return Ok(value),
+                // don't end a loop in the script that called this one
+                Err(Error::Break) => {
+                    Err(Error::OutsideLoop("break")).with_script_name(script.name.clone())
+                }
+                Err(Error::Continue) => {
+                    Err(Error::OutsideLoop("continue")).with_script_name(script.name.clone())
+                }
                 result => result.with_script_name(script.name.clone()),
             }?;
         }
-        self.locals = old_locals;
         Ok(Value::Undefined)
     }
 
+    /// Counts a loop iteration, erroring if the loop has now run more than
+    /// [`Self::iteration_limit`] times.
+    fn count_iteration(&self, iterations: &mut u32) -> Result {
+        *iterations = iterations.saturating_add(1);
+        match self.iteration_limit {
+            Some(limit) if *iterations > limit => Err(Error::IterationLimit(limit)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Runs the body of a loop or `with`, returning false if it ran `break`.
+    fn exec_loop_body(&mut self, body: &ast::Stmt) -> Result<bool> {
+        match self.exec(body) {
+            Ok(()) | Err(Error::Continue) => Ok(true),
+            Err(Error::Break) => Ok(false),
+            Err(error) => Err(error),
+        }
+    }
+
     pub fn exec(&mut self, stmt: &ast::Stmt) -> Result {
+        if let Some(trace) = &self.trace {
+            trace(Trace::Stmt {
+                pos: stmt.pos(),
+                stmt,
+            });
+        }
         match stmt {
             ast::Stmt::Expr { pos, expr } => {
                 self.eval(expr).with_position(*pos)?;
             }
-            ast::Stmt::Var(id) => {
+            ast::Stmt::Var(decls) => {
                 // var foo; ensures there is an entry in locals, so later references use it.
-                self.locals.set_member(id, ().into())?;
+                for (id, init) in decls {
+                    let value = match init {
+                        Some(init) => self.eval(init)?,
+                        None => ().into(),
+                    };
+                    self.locals.set_member(id, value)?;
+                }
             }
             ast::Stmt::Assign { pos, assign } => {
                 self.exec_assign(assign).with_position(*pos)?;
@@ -663,45 +912,79 @@ impl<'a> Context<'a> {
                     self.exec(alt)?;
                 }
             }
-            ast::Stmt::Repeat { count, body } => {
-                let count = self.eval(count)?.to_int();
+            ast::Stmt::Repeat { pos, count, body } => {
+                // GM rounds the count rather than truncating, and negative counts don't run
+                let count = self.eval(count)?.to_float().round() as i32;
+                let mut iterations = 0;
                 for _ in 0..count {
-                    self.exec(body)?;
+                    self.count_iteration(&mut iterations).with_position(*pos)?;
+                    if !self.exec_loop_body(body)? {
+                        break;
+                    }
                 }
             }
-            ast::Stmt::While { cond, body } => loop {
-                if !self.eval(cond)?.to_bool() {
-                    break;
+            ast::Stmt::While { pos, cond, body } => {
+                let mut iterations = 0;
+                loop {
+                    if !self.eval(cond)?.to_bool() {
+                        break;
+                    }
+                    self.count_iteration(&mut iterations).with_position(*pos)?;
+                    if !self.exec_loop_body(body)? {
+                        break;
+                    }
                 }
-                self.exec(body)?;
-            },
+            }
             ast::Stmt::For {
+                pos,
                 assign,
                 cond,
                 update,
                 body,
             } => {
                 self.exec_assign(assign)?;
+                let mut iterations = 0;
                 loop {
                     if !self.eval(cond)?.to_bool() {
                         break;
                     }
-                    self.exec(body)?;
+                    self.count_iteration(&mut iterations).with_position(*pos)?;
+                    if !self.exec_loop_body(body)? {
+                        break;
+                    }
                     self.exec_assign(update)?;
                 }
             }
             ast::Stmt::With { obj, body } => {
                 let value = self.eval(obj)?;
                 let id = value.as_object_id().ok_or(Error::InvalidObject(value))?;
-                for instance in self.global.instances_all(id) {
-                    self.with_instance(id, instance, |ctx| ctx.exec(body))?;
+                let id = self.resolve_id(id);
+                // the body may create or destroy instances, so only look up each one when
+                // it's reached, skipping any that were destroyed
+                let ids = if id == self.instance_id {
+                    vec![id]
+                } else {
+                    self.global.instance_ids(id)
+                };
+                for id in ids {
+                    let Ok(instance) = self.instance(id) else {
+                        continue;
+                    };
+                    if !self.with_instance(id, instance, |ctx| ctx.exec_loop_body(body))? {
+                        break;
+                    }
                 }
             }
             ast::Stmt::Return { expr } => {
-                let value = self.eval(expr)?;
+                let value = match expr {
+                    Some(expr) => self.eval(expr)?,
+                    None => Value::Undefined,
+                };
                 return Err(Error::Return(value));
             }
             ast::Stmt::Exit => return Err(Error::Exit),
+            ast::Stmt::Break => return Err(Error::Break),
+            ast::Stmt::Continue => return Err(Error::Continue),
             ast::Stmt::Block { stmts } => {
                 for stmt in stmts {
                     self.exec(stmt)?;
@@ -718,11 +1001,13 @@ impl<'a> Context<'a> {
         instance: Rc<dyn Object>,
         body: F,
     ) -> R {
+        let old_other_id = std::mem::replace(&mut self.other_id, self.instance_id);
         let old_instance_id = std::mem::replace(&mut self.instance_id, instance_id);
         let old_instance = std::mem::replace(&mut self.instance, instance);
         let result = body(self);
         self.instance = old_instance;
         self.instance_id = old_instance_id;
+        self.other_id = old_other_id;
         result
     }
 
@@ -759,10 +1044,21 @@ impl<'a> Context<'a> {
             ast::Expr::String(value) => Ok(Place::Value(Value::String(value.clone()))),
             ast::Expr::Unary { op, expr } => {
                 let place = self.eval_place(expr)?;
+                if self.strict {
+                    if let value @ Value::String(_) = self.place_value(&place)? {
+                        return Err(Error::StrictConversion(value));
+                    }
+                }
                 let value = match op {
                     ast::UnaryOp::Not => (!self.place_value(&place)?.to_bool()).into(),
                     ast::UnaryOp::Pos => self.place_value(&place)?.to_int().into(),
-                    ast::UnaryOp::Neg => (-self.place_value(&place)?.to_int()).into(),
+                    ast::UnaryOp::Neg => match self.place_value(&place)? {
+                        Value::Float(value) => (-value).into(),
+                        value => {
+                            let value = value.to_int();
+                            int_or_float(value.checked_neg(), || -f64::from(value))
+                        }
+                    },
                     ast::UnaryOp::BitNot => (!self.place_value(&place)?.to_int()).into(),
                     ast::UnaryOp::PreIncr => todo!(),
                     ast::UnaryOp::PreDecr => todo!(),
@@ -774,6 +1070,9 @@ impl<'a> Context<'a> {
             ast::Expr::Binary { lhs, op, rhs } => {
                 let lhs = self.eval(lhs)?;
                 let rhs = self.eval(rhs)?;
+                if self.strict {
+                    check_strict_operands(*op, &lhs, &rhs)?;
+                }
                 let value = match op {
                     ast::BinaryOp::And => (lhs.to_bool() && rhs.to_bool()).into(),
                     ast::BinaryOp::Or => (lhs.to_bool() || rhs.to_bool()).into(),
@@ -786,16 +1085,17 @@ impl<'a> Context<'a> {
                     ast::BinaryOp::Ge => (lhs >= rhs).into(),
                     ast::BinaryOp::Gt => (lhs > rhs).into(),
                     ast::BinaryOp::Ne => (lhs != rhs).into(),
-                    ast::BinaryOp::Eq => (lhs == rhs).into(),
+                    ast::BinaryOp::Eq | ast::BinaryOp::AssignEq => (lhs == rhs).into(),
                     ast::BinaryOp::Add => (lhs + rhs)?,
                     ast::BinaryOp::Sub => (lhs - rhs)?,
                     ast::BinaryOp::Mul => (lhs * rhs)?,
                     ast::BinaryOp::Div => (lhs / rhs)?,
-                    ast::BinaryOp::IDiv => (lhs.to_int() / rhs.to_int()).into(),
+                    ast::BinaryOp::IDiv => int_div(lhs.to_int(), rhs.to_int())?,
                     ast::BinaryOp::IMod => (lhs % rhs)?,
                 };
                 Ok(Place::Value(value))
             }
+            // `a.b.c`: `a.b` is evaluated to an instance id, so chains can be any depth
             ast::Expr::Member { lhs, name } => {
                 let value = self.eval(lhs)?;
                 let id = value.as_object_id().ok_or(Error::InvalidObject(value))?;
@@ -807,6 +1107,13 @@ impl<'a> Context<'a> {
                     .iter()
                     .map(|index| self.eval(index))
                     .collect::<Result<Vec<_>>>()?;
+                if self.strict {
+                    if let Some(index @ Value::String(_)) =
+                        indices.iter().find(|i| i.as_str().is_some())
+                    {
+                        return Err(Error::StrictConversion(index.clone()));
+                    }
+                }
                 Ok(Place::Index(lhs, indices))
             }
             // (id).member
@@ -819,16 +1126,682 @@ impl<'a> Context<'a> {
                 pos,
                 name: id,
                 args,
+                builtin,
             } => {
-                // println!("{line}:{column}: {id}()");
-                let args = args
-                    .iter()
-                    .map(|arg| self.eval(arg))
-                    .collect::<Result<Vec<_>>>()
-                    .with_position(*pos)?;
+                if let Some(trace) = &self.trace {
+                    trace(Trace::Call {
+                        pos: *pos,
+                        name: id,
+                    });
+                }
+                let args = if let Some(builtin) = builtin.filter(|_| args.len() <= BUILTIN_MAX_ARGS)
+                {
+                    let mut values: [Value; BUILTIN_MAX_ARGS] = Default::default();
+                    for (value, arg) in values.iter_mut().zip(args) {
+                        *value = self.eval(arg).with_position(*pos)?;
+                    }
+                    if let Some(result) = call_builtin(builtin, &values[..args.len()]) {
+                        return Ok(Place::Value(result));
+                    }
+                    // don't evaluate them again, they may have side effects
+                    values.into_iter().take(args.len()).collect()
+                } else {
+                    args.iter()
+                        .map(|arg| self.eval(arg))
+                        .collect::<Result<Vec<_>>>()
+                        .with_position(*pos)?
+                };
                 let result = self.global.call(self, id, args).with_position(*pos)?;
                 Ok(Place::Value(result))
             }
         }
     }
 }
+
+/// Reads the number at the start of `value` like GM's `real()`: an optional sign, digits
+/// and a fraction, ignoring anything after them. Unlike `str::parse()`, `"3abc"` is 3 rather
+/// than an error, but there are no exponents, `inf` or `nan`. 0 if there's no number.
+pub fn parse_number_prefix(value: &str) -> f64 {
+    let value = value.trim_start();
+    let bytes = value.as_bytes();
+    let skip_digits = |mut end: usize| {
+        while bytes.get(end).is_some_and(u8::is_ascii_digit) {
+            end += 1;
+        }
+        end
+    };
+    let mut end = usize::from(matches!(bytes.first(), Some(b'+' | b'-')));
+    end = skip_digits(end);
+    if bytes.get(end) == Some(&b'.') {
+        end = skip_digits(end + 1);
+    }
+    value[..end].parse().unwrap_or_default()
+}
+
+/// Builtins called with more arguments than this go through [`Global::call()`], so
+/// their arguments can be evaluated without allocating.
+const BUILTIN_MAX_ARGS: usize = 4;
+
+/// Runs `builtin`, or `None` if it doesn't take `args.len()` arguments. [`Global::call()`]
+/// can use this so calls that aren't evaluated directly, or `script_execute()`, agree.
+pub fn call_builtin(builtin: ast::Builtin, args: &[Value]) -> Option<Value> {
+    use ast::Builtin::*;
+
+    let value = |index: usize| args[index].to_float();
+    let result = match (builtin, args.len()) {
+        (Abs, 1) => value(0).abs(),
+        (Floor, 1) => value(0).floor(),
+        (Min, 1..) => args
+            .iter()
+            .map(Value::to_float)
+            .fold(f64::INFINITY, f64::min),
+        (Max, 1..) => args
+            .iter()
+            .map(Value::to_float)
+            .fold(f64::NEG_INFINITY, f64::max),
+        // unlike f64::clamp(), doesn't panic if min > max
+        (Clamp, 3) => value(0).max(value(1)).min(value(2)),
+        (Power, 2) => value(0).powf(value(1)),
+        (Sqr, 1) => value(0) * value(0),
+        _ => return None,
+    };
+    Some(result.into())
+}
+
+/// Whether `name` is `argument0`, `argument1` etc., which are always script locals, and
+/// 0 if they weren't passed.
+fn is_argument(name: &str) -> bool {
+    name.strip_prefix("argument")
+        .is_some_and(|index| index.parse::<u32>().is_ok())
+}
+
+/// Whether `place` is the `argument` in `argument[i]`.
+fn is_argument_array(place: &Place) -> bool {
+    matches!(place, Place::Var(ast::Var::Local(name)) if name == "argument")
+}
+
+/// `argument[i]` is `argumenti`, or `None` if `i` is negative.
+fn argument_var(indices: &[Value]) -> Option<ast::Var> {
+    let index = u32::try_from(indices.first()?.to_int()).ok()?;
+    Some(ast::Var::Local(format!("argument{index}")))
+}
+
+/// Strings may only be used with other strings, in concatenation and comparisons.
+fn check_strict_operands(op: ast::BinaryOp, lhs: &Value, rhs: &Value) -> Result {
+    use ast::BinaryOp::*;
+
+    match (lhs, rhs) {
+        (Value::String(_), Value::String(_))
+            if matches!(op, Add | Le | Lt | Ge | Gt | Ne | Eq | AssignEq) =>
+        {
+            Ok(())
+        }
+        (Value::String(_), _) | (_, Value::String(_)) => {
+            Err(Error::InvalidOperands(lhs.clone(), rhs.clone()))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(strict: bool, source: &str) -> Result<Value> {
+        let global = SimpleGlobal::default();
+        let mut context = Context::new(&global, ObjectId(100000), Rc::<Namespace>::default());
+        context.strict = strict;
+        context.eval(&crate::parse_expr(source).unwrap())
+    }
+
+    #[test]
+    fn test_strict() {
+        assert_eq!(
+            eval(false, r#"2 * "x""#).unwrap(),
+            Value::String("xx".into())
+        );
+        assert!(matches!(
+            eval(true, r#"2 * "x""#),
+            Err(Error::InvalidOperands(..))
+        ));
+
+        assert_eq!(eval(false, r#""5" div 2"#).unwrap(), Value::Int(2));
+        assert!(eval(true, r#""5" div 2"#).is_err());
+
+        assert_eq!(eval(false, "-\"3\"").unwrap(), Value::Int(-3));
+        assert!(matches!(
+            eval(true, "-\"3\""),
+            Err(Error::StrictConversion(..))
+        ));
+
+        assert_eq!(
+            eval(true, r#""a" + "b""#).unwrap(),
+            Value::String("ab".into())
+        );
+    }
+
+    #[test]
+    fn test_parse_number_prefix() {
+        assert_eq!(parse_number_prefix("3abc"), 3.0);
+        assert_eq!(parse_number_prefix("-2.5x"), -2.5);
+        assert_eq!(parse_number_prefix(" +.5"), 0.5);
+        assert_eq!(parse_number_prefix("abc"), 0.0);
+        assert_eq!(parse_number_prefix("-"), 0.0);
+        assert_eq!(parse_number_prefix("1e3"), 1.0);
+
+        let value = Value::String("-2.5x".into());
+        assert_eq!(value.to_float(), -2.5);
+        assert_eq!(value.to_int(), -2);
+    }
+
+    #[test]
+    fn test_mod_div() {
+        assert_eq!(eval(false, "7 mod 3").unwrap(), Value::Int(1));
+        assert_eq!(eval(false, "7 div 3").unwrap(), Value::Int(2));
+        assert_eq!(eval(false, "-7 div 2").unwrap(), Value::Int(-3));
+        assert_eq!(eval(false, "7.5 mod 2").unwrap(), Value::Float(1.5));
+        // `/` isn't integer division
+        assert_eq!(eval(false, "7 / 2").unwrap(), Value::Float(3.5));
+        assert_eq!(eval(false, "6 / 2").unwrap(), Value::Float(3.0));
+        // which still compares equal to an integer, in either order
+        assert_eq!(eval(false, "3 == 6 / 2").unwrap(), Value::Bool(true));
+        assert_eq!(eval(false, "6 / 2 == 3").unwrap(), Value::Bool(true));
+        assert_eq!(eval(false, "3 != 6 / 2").unwrap(), Value::Bool(false));
+        assert_eq!(eval(false, "3 < 7 / 2").unwrap(), Value::Bool(true));
+        assert_eq!(eval(false, "7 / 2 > 3").unwrap(), Value::Bool(true));
+
+        for source in ["1 / 0", "1 / 0.0", "1 mod 0", "1.5 mod 0", "1 div 0"] {
+            assert!(
+                matches!(eval(false, source), Err(Error::DivisionByZero)),
+                "{source}"
+            );
+        }
+        let min = "(-2147483647 - 1)";
+        assert_eq!(
+            eval(false, &format!("{min} / -1")).unwrap(),
+            Value::Float(2147483648.0)
+        );
+        assert_eq!(
+            eval(false, &format!("{min} div -1")).unwrap(),
+            Value::Float(2147483648.0)
+        );
+        assert_eq!(
+            eval(false, &format!("{min} mod -1")).unwrap(),
+            Value::Int(0)
+        );
+    }
+
+    #[test]
+    fn test_int_overflow() {
+        assert_eq!(
+            eval(false, "2147483647 + 1").unwrap(),
+            Value::Float(2147483648.0)
+        );
+        assert_eq!(
+            eval(false, "-2147483647 - 2").unwrap(),
+            Value::Float(-2147483649.0)
+        );
+        assert_eq!(eval(false, "50000 * 50000").unwrap(), Value::Float(2.5e9));
+        assert_eq!(
+            eval(false, "-(-2147483647 - 1)").unwrap(),
+            Value::Float(2147483648.0)
+        );
+        assert_eq!(eval(false, "-2.5").unwrap(), Value::Float(-2.5));
+        // still integers when they fit
+        assert_eq!(
+            eval(false, "46340 * 46340").unwrap(),
+            Value::Int(2147395600)
+        );
+    }
+
+    #[test]
+    fn test_power_sqr() {
+        let global = SimpleGlobal::default();
+        let mut context = Context::new(&global, ObjectId(100000), Rc::<Namespace>::default());
+        let mut eval = |source: &str| context.eval(&crate::parse_expr(source).unwrap()).unwrap();
+        assert_eq!(eval("power(2, 31)"), Value::Float(2147483648.0));
+        assert_eq!(eval("power(2, -1)"), Value::Float(0.5));
+        assert_eq!(eval("power(4, 0.5)"), Value::Float(2.0));
+        assert_eq!(eval("sqr(50000)"), Value::Float(2.5e9));
+        assert_eq!(eval("sqr(1.5)"), Value::Float(2.25));
+    }
+
+    #[test]
+    fn test_logical_bitwise() {
+        // the keywords are logical, the single symbols bitwise
+        assert_eq!(eval(false, "3 and 0").unwrap(), Value::Bool(false));
+        assert_eq!(eval(false, "3 && 2").unwrap(), Value::Bool(true));
+        assert_eq!(eval(false, "0 or 2").unwrap(), Value::Bool(true));
+        assert_eq!(eval(false, "3 xor 1").unwrap(), Value::Bool(false));
+        assert_eq!(eval(false, "not 3").unwrap(), Value::Bool(false));
+        assert_eq!(eval(false, "3 & 1").unwrap(), Value::Int(1));
+        assert_eq!(eval(false, "2 | 1").unwrap(), Value::Int(3));
+        assert_eq!(eval(false, "3 ^ 1").unwrap(), Value::Int(2));
+        assert_eq!(eval(false, "~0").unwrap(), Value::Int(-1));
+        assert_eq!(eval(false, "true & false").unwrap(), Value::Int(0));
+        assert_eq!(eval(false, "notes + 1").unwrap(), Value::Int(1));
+    }
+
+    #[test]
+    fn test_var_decls() {
+        let global = SimpleGlobal::default();
+        let mut context = Context::new(&global, ObjectId(100000), Rc::<Namespace>::default());
+        let script = crate::parse("test", "var a = 1, b; b = a + 1; return b;").unwrap();
+        assert_eq!(context.exec_script(&script, &[]).unwrap(), Value::Int(2));
+    }
+
+    #[test]
+    fn test_var_shadows_instance() {
+        let global = SimpleGlobal::default();
+        let instance = Rc::<Namespace>::default();
+        instance.set_member("x", Value::Int(5)).unwrap();
+        let mut context = Context::new(&global, ObjectId(100000), instance.clone());
+        // (-2) is self, which still reads the instance
+        let script = crate::parse("test", "var x; x = 1; return x * 10 + (-2).x;").unwrap();
+        assert_eq!(context.exec_script(&script, &[]).unwrap(), Value::Int(15));
+        assert_eq!(instance.get("x"), Some(Value::Int(5)));
+    }
+
+    #[test]
+    fn test_bare_return() {
+        let global = SimpleGlobal::default();
+        let mut context = Context::new(&global, ObjectId(100000), Rc::<Namespace>::default());
+        let source = "var x, y; x = argument0; if (x) return; y = 1; return y;";
+        let script = crate::parse("test", source).unwrap();
+        assert_eq!(
+            context.exec_script(&script, &[1.into()]).unwrap(),
+            Value::Undefined
+        );
+        assert_eq!(
+            context.exec_script(&script, &[0.into()]).unwrap(),
+            Value::Int(1)
+        );
+    }
+
+    #[test]
+    fn test_break_continue() {
+        let global = SimpleGlobal::default();
+        let mut context = Context::new(&global, ObjectId(100000), Rc::<Namespace>::default());
+        let mut exec =
+            |source: &str| context.exec_script(&crate::parse("test", source).unwrap(), &[]);
+
+        let source = "i = 0; while (1) { i += 1; if (i == 3) break; } return i;";
+        assert_eq!(exec(source).unwrap(), Value::Int(3));
+        let source =
+            "n = 0; for (i = 0; i < 5; i += 1) { if (i mod 2) continue; n += 1; } return n;";
+        assert_eq!(exec(source).unwrap(), Value::Int(3));
+        let source = "n = 0; repeat (3) { repeat (5) { n += 1; break; } } return n;";
+        assert_eq!(exec(source).unwrap(), Value::Int(3));
+
+        let Err(Error::WithScriptName(error, _)) = exec("x = 1;\nbreak;") else {
+            panic!("expected an error");
+        };
+        assert!(matches!(*error, Error::OutsideLoop("break")));
+        assert_eq!(error.to_string(), "break outside of a loop");
+        let Err(Error::WithScriptName(error, _)) = exec("if (1) { continue; }") else {
+            panic!("expected an error");
+        };
+        assert!(matches!(*error, Error::OutsideLoop("continue")));
+    }
+
+    #[test]
+    fn test_undefined_function() {
+        let global = SimpleGlobal::default();
+        let script = crate::parse("obj_test_step", "a = 1;\nmissing_fn();\nb = 1;").unwrap();
+
+        // like an instance's event, the error ends its script...
+        let first = Rc::<Namespace>::default();
+        let mut context = Context::new(&global, ObjectId(100001), first.clone());
+        let error = context.exec_script(&script, &[]).unwrap_err();
+        let message = error.to_string();
+        assert!(message.starts_with("function \"missing_fn\" has no definition"));
+        assert!(message.ends_with("in obj_test_step"));
+        assert_eq!(first.get("a"), Some(Value::Int(1)));
+        assert_eq!(first.get("b"), None);
+
+        // ...but not the event's later scripts, or other instances'
+        let source = "var x; x = 2; return x;";
+        let next = crate::parse("next", source).unwrap();
+        assert_eq!(context.exec_script(&next, &[]).unwrap(), Value::Int(2));
+        let second = Rc::<Namespace>::default();
+        let mut context = Context::new(&global, ObjectId(100002), second.clone());
+        assert!(context.exec_script(&script, &[]).is_err());
+        assert_eq!(second.get("a"), Some(Value::Int(1)));
+    }
+
+    #[test]
+    fn test_repeat_count() {
+        let global = SimpleGlobal::default();
+        let mut context = Context::new(&global, ObjectId(100000), Rc::<Namespace>::default());
+        let mut repeat = |count: &str| {
+            let source = format!("n = 0; repeat ({count}) n += 1; return n;");
+            context.exec_script(&crate::parse("test", &source).unwrap(), &[])
+        };
+        assert_eq!(repeat("2.7").unwrap(), Value::Int(3));
+        assert_eq!(repeat("2.2").unwrap(), Value::Int(2));
+        assert_eq!(repeat("0.5 + 0.1").unwrap(), Value::Int(1));
+        assert_eq!(repeat("-1").unwrap(), Value::Int(0));
+        assert_eq!(repeat("-0.7").unwrap(), Value::Int(0));
+        assert_eq!(repeat("0").unwrap(), Value::Int(0));
+    }
+
+    #[test]
+    fn test_self_other() {
+        let global = SimpleGlobal::default();
+        let mut context = Context::new(&global, ObjectId(100000), Rc::<Namespace>::default());
+        assert_eq!(context.resolve_id(ObjectId::SELF), ObjectId(100000));
+        // other is self until something sets it
+        let script = crate::parse("test", "(-3).x = 5; return (-2).x;").unwrap();
+        assert_eq!(context.exec_script(&script, &[]).unwrap(), Value::Int(5));
+
+        context.other_id = ObjectId(100002);
+        assert_eq!(context.resolve_id(ObjectId::OTHER), ObjectId(100002));
+        context.with_instance(ObjectId(100003), Rc::<Namespace>::default(), |context| {
+            assert_eq!(context.resolve_id(ObjectId::OTHER), ObjectId(100000));
+        });
+        assert_eq!(context.resolve_id(ObjectId::OTHER), ObjectId(100002));
+    }
+
+    /// Has `count` instances of object 1, with ids from 100001.
+    fn instances_global(count: usize) -> (SimpleGlobal, Vec<Rc<Namespace>>) {
+        let global = SimpleGlobal::default();
+        let instances = (0..count)
+            .map(|_| {
+                let instance = Rc::<Namespace>::default();
+                global.add_instance(ObjectId(1), instance.clone());
+                instance
+            })
+            .collect();
+        (global, instances)
+    }
+
+    /// Has instances 100001 and 100002, and `instance_nearest()` returns the second if its
+    /// first argument is positive.
+    fn nearest_global() -> (SimpleGlobal, Rc<Namespace>) {
+        let (global, instances) = instances_global(2);
+        let global = global.with_function("instance_nearest", |_, _, args| {
+            Ok(if args[0].to_int() > 0 {
+                ObjectId(100002).into()
+            } else {
+                ObjectId::NOONE.into()
+            })
+        });
+        (global, instances[1].clone())
+    }
+
+    #[test]
+    fn test_call_member() {
+        let (global, nearest) = nearest_global();
+        nearest.insert("hp", 3);
+        let mut context = Context::new(&global, ObjectId(100000), Rc::<Namespace>::default());
+
+        let script = crate::parse(
+            "test",
+            "instance_nearest(1, 2, 0).hp += 1; return instance_nearest(1, 2, 0).hp;",
+        )
+        .unwrap();
+        assert_eq!(context.exec_script(&script, &[]).unwrap(), Value::Int(4));
+
+        let script = crate::parse("test", "return instance_nearest(-1, 2, 0).hp;").unwrap();
+        assert!(matches!(
+            context.exec_script(&script, &[]),
+            Err(Error::WithScriptName(error, _))
+                if matches!(*error, Error::InvalidObject(Value::Int(-5)))
+        ));
+    }
+
+    #[test]
+    fn test_with_sets_target() {
+        let (global, nearest) = nearest_global();
+        let caller = Rc::<Namespace>::default();
+        let mut context = Context::new(&global, ObjectId(100000), caller.clone());
+
+        // script locals are still visible in the body, but other names are the target's
+        let script = crate::parse(
+            "test",
+            "var total; total = 1; with (100002) { hp = 10; total += hp; } return total;",
+        )
+        .unwrap();
+        assert_eq!(context.exec_script(&script, &[]).unwrap(), Value::Int(11));
+        assert_eq!(nearest.get("hp"), Some(Value::Int(10)));
+        assert_eq!(caller.get("hp"), None);
+        assert_eq!(caller.get("total"), None);
+    }
+
+    #[test]
+    fn test_arguments() {
+        let global = SimpleGlobal::default();
+        let instance = Rc::<Namespace>::default();
+        let mut context = Context::new(&global, ObjectId(100000), instance.clone());
+        let script = crate::parse("test", "return argument0 + argument3;").unwrap();
+        assert_eq!(
+            context.exec_script(&script, &[5.into()]).unwrap(),
+            Value::Int(5)
+        );
+
+        // arguments are locals, even if they weren't passed
+        let script = crate::parse("test", "argument2 = 7; return argument2;").unwrap();
+        assert_eq!(context.exec_script(&script, &[]).unwrap(), Value::Int(7));
+        assert_eq!(instance.get("argument2"), None);
+
+        let script = crate::parse(
+            "test",
+            "argument[1] = 2; return argument[0] + argument[1] + argument[20] + argument[-1];",
+        );
+        assert_eq!(
+            context.exec_script(&script.unwrap(), &[5.into()]).unwrap(),
+            Value::Int(7)
+        );
+    }
+
+    #[test]
+    fn test_iteration_limit() {
+        let global = SimpleGlobal::default();
+        let mut context = Context::new(&global, ObjectId(100000), Rc::<Namespace>::default());
+        context.iteration_limit = Some(10);
+
+        let script = crate::parse("test", "i = 0; repeat (10) i += 1; return i;").unwrap();
+        assert_eq!(context.exec_script(&script, &[]).unwrap(), Value::Int(10));
+
+        let script = crate::parse("test", "i = 0;\nwhile (1) {\n  i += 1;\n}").unwrap();
+        let Err(Error::WithScriptName(error, name)) = context.exec_script(&script, &[]) else {
+            panic!("expected an error");
+        };
+        assert_eq!(name, "test");
+        assert!(matches!(
+            *error,
+            Error::WithPosition(error, ast::Pos { line: 2, .. })
+                if matches!(*error, Error::IterationLimit(10))
+        ));
+    }
+
+    #[test]
+    fn test_array_index_errors() {
+        let array = Array::new(10);
+        assert!(matches!(
+            array.set_index(&[(-1).into()], 1.into()),
+            Err(Error::NegativeIndex(-1))
+        ));
+        assert!(matches!(
+            array.set_index(&[10.into()], 1.into()),
+            Err(Error::IndexLimit {
+                index: 10,
+                limit: 10
+            })
+        ));
+        assert_eq!(array.debug_index_length(), Some(0));
+
+        array.set_index(&[9.into()], 1.into()).unwrap();
+        assert_eq!(array.index(&[9.into()]).unwrap(), Some(Value::Int(1)));
+        let entries = array.debug_entries().unwrap();
+        assert_eq!(entries.len(), 10);
+        assert_eq!(entries[9], ("9".to_string(), Value::Int(1)));
+        assert_eq!(array.index(&[(-1).into()]).unwrap(), None);
+
+        let global = SimpleGlobal::default();
+        let context = Context::new(&global, ObjectId(100000), Rc::<Namespace>::default());
+        assert_eq!(context.array_len_limit, DEFAULT_ARRAY_LEN_LIMIT);
+    }
+
+    #[test]
+    fn test_event_vars() {
+        let global = SimpleGlobal::default();
+        let mut context = Context::new(&global, ObjectId(100000), Rc::<Namespace>::default());
+        let script = crate::parse("test", "return event_type * 10 + event_number;").unwrap();
+        // step end
+        context.event = Some((3, 2));
+        assert_eq!(context.exec_script(&script, &[]).unwrap(), Value::Int(32));
+    }
+
+    #[test]
+    fn test_builtin_var() {
+        let global = SimpleGlobal::default().with_builtin_var("score");
+        let instance = Rc::<Namespace>::default();
+        let mut context = Context::new(&global, ObjectId(100000), instance.clone());
+        let script = crate::parse("test", "score = 5; score += 1; hp = score;").unwrap();
+        context.exec_script(&script, &[]).unwrap();
+        assert_eq!(global.vars.get("score"), Some(Value::Int(6)));
+        assert_eq!(instance.get("score"), None);
+        assert_eq!(instance.get("hp"), Some(Value::Int(6)));
+    }
+
+    #[test]
+    fn test_trace() {
+        let global = SimpleGlobal::default();
+        let mut context = Context::new(&global, ObjectId(100000), Rc::<Namespace>::default());
+        let traced = Rc::new(RefCell::new(vec![]));
+        context.trace = Some(Rc::new({
+            let traced = traced.clone();
+            move |trace: Trace<'_>| {
+                let entry = match trace {
+                    Trace::Stmt { pos, .. } => pos.map(|pos| pos.to_string()),
+                    Trace::Call { pos, name } => Some(format!("{name}() {pos}")),
+                };
+                traced.borrow_mut().push(entry);
+            }
+        }));
+        let source = "a = 1;\nrepeat (2) {\n    b = abs(a);\n}";
+        let script = crate::parse("test", source).unwrap();
+        context.exec_script(&script, &[]).unwrap();
+
+        // the block has no position
+        let body = [None, Some("3:5"), Some("abs() 3:9")];
+        let expected = [Some("1:1"), Some("2:1")]
+            .into_iter()
+            .chain(body)
+            .chain(body)
+            .map(|entry| entry.map(str::to_string))
+            .collect::<Vec<_>>();
+        assert_eq!(*traced.borrow(), expected);
+    }
+
+    #[test]
+    fn test_builtin_calls() {
+        let global = SimpleGlobal::default();
+        let mut context = Context::new(&global, ObjectId(100000), Rc::<Namespace>::default());
+        for (source, expected) in [
+            ("abs(-2.5)", 2.5),
+            ("floor(-2.5)", -3.0),
+            ("min(3, -1, 2)", -1.0),
+            ("max(3, -1, \"7\")", 7.0),
+            ("max(1, 2, 3, 4, 5, 6)", 6.0),
+            ("clamp(5, 0, 3)", 3.0),
+            ("clamp(-5, 0, 3)", 0.0),
+            ("clamp(1, 3, 0)", 0.0),
+            ("power(2, 3)", 8.0),
+            ("sqr(-3)", 9.0),
+        ] {
+            let mut expr = crate::parse_expr(source).unwrap();
+            assert_eq!(
+                context.eval(&expr).unwrap(),
+                Value::Float(expected),
+                "{source}"
+            );
+            // the same through Global::call()
+            let ast::Expr::Call { builtin, .. } = &mut *expr else {
+                panic!("{source} isn't a call");
+            };
+            assert!(builtin.is_some(), "{source} isn't a builtin");
+            *builtin = None;
+            assert_eq!(
+                context.eval(&expr).unwrap(),
+                Value::Float(expected),
+                "{source}"
+            );
+        }
+        assert_eq!(
+            context
+                .eval(&crate::parse_expr("clamp(5, 0, 3) + min(2, 1)").unwrap())
+                .unwrap(),
+            Value::Float(4.0)
+        );
+    }
+
+    #[test]
+    fn test_builtin_wrong_arity() {
+        let global = SimpleGlobal::default().with_function("count", |global, _, _| {
+            let count = global.vars.get("count").unwrap_or_default().to_int() + 1;
+            global.vars.set_member("count", count.into())?;
+            Ok(count.into())
+        });
+        let mut context = Context::new(&global, ObjectId(100000), Rc::<Namespace>::default());
+        // falls back to Global::call(), which doesn't have it either
+        let expr = crate::parse_expr("abs(count(), 2)").unwrap();
+        assert!(context.eval(&expr).is_err());
+        // without running the arguments again
+        assert_eq!(global.vars.get("count"), Some(Value::Int(1)));
+    }
+
+    #[test]
+    fn test_with_destroy() {
+        let (global, instances) = instances_global(3);
+        let global = global.with_function("instance_destroy", |global, context, args| {
+            let id = match args.first() {
+                Some(id) => id.try_to_object_id()?,
+                None => context.instance_id,
+            };
+            global.remove_instance(id);
+            Ok(().into())
+        });
+        let mut context = Context::new(&global, ObjectId(100000), Rc::<Namespace>::default());
+        let script = crate::parse(
+            "test",
+            "with (1) { visited = 1; instance_destroy(); instance_destroy(100003); }",
+        )
+        .unwrap();
+        context.exec_script(&script, &[]).unwrap();
+        assert!(global.instance_ids(ObjectId(1)).is_empty());
+        // 100003 was destroyed before the loop reached it
+        let visited = instances
+            .iter()
+            .map(|instance| instance.get("visited").is_some())
+            .collect::<Vec<_>>();
+        assert_eq!(visited, [true, true, false]);
+    }
+
+    #[test]
+    fn test_assign_member_chain() {
+        let (global, instances) = instances_global(3);
+        let mut context = Context::new(&global, ObjectId(100000), Rc::<Namespace>::default());
+        let mut exec =
+            |source: &str| context.exec_script(&crate::parse("test", source).unwrap(), &[]);
+
+        exec("player = 100001; player.target = 100002; player.target.hp = 5;").unwrap();
+        assert_eq!(instances[0].get("target"), Some(100002.into()));
+        assert_eq!(instances[1].get("hp"), Some(5.into()));
+
+        // any depth, as each member before the last is read as an instance id
+        let source = "player.target.next = 100003; player.target.next.hp = 2; \
+            player.target.next.hp += 1; return (100003).hp;";
+        assert_eq!(exec(source).unwrap(), Value::Int(3));
+
+        let Err(Error::WithScriptName(error, _)) = exec("player.name = \"a\"; player.name.x = 1;")
+        else {
+            panic!("expected an error");
+        };
+        assert!(matches!(
+            *error,
+            Error::WithPosition(error, _) if matches!(*error, Error::InvalidObject(_))
+        ));
+    }
+}
@@ -0,0 +1,135 @@
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+
+use super::{call_builtin, Context, Error, Global, Namespace, Object, ObjectId, Result, Value};
+use crate::ast;
+
+type Function = Box<dyn Fn(&SimpleGlobal, &mut Context<'_>, Vec<Value>) -> Result<Value>>;
+
+/// A [`Global`] without a game, for running scripts in tests and benchmarks: global
+/// variables, instances, the builtin functions, and any others added with
+/// [`Self::with_function()`].
+#[derive(Default)]
+pub struct SimpleGlobal {
+    pub vars: Namespace,
+    /// The names [`Global::builtin_var()`] is true for, which are kept in `vars`.
+    builtin_vars: Vec<&'static str>,
+    instances: RefCell<BTreeMap<ObjectId, Instance>>,
+    added_instances: Cell<i32>,
+    functions: HashMap<&'static str, Function>,
+}
+
+struct Instance {
+    object: ObjectId,
+    value: Rc<dyn Object>,
+}
+
+impl SimpleGlobal {
+    /// Like GM, instance ids start after this.
+    const LAST_RESERVED_ID: i32 = 100000;
+
+    pub fn with_builtin_var(mut self, name: &'static str) -> Self {
+        self.builtin_vars.push(name);
+        self
+    }
+
+    pub fn with_function(
+        mut self,
+        name: &'static str,
+        function: impl Fn(&SimpleGlobal, &mut Context<'_>, Vec<Value>) -> Result<Value> + 'static,
+    ) -> Self {
+        self.functions.insert(name, Box::new(function));
+        self
+    }
+
+    /// Adds an instance of `object`, which `with (object)` runs for, returning its id.
+    pub fn add_instance(&self, object: ObjectId, instance: Rc<dyn Object>) -> ObjectId {
+        let index = self.added_instances.get() + 1;
+        self.added_instances.set(index);
+        let id = ObjectId(Self::LAST_RESERVED_ID + index);
+        self.instances.borrow_mut().insert(
+            id,
+            Instance {
+                object,
+                value: instance,
+            },
+        );
+        id
+    }
+
+    /// Removes the instance, returning whether it existed.
+    pub fn remove_instance(&self, id: ObjectId) -> bool {
+        self.instances.borrow_mut().remove(&id).is_some()
+    }
+}
+
+impl Global for SimpleGlobal {
+    fn get(&self, name: &str) -> Result<Option<Value>> {
+        Ok(self.vars.get(name))
+    }
+
+    fn set(&self, name: &str, value: Value) -> Result {
+        self.vars.set_member(name, value)
+    }
+
+    fn instance_ids(&self, id: ObjectId) -> Vec<ObjectId> {
+        let instances = self.instances.borrow();
+        if instances.contains_key(&id) {
+            return vec![id];
+        }
+        instances
+            .iter()
+            .filter(|(_, instance)| instance.object == id)
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    fn instance(&self, id: ObjectId) -> Option<Rc<dyn Object>> {
+        let instances = self.instances.borrow();
+        instances.get(&id).map(|instance| instance.value.clone())
+    }
+
+    fn new_instance(&self, object: Rc<dyn Object>) -> ObjectId {
+        self.add_instance(ObjectId::NOONE, object)
+    }
+
+    fn call(&self, context: &mut Context<'_>, id: &str, args: Vec<Value>) -> Result<Value> {
+        if let Some(function) = self.functions.get(id) {
+            return function(self, context, args);
+        }
+        ast::Builtin::from_name(id)
+            .and_then(|builtin| call_builtin(builtin, &args))
+            .ok_or_else(|| Error::UndefinedFunction(id.to_string()))
+    }
+
+    fn builtin_var(&self, name: &str) -> bool {
+        self.builtin_vars.contains(&name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instances() {
+        let global = SimpleGlobal::default();
+        let first = global.add_instance(ObjectId(1), Rc::<Namespace>::default());
+        let second = global.add_instance(ObjectId(1), Rc::<Namespace>::default());
+        let array = global.new_instance(Rc::<Namespace>::default());
+        assert_eq!(
+            (first, second, array),
+            (ObjectId(100001), ObjectId(100002), ObjectId(100003))
+        );
+
+        assert_eq!(global.instance_ids(ObjectId(1)), [first, second]);
+        assert_eq!(global.instance_ids(second), [second]);
+        assert!(global.instance(array).is_some());
+        assert!(global.remove_instance(first));
+        assert!(!global.remove_instance(first));
+        assert!(global.instance(first).is_none());
+        assert_eq!(global.instance_ids(ObjectId(1)), [second]);
+        assert!(global.instance_ids(ObjectId(2)).is_empty());
+    }
+}
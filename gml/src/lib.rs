@@ -1,7 +1,8 @@
 pub use ast::Script;
 pub use eval::Context;
-pub use parse::{dump_parse, parse, parse_expr};
+pub use parse::{dump_parse, parse, parse_expr, parse_recovering, ParseError};
 
 pub mod ast;
 pub mod eval;
+pub mod lint;
 mod parse;
@@ -0,0 +1,223 @@
+//! Reports suspicious patterns in parsed scripts.
+
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+
+use super::ast;
+
+#[derive(Clone, Debug)]
+pub struct Lint {
+    /// Position of the nearest enclosing statement or call, if it has one.
+    pub pos: Option<ast::Pos>,
+    pub message: String,
+}
+
+impl Display for Lint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.pos {
+            Some(pos) => write!(f, "{pos}: {}", self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+/// Names defined outside the script being linted.
+#[derive(Default, Debug)]
+pub struct Known {
+    /// Built-in functions and scripts.
+    pub functions: HashSet<String>,
+    /// Constants, resource names, built-in and instance variables.
+    pub names: HashSet<String>,
+}
+
+pub fn lint(script: &ast::Script, known: &Known) -> Vec<Lint> {
+    let mut assigned = Assigned::default();
+    script.visit(&mut assigned);
+
+    let mut linter = Linter {
+        known,
+        assigned: assigned.0,
+        pos: None,
+        lints: vec![],
+    };
+    script.visit(&mut linter);
+    linter.lints
+}
+
+#[derive(Default)]
+struct Assigned(HashSet<String>);
+
+impl ast::Visitor for Assigned {
+    fn stmt(&mut self, value: &ast::Stmt) -> bool {
+        if let ast::Stmt::Var(decls) = value {
+            self.0.extend(decls.iter().map(|(name, _)| name.clone()));
+        }
+        true
+    }
+
+    fn assign(&mut self, value: &ast::Assign) -> bool {
+        if let ast::Expr::Var(ast::Var::Local(name)) = &*value.lhs {
+            self.0.insert(name.clone());
+        }
+        true
+    }
+}
+
+struct Linter<'a> {
+    known: &'a Known,
+    assigned: HashSet<String>,
+    pos: Option<ast::Pos>,
+    lints: Vec<Lint>,
+}
+
+impl Linter<'_> {
+    fn push(&mut self, message: String) {
+        self.lints.push(Lint {
+            pos: self.pos,
+            message,
+        });
+    }
+
+    /// `what` is the use of `cond`, e.g. "condition".
+    fn check_cond(&mut self, what: &str, cond: &ast::Expr) {
+        if let ast::Expr::Binary {
+            op: ast::BinaryOp::AssignEq,
+            lhs,
+            rhs,
+        } = cond
+        {
+            self.push(format!(
+                "assignment used as {what}: `{lhs} = {rhs}`, did you mean `==`?"
+            ));
+        }
+    }
+
+    /// Visits the reads in an assignment target, but not the assigned variable.
+    fn visit_lhs(&mut self, lhs: &ast::Expr) {
+        match lhs {
+            ast::Expr::Var(_) => {}
+            ast::Expr::Member { lhs, .. } => lhs.visit(self),
+            ast::Expr::Index { lhs, indices } => {
+                self.visit_lhs(lhs);
+                for index in indices {
+                    index.visit(self);
+                }
+            }
+            lhs => lhs.visit(self),
+        }
+    }
+}
+
+impl ast::Visitor for Linter<'_> {
+    fn stmt(&mut self, value: &ast::Stmt) -> bool {
+        match value {
+            ast::Stmt::Assign { pos, .. } | ast::Stmt::Expr { pos, .. } => {
+                self.pos = Some(*pos);
+            }
+            ast::Stmt::If { cond, .. } => {
+                self.check_cond("condition", cond);
+            }
+            ast::Stmt::While { pos, cond, .. } | ast::Stmt::For { pos, cond, .. } => {
+                self.pos = Some(*pos);
+                self.check_cond("condition", cond);
+            }
+            ast::Stmt::Repeat { pos, count, .. } => {
+                self.pos = Some(*pos);
+                self.check_cond("repeat count", count);
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn assign(&mut self, value: &ast::Assign) -> bool {
+        self.visit_lhs(&value.lhs);
+        value.rhs.visit(self);
+        false
+    }
+
+    fn expr(&mut self, value: &ast::Expr) -> bool {
+        if let ast::Expr::Call {
+            pos, name, builtin, ..
+        } = value
+        {
+            self.pos = Some(*pos);
+            if builtin.is_none() && !self.known.functions.contains(name) {
+                self.push(format!("call to unknown function `{name}`"));
+            }
+        }
+        true
+    }
+
+    fn var(&mut self, value: &ast::Var) {
+        let ast::Var::Local(name) = value else {
+            return;
+        };
+        if self.assigned.contains(name)
+            || self.known.names.contains(name)
+            || name.starts_with("argument")
+        {
+            return;
+        }
+        self.push(format!("read of never assigned variable `{name}`"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lint_source(source: &str) -> Vec<String> {
+        let script = crate::parse("test", source).unwrap();
+        let known = Known {
+            functions: ["show_message".to_string()].into(),
+            names: ["x".to_string()].into(),
+        };
+        lint(&script, &known)
+            .into_iter()
+            .map(|lint| lint.message)
+            .collect()
+    }
+
+    #[test]
+    fn test_assign_in_condition() {
+        assert_eq!(lint_source("var a; a = 1; if (a == 1) a = 2;").len(), 0);
+        let lints = lint_source("var a; a = 1; if (a = 1) a = 2;");
+        assert_eq!(lints.len(), 1);
+        assert!(lints[0].starts_with("assignment used as condition"));
+
+        let lints = lint_source("var a; a = 1; while (a = 1) a += 1;");
+        assert_eq!(lints.len(), 1);
+        assert!(lints[0].starts_with("assignment used as condition"));
+        let lints = lint_source("var i, n; n = 3; for (i = 0; i = n; i += 1) {}");
+        assert_eq!(lints.len(), 1);
+        assert!(lints[0].starts_with("assignment used as condition"));
+        let lints = lint_source("var n; n = 3; repeat (n = 2) {}");
+        assert_eq!(lints.len(), 1);
+        assert!(lints[0].starts_with("assignment used as repeat count"));
+        assert_eq!(lint_source("var i; for (i = 0; i < 3; i += 1) {}").len(), 0);
+    }
+
+    #[test]
+    fn test_unknown_function() {
+        assert_eq!(lint_source("show_message(x);").len(), 0);
+        assert_eq!(
+            lint_source("show_messag(x);"),
+            ["call to unknown function `show_messag`"]
+        );
+    }
+
+    #[test]
+    fn test_unassigned_read() {
+        assert_eq!(lint_source("y = x + argument0; x = y;").len(), 0);
+        assert_eq!(
+            lint_source("y = z;"),
+            ["read of never assigned variable `z`"]
+        );
+        // assignment targets aren't reads, but their indices are
+        assert_eq!(
+            lint_source("a[i] = 1;"),
+            ["read of never assigned variable `i`"]
+        );
+    }
+}
@@ -36,6 +36,191 @@ pub fn parse(name: &str, input: &str) -> anyhow::Result<Script> {
     Ok(Script { name, stmts })
 }
 
+/// A syntax error from [`parse_recovering()`], `line` and `col` are 1-based.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    pub pos: usize,
+    pub line: usize,
+    pub col: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.col, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Like [`parse()`], but when a statement fails to parse, skips to the next statement
+/// boundary and keeps going, to report all the errors in the script. The script has the
+/// statements that did parse, or is `None` if there were only errors.
+pub fn parse_recovering(name: &str, input: &str) -> (Option<Script>, Vec<ParseError>) {
+    let mut stmts = vec![];
+    let mut errors = vec![];
+    let mut offset = 0;
+    loop {
+        match G::parse(Rule::next_stmt, &input[offset..]) {
+            Ok(mut pairs) => {
+                let pair = pairs.next().unwrap();
+                if pair.as_rule() == Rule::EOI {
+                    break;
+                }
+                let mut stmt = parse_stmt(pair.clone());
+                // positions are in the rest of the input
+                shift_stmt(&mut stmt, line_col(input, offset));
+                stmts.push(stmt);
+                offset += pair.as_span().end();
+            }
+            Err(error) => {
+                let pos = offset
+                    + match error.location {
+                        pest::error::InputLocation::Pos(pos) => pos,
+                        pest::error::InputLocation::Span((start, _)) => start,
+                    };
+                let Pos { line, column } = line_col(input, pos);
+                errors.push(ParseError {
+                    pos,
+                    line,
+                    col: column,
+                    message: error.variant.message().into_owned(),
+                });
+                offset = skip_stmt(input, offset);
+            }
+        }
+    }
+
+    let script = (errors.is_empty() || !stmts.is_empty()).then(|| Script {
+        name: name.to_string(),
+        stmts,
+    });
+    (script, errors)
+}
+
+/// The 1-based line and column of the byte offset `pos` in `input`.
+fn line_col(input: &str, pos: usize) -> Pos {
+    let line_start = input[..pos].rfind('\n').map_or(0, |index| index + 1);
+    Pos {
+        line: input[..pos].matches('\n').count() + 1,
+        column: input[line_start..pos].chars().count() + 1,
+    }
+}
+
+/// Moves positions parsed from the part of the input starting at `start` to where they are
+/// in the whole input.
+fn shift_stmt(stmt: &mut Stmt, start: Pos) {
+    if let Stmt::Assign { pos, .. }
+    | Stmt::Expr { pos, .. }
+    | Stmt::Repeat { pos, .. }
+    | Stmt::While { pos, .. }
+    | Stmt::For { pos, .. } = stmt
+    {
+        shift_pos(pos, start);
+    }
+    match stmt {
+        Stmt::Var(decls) => {
+            for (_, init) in decls {
+                if let Some(init) = init {
+                    shift_expr(init, start);
+                }
+            }
+        }
+        Stmt::Assign { assign, .. } => shift_assign(assign, start),
+        Stmt::Expr { expr, .. } => shift_expr(expr, start),
+        Stmt::If { cond, body, alt } => {
+            shift_expr(cond, start);
+            shift_stmt(body, start);
+            if let Some(alt) = alt {
+                shift_stmt(alt, start);
+            }
+        }
+        Stmt::Repeat {
+            count: cond, body, ..
+        }
+        | Stmt::While { cond, body, .. }
+        | Stmt::With { obj: cond, body } => {
+            shift_expr(cond, start);
+            shift_stmt(body, start);
+        }
+        Stmt::For {
+            assign,
+            cond,
+            update,
+            body,
+            ..
+        } => {
+            shift_assign(assign, start);
+            shift_expr(cond, start);
+            shift_assign(update, start);
+            shift_stmt(body, start);
+        }
+        Stmt::Return { expr: Some(expr) } => shift_expr(expr, start),
+        Stmt::Block { stmts } => {
+            for stmt in stmts {
+                shift_stmt(stmt, start);
+            }
+        }
+        Stmt::Return { expr: None } | Stmt::Exit | Stmt::Break | Stmt::Continue | Stmt::Empty => {}
+    }
+}
+
+fn shift_assign(assign: &mut Assign, start: Pos) {
+    shift_expr(&mut assign.lhs, start);
+    shift_expr(&mut assign.rhs, start);
+}
+
+fn shift_expr(expr: &mut Expr, start: Pos) {
+    match expr {
+        Expr::Var(_) | Expr::Int(_) | Expr::Float(_) | Expr::String(_) => {}
+        Expr::Unary { expr, .. } => shift_expr(expr, start),
+        Expr::Binary { lhs, rhs, .. } => {
+            shift_expr(lhs, start);
+            shift_expr(rhs, start);
+        }
+        Expr::Member { lhs, .. } | Expr::InstanceProperty { id: lhs, .. } => shift_expr(lhs, start),
+        Expr::Index { lhs, indices } => {
+            shift_expr(lhs, start);
+            for index in indices {
+                shift_expr(index, start);
+            }
+        }
+        Expr::Call { pos, args, .. } => {
+            shift_pos(pos, start);
+            for arg in args {
+                shift_expr(arg, start);
+            }
+        }
+    }
+}
+
+fn shift_pos(pos: &mut Pos, start: Pos) {
+    if pos.line == 1 {
+        pos.column += start.column - 1;
+    }
+    pos.line += start.line - 1;
+}
+
+/// The offset after the next `;` or matching `}` from `offset`, ignoring those in strings,
+/// or the end of `input`. Always skips at least one character.
+fn skip_stmt(input: &str, offset: usize) -> usize {
+    let mut depth = 0;
+    let mut quote = None;
+    for (index, char) in input[offset..].char_indices() {
+        match (quote, char) {
+            (Some(q), _) if char == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(char),
+            (None, '{') => depth += 1,
+            (None, '}') if depth > 1 => depth -= 1,
+            (None, ';') if depth > 0 => {}
+            (None, ';' | '}') => return offset + index + 1,
+            _ => {}
+        }
+    }
+    input.len()
+}
+
 pub fn parse_expr(input: &str) -> anyhow::Result<Box<Expr>> {
     let mut pairs = G::parse(Rule::expr, input)?;
     Ok(parse_expr_pair(pairs.next().unwrap()))
@@ -106,20 +291,27 @@ fn parse_stmt(pair: Pair<'_, Rule>) -> Box<Stmt> {
             Box::new(Stmt::If { cond, body, alt })
         }
         Rule::repeat_stmt => {
+            let pos = Pos::from(pair.line_col());
             let mut inner = pair.into_inner();
             assert_eq!(inner.next().unwrap().as_rule(), Rule::kw_repeat);
             let count = parse_expr_pair(inner.next().unwrap());
             let stmt = parse_stmt(inner.next().unwrap());
-            Box::new(Stmt::Repeat { count, body: stmt })
+            Box::new(Stmt::Repeat {
+                pos,
+                count,
+                body: stmt,
+            })
         }
         Rule::while_stmt => {
+            let pos = Pos::from(pair.line_col());
             let mut inner = pair.into_inner();
             assert_eq!(inner.next().unwrap().as_rule(), Rule::kw_while);
             let cond = parse_expr_pair(inner.next().unwrap());
             let body = parse_stmt(inner.next().unwrap());
-            Box::new(Stmt::While { cond, body })
+            Box::new(Stmt::While { pos, cond, body })
         }
         Rule::for_stmt => {
+            let pos = Pos::from(pair.line_col());
             let mut inner = pair.into_inner();
             assert_eq!(inner.next().unwrap().as_rule(), Rule::kw_for);
             let assign = parse_assign(inner.next().unwrap());
@@ -127,6 +319,7 @@ fn parse_stmt(pair: Pair<'_, Rule>) -> Box<Stmt> {
             let update = parse_assign(inner.next().unwrap());
             let body = parse_stmt(inner.next().unwrap());
             Box::new(Stmt::For {
+                pos,
                 assign,
                 cond,
                 update,
@@ -143,10 +336,12 @@ fn parse_stmt(pair: Pair<'_, Rule>) -> Box<Stmt> {
         Rule::return_stmt => {
             let mut inner = pair.into_inner();
             assert_eq!(inner.next().unwrap().as_rule(), Rule::kw_return);
-            let expr = parse_expr_pair(inner.next().unwrap());
+            let expr = inner.next().map(parse_expr_pair);
             Box::new(Stmt::Return { expr })
         }
         Rule::exit_stmt => Box::new(Stmt::Exit),
+        Rule::break_stmt => Box::new(Stmt::Break),
+        Rule::continue_stmt => Box::new(Stmt::Continue),
         Rule::block_stmt => {
             let inner = pair.into_inner();
             let stmts = inner.map(parse_stmt).collect();
@@ -155,8 +350,14 @@ fn parse_stmt(pair: Pair<'_, Rule>) -> Box<Stmt> {
         Rule::var_stmt => {
             let mut inner = pair.into_inner();
             assert_eq!(inner.next().unwrap().as_rule(), Rule::kw_var);
-            let id = inner.next().unwrap().as_str().into();
-            Box::new(Stmt::Var(id))
+            let decls = inner
+                .map(|decl| {
+                    let mut inner = decl.into_inner();
+                    let id = inner.next().unwrap().as_str().into();
+                    (id, inner.next().map(parse_expr_pair))
+                })
+                .collect();
+            Box::new(Stmt::Var(decls))
         }
         Rule::assign_stmt => {
             let pos = Pos::from(pair.line_col());
@@ -190,6 +391,8 @@ fn parse_assign_lhs(pair: Pair<'_, Rule>) -> Box<Expr> {
     let id = inner.next().unwrap();
     let mut lhs = match id.as_rule() {
         Rule::var => Box::new(Expr::Var(parse_var(id))),
+        // instance_nearest(...).member
+        Rule::call_expr => parse_call(id, &pratt()),
         Rule::assign_id_property => {
             let mut inner = id.into_inner();
             let id = inner.next().unwrap();
@@ -233,6 +436,19 @@ fn parse_assign(pair: Pair<'_, Rule>) -> Assign {
     Assign { lhs, op, rhs }
 }
 
+fn parse_call(pair: Pair<'_, Rule>, pratt: &PrattParser<Rule>) -> Box<Expr> {
+    let pos = Pos::from(pair.line_col());
+    let mut inner = pair.into_inner();
+    let id = inner.next().unwrap().as_str();
+    let args = inner.map(|pair| parse_expr_rec(pair, pratt)).collect();
+    Box::new(Expr::Call {
+        pos,
+        name: id.into(),
+        args,
+        builtin: Builtin::from_name(id),
+    })
+}
+
 fn parse_expr_pair(pair: Pair<'_, Rule>) -> Box<Expr> {
     parse_expr_rec(pair, &pratt())
 }
@@ -243,17 +459,7 @@ fn parse_expr_rec(pair: Pair<'_, Rule>, pratt: &PrattParser<Rule>) -> Box<Expr>
         .map_primary(|primary| {
             match primary.as_rule() {
                 Rule::expr => parse_expr_rec(primary, pratt),
-                Rule::call_expr => {
-                    let pos = Pos::from(primary.line_col());
-                    let mut inner = primary.into_inner();
-                    let id = inner.next().unwrap().as_str().into();
-                    let args = inner.map(|pair| parse_expr_rec(pair, pratt)).collect();
-                    Box::new(Expr::Call {
-                        pos,
-                        name: id,
-                        args,
-                    })
-                }
+                Rule::call_expr => parse_call(primary, pratt),
                 Rule::var => Box::new(Expr::Var(parse_var(primary))),
                 Rule::int => Box::new(Expr::Int(primary.as_str().parse().unwrap())),
                 Rule::float => Box::new(Expr::Float(primary.as_str().parse().unwrap())),
@@ -313,6 +519,7 @@ fn parse_expr_rec(pair: Pair<'_, Rule>, pratt: &PrattParser<Rule>) -> Box<Expr>
                 Rule::gt => BinaryOp::Gt,
                 Rule::ne => BinaryOp::Ne,
                 Rule::eq => BinaryOp::Eq,
+                Rule::assign_eq => BinaryOp::AssignEq,
                 Rule::add => BinaryOp::Add,
                 Rule::sub => BinaryOp::Sub,
                 Rule::mul => BinaryOp::Mul,
@@ -336,7 +543,8 @@ fn pratt() -> PrattParser<Rule> {
             | Op::infix(Rule::ge, Assoc::Left)
             | Op::infix(Rule::gt, Assoc::Left)
             | Op::infix(Rule::ne, Assoc::Left)
-            | Op::infix(Rule::eq, Assoc::Left))
+            | Op::infix(Rule::eq, Assoc::Left)
+            | Op::infix(Rule::assign_eq, Assoc::Left))
         .op(Op::infix(Rule::bit_and, Assoc::Left)
             | Op::infix(Rule::bit_or, Assoc::Left)
             | Op::infix(Rule::bit_xor, Assoc::Left))
@@ -387,4 +595,183 @@ mod tests {
             ]
         }
     }
+
+    fn if_cond(source: &str) -> crate::ast::Expr {
+        let script = crate::parse("test", source).unwrap();
+        let crate::ast::Stmt::If { cond, .. } = *script.stmts[0].clone() else {
+            panic!("expected if statement");
+        };
+        *cond
+    }
+
+    #[test]
+    fn test_compat_operators() {
+        use crate::ast::{BinaryOp, Expr};
+
+        assert!(matches!(
+            if_cond("if (x <> 3) y = 1;"),
+            Expr::Binary {
+                op: BinaryOp::Ne,
+                ..
+            }
+        ));
+        assert!(matches!(
+            if_cond("if (x = 3) y = 1;"),
+            Expr::Binary {
+                op: BinaryOp::AssignEq,
+                ..
+            }
+        ));
+        assert!(matches!(
+            if_cond("if (x < 3) y = 1;"),
+            Expr::Binary {
+                op: BinaryOp::Lt,
+                ..
+            }
+        ));
+
+        let script = crate::parse("test", "x := 3;").unwrap();
+        assert!(matches!(*script.stmts[0], crate::ast::Stmt::Assign { .. }));
+    }
+
+    #[test]
+    fn test_call_args() {
+        let args = |source: &str| {
+            let expr = crate::parse_expr(source).unwrap();
+            let crate::ast::Expr::Call { args, .. } = *expr else {
+                panic!("expected call");
+            };
+            args.len()
+        };
+        assert_eq!(args("foo()"), 0);
+        assert_eq!(args("foo(1,)"), 1);
+        assert_eq!(args("foo(1, 2,)"), 2);
+        assert!(crate::parse("test", "foo(,);").is_err());
+        // omitted arguments are an error, rather than undefined
+        assert!(crate::parse("test", "foo(1,,3);").is_err());
+        assert!(crate::parse("test", "x = a[1,];").is_ok());
+    }
+
+    #[test]
+    fn test_var_decls() {
+        let script = crate::parse("test", "var a = 1, b;").unwrap();
+        let crate::ast::Stmt::Var(decls) = &*script.stmts[0] else {
+            panic!("expected var statement");
+        };
+        assert_eq!(decls.len(), 2);
+        assert_eq!(decls[0].0, "a");
+        assert_eq!(decls[0].1.as_ref().unwrap().to_string(), "1");
+        assert_eq!(decls[1].0, "b");
+        assert!(decls[1].1.is_none());
+    }
+
+    #[test]
+    fn test_bare_return() {
+        use crate::ast::Stmt;
+
+        let script = crate::parse("test", "if (x) return; y = 1;").unwrap();
+        assert_eq!(script.stmts.len(), 2);
+        let Stmt::If { body, .. } = &*script.stmts[0] else {
+            panic!("expected if statement");
+        };
+        assert!(matches!(**body, Stmt::Return { expr: None }));
+        assert!(matches!(*script.stmts[1], Stmt::Assign { .. }));
+
+        let script = crate::parse("test", "{ return }").unwrap();
+        let Stmt::Block { stmts } = &*script.stmts[0] else {
+            panic!("expected block statement");
+        };
+        assert!(matches!(*stmts[0], Stmt::Return { expr: None }));
+    }
+
+    #[test]
+    fn test_comments() {
+        use crate::ast::Stmt;
+
+        let source = "x = \"http://a/*b*/\"; /* one // two\nthree */ y = 2; // z = 3;\nw = 4;";
+        let script = crate::parse("test", source).unwrap();
+        let assigns = script
+            .stmts
+            .iter()
+            .map(|stmt| match &**stmt {
+                Stmt::Assign { assign, .. } => assign.to_string(),
+                stmt => panic!("expected assignment: {stmt:?}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(assigns, ["x = \"http://a/*b*/\"", "y = 2", "w = 4"]);
+
+        assert!(crate::parse("test", "x = 1; /* not closed").is_err());
+    }
+
+    #[test]
+    fn test_parse_recovering() {
+        let source = "x = 1;\ny = (;\nz = 2;\nw = 3 +* 4;\nv = \"a;b\";\n";
+        let (script, errors) = crate::parse_recovering("test", source);
+        assert_eq!(
+            errors.iter().map(|error| error.line).collect::<Vec<_>>(),
+            [2, 4]
+        );
+        let script = script.unwrap();
+        assert!(matches!(
+            script.stmts.last().map(|stmt| &**stmt),
+            Some(crate::ast::Stmt::Assign { .. })
+        ));
+
+        // positions are in the whole script, not from where parsing restarted
+        let source = "if (; y = 1;\nz = f(2);";
+        let (script, errors) = crate::parse_recovering("test", source);
+        assert_eq!(errors.len(), 1);
+        let script = script.unwrap();
+        let pos = |stmt: &crate::ast::Stmt| stmt.pos().map(|pos| (pos.line, pos.column));
+        assert_eq!(pos(&script.stmts[0]), Some((1, 7)));
+        assert_eq!(pos(&script.stmts[1]), Some((2, 1)));
+        let crate::ast::Stmt::Assign { assign, .. } = &*script.stmts[1] else {
+            panic!("expected an assignment: {:?}", script.stmts[1]);
+        };
+        let crate::ast::Expr::Call { pos, .. } = &*assign.rhs else {
+            panic!("expected a call: {:?}", assign.rhs);
+        };
+        assert_eq!((pos.line, pos.column), (2, 5));
+
+        let (script, errors) = crate::parse_recovering("test", "if { x = ; }");
+        assert!(script.is_none());
+        assert_eq!(errors.len(), 1);
+
+        let (script, errors) = crate::parse_recovering("test", "x = 1;");
+        assert_eq!(script.unwrap().stmts.len(), 1);
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_mod_div() {
+        use crate::ast::{BinaryOp, Expr};
+
+        let binary_op = |source| match *crate::parse_expr(source).unwrap() {
+            Expr::Binary { op, .. } => op,
+            expr => panic!("expected binary op: {expr:?}"),
+        };
+        assert!(matches!(binary_op("7 mod 3"), BinaryOp::IMod));
+        assert!(matches!(binary_op("7 % 3"), BinaryOp::IMod));
+        assert!(matches!(binary_op("7 div 3"), BinaryOp::IDiv));
+
+        // only whole words are keywords
+        assert!(matches!(binary_op("modifier + mod_x"), BinaryOp::Add));
+        assert!(matches!(binary_op("divide * div2"), BinaryOp::Mul));
+        assert!(crate::parse("test", "var modifier, mod_x;").is_ok());
+
+        assert!(crate::parse("test", "var mod;").is_err());
+        assert!(crate::parse("test", "div = 1;").is_err());
+    }
+
+    #[test]
+    fn test_serde_round_trip() {
+        let source = "var i, s;\nfor (i = 0; i < 3; i += 1) { s[i] = -i * 1.5; }\n\
+            with (obj_test) { if (!solid) x = other.x; else break; }\n\
+            return string(s[2]) + \"done\";";
+        let script = crate::parse("test", source).unwrap();
+        let json = serde_json::to_string(&script).unwrap();
+        let cached: crate::ast::Script = serde_json::from_str(&json).unwrap();
+        // the AST has no PartialEq, but Debug shows every field
+        assert_eq!(format!("{cached:?}"), format!("{script:?}"));
+    }
 }
@@ -1,12 +1,15 @@
 use std::cell::RefCell;
+use std::collections::BTreeSet;
 
 use macroquad::prelude::*;
 use serde::Serialize;
 
+pub use atlas::Atlas;
 pub use background::BackgroundAsset;
 pub use imp::*;
-pub use sprite::SpriteAsset;
+pub use sprite::{take_texture_switches, SpriteAsset};
 
+mod atlas;
 mod imp;
 
 mod background;
@@ -16,34 +19,119 @@ mod sprite;
 pub struct Assets {
     pub backgrounds: AssetSet<BackgroundAsset>,
     pub sprites: AssetSet<SpriteAsset>,
+    #[serde(skip)]
+    pub atlas: Atlas,
+    /// [`take_texture_switches()`] for the last frame drawn.
+    pub texture_switches: u32,
+}
+
+impl Assets {
+    pub fn clear(&mut self) {
+        self.backgrounds.clear();
+        self.sprites.clear();
+        // after the sprites, which may still be using its pages
+        self.atlas.clear();
+    }
 }
 
 pub struct Loader<'a> {
     content: &'a gmk_file::Content,
     assets: &'a RefCell<Assets>,
+    filter: FilterMode,
 }
 
 impl<'a> Loader<'a> {
     pub fn new(content: &'a gmk_file::Content, assets: &'a RefCell<Assets>) -> Self {
-        Self { content, assets }
+        Self {
+            content,
+            assets,
+            filter: texture_filter(content.settings.interpolate.into()),
+        }
     }
 
-    pub fn get_background(&mut self, index: u32) -> AssetId<background::BackgroundAsset> {
-        self.assets
-            .borrow_mut()
-            .backgrounds
-            .load(&self.content.backgrounds, index)
+    /// `None` if there's no background `index`.
+    pub fn get_background(&mut self, index: u32) -> Option<AssetId<background::BackgroundAsset>> {
+        let assets = &mut *self.assets.borrow_mut();
+        assets.backgrounds.load(
+            &self.content.backgrounds,
+            index,
+            self.filter,
+            &mut assets.atlas,
+        )
+    }
+
+    /// Like [`Self::try_get_sprite()`], for a background passed to a script.
+    pub fn try_get_background(
+        &mut self,
+        index: i32,
+    ) -> Option<AssetId<background::BackgroundAsset>> {
+        let index = existing_index(&self.content.backgrounds, index)?;
+        self.get_background(index)
     }
 
-    pub fn get_sprite(&mut self, index: u32) -> AssetId<sprite::SpriteAsset> {
-        self.assets
-            .borrow_mut()
+    /// `None` if there's no sprite `index`.
+    pub fn get_sprite(&mut self, index: u32) -> Option<AssetId<sprite::SpriteAsset>> {
+        let assets = &mut *self.assets.borrow_mut();
+        assets
             .sprites
-            .load(&self.content.sprites, index)
+            .load(&self.content.sprites, index, self.filter, &mut assets.atlas)
+    }
+
+    /// The sprite for an instance `sprite_index` or `mask_index`, if there is one. GM uses
+    /// -1 for no sprite, and scripts may refer to deleted sprites.
+    pub fn try_get_sprite(&mut self, index: i32) -> Option<AssetId<sprite::SpriteAsset>> {
+        let index = existing_index(&self.content.sprites, index)?;
+        self.get_sprite(index)
+    }
+
+    /// Decodes the images used by a room in parallel, so the first frame doesn't stall
+    /// loading them one at a time. Textures are still created on first use.
+    pub fn preload_room(&mut self, def: &gmk_file::Room) {
+        let (sprites, backgrounds) = room_asset_indices(self.content, def);
+        let mut assets = self.assets.borrow_mut();
+        assets.sprites.preload(&self.content.sprites, &sprites);
+        assets
+            .backgrounds
+            .preload(&self.content.backgrounds, &backgrounds);
+    }
+}
+
+/// The sprite and background indices a room uses when it starts.
+fn room_asset_indices(
+    content: &gmk_file::Content,
+    def: &gmk_file::Room,
+) -> (BTreeSet<u32>, BTreeSet<u32>) {
+    let sprites = def
+        .instances
+        .iter()
+        .flat_map(|res| {
+            let object = &content.objects[res.object_index];
+            [object.sprite_index, object.mask_sprite_index]
+        })
+        .filter_map(|index| index.try_into().ok())
+        .collect();
+
+    let backgrounds = def
+        .backgrounds
+        .iter()
+        .filter_map(|b| b.background_image_index.try_into().ok())
+        .chain(def.tiles.iter().map(|t| t.background_index))
+        .collect();
+
+    (sprites, backgrounds)
+}
+
+/// GM only smooths textures if the game's "interpolate colors between pixels" setting is on,
+/// otherwise pixel art stays sharp when scaled.
+fn texture_filter(interpolate: bool) -> FilterMode {
+    if interpolate {
+        FilterMode::Linear
+    } else {
+        FilterMode::Nearest
     }
 }
 
-fn texture_from_data(data: &[u8], transparent: bool) -> Texture2D {
+fn image_from_data(data: &[u8], transparent: bool) -> Image {
     let mut image = Image::from_file_with_format(data, None);
 
     // the bottom left pixel is the transparent pixel color
@@ -58,5 +146,61 @@ fn texture_from_data(data: &[u8], transparent: bool) -> Texture2D {
         }
     }
 
-    Texture2D::from_image(&image)
+    image
+}
+
+fn existing_index<T>(chunk: &gmk_file::ResourceChunk<T>, index: i32) -> Option<u32> {
+    let index = u32::try_from(index).ok()?;
+    chunk.get_item(index).map(|_| index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_texture_filter() {
+        assert_eq!(texture_filter(true), FilterMode::Linear);
+        assert_eq!(texture_filter(false), FilterMode::Nearest);
+    }
+
+    #[test]
+    fn test_existing_index() {
+        let chunk = gmk_file::ResourceChunk {
+            ver: 0,
+            items: vec![
+                Some(gmk_file::ResourceItem {
+                    name: gmk_file::String32("spr_player".to_string()),
+                    data: (),
+                }),
+                None,
+            ],
+        };
+        assert_eq!(existing_index(&chunk, 0), Some(0));
+        // no sprite
+        assert_eq!(existing_index(&chunk, -1), None);
+        // deleted
+        assert_eq!(existing_index(&chunk, 1), None);
+        assert_eq!(existing_index(&chunk, 2), None);
+    }
+
+    /// Reads the game file, which isn't in the repo, see README.
+    #[test]
+    #[ignore]
+    fn test_preload_room() {
+        let content = gmk_file::parse(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/ref/source code/iji.gmk"
+        ));
+        let assets = RefCell::default();
+        let def = &content.rooms[content.room_order.items[0]];
+        Loader::new(&content, &assets).preload_room(def);
+
+        let (sprites, backgrounds) = room_asset_indices(&content, def);
+        let assets = assets.borrow();
+        assert!(sprites.iter().all(|&index| assets.sprites.is_staged(index)));
+        assert!(backgrounds
+            .iter()
+            .all(|&index| assets.backgrounds.is_staged(index)));
+    }
 }
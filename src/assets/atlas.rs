@@ -0,0 +1,168 @@
+use macroquad::prelude::*;
+
+/// Sprite frames are copied into shared textures of this size, so drawing different sprites
+/// doesn't need to bind a different texture each time.
+const PAGE_SIZE: u32 = 1024;
+
+/// Space left between rects, so linear filtering doesn't sample a neighbouring frame.
+const PADDING: u32 = 1;
+
+/// Where to put rects in a fixed size page, in rows ("shelves") as tall as the first rect
+/// put in them. Rects are never freed, the whole page is dropped instead.
+#[derive(Debug)]
+pub struct Packer {
+    size: UVec2,
+    shelves: Vec<Shelf>,
+}
+
+#[derive(Debug)]
+struct Shelf {
+    y: u32,
+    height: u32,
+    /// Where the next rect goes.
+    x: u32,
+}
+
+impl Packer {
+    pub fn new(size: UVec2) -> Self {
+        Self {
+            size,
+            shelves: Vec::new(),
+        }
+    }
+
+    /// The top-left of where a rect of `size` goes, or `None` if it doesn't fit in the rest
+    /// of the page.
+    pub fn alloc(&mut self, size: UVec2) -> Option<UVec2> {
+        let padded = size + UVec2::splat(PADDING);
+        if padded.x > self.size.x || padded.y > self.size.y {
+            return None;
+        }
+
+        // the shortest shelf it fits in, so short rects don't use up tall shelves
+        let shelf = self
+            .shelves
+            .iter_mut()
+            .filter(|shelf| shelf.height >= padded.y && shelf.x + padded.x <= self.size.x)
+            .min_by_key(|shelf| shelf.height);
+        let shelf = match shelf {
+            Some(shelf) => shelf,
+            None => {
+                let y = self.shelves.last().map_or(0, |last| last.y + last.height);
+                if y + padded.y > self.size.y {
+                    return None;
+                }
+                self.shelves.push(Shelf {
+                    y,
+                    height: padded.y,
+                    x: 0,
+                });
+                self.shelves.last_mut().unwrap()
+            }
+        };
+
+        let pos = uvec2(shelf.x, shelf.y);
+        shelf.x += padded.x;
+        Some(pos)
+    }
+}
+
+/// Textures that sprite frames are packed into. Pages are only deleted by [`Self::clear()`],
+/// as the sprites using them only share them.
+#[derive(Debug)]
+pub struct Atlas {
+    /// If false every frame gets its own texture, e.g. to compare how many draw calls the
+    /// atlas saves.
+    pub enabled: bool,
+    pages: Vec<(Texture2D, Packer)>,
+}
+
+impl Default for Atlas {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            pages: Vec::new(),
+        }
+    }
+}
+
+impl Atlas {
+    /// Copies `image` into a page, returning the page and where in it the image is, or `None`
+    /// if packing is disabled or the image is too big for a page.
+    pub fn add(&mut self, image: &Image, filter: FilterMode) -> Option<(Texture2D, Rect)> {
+        if !self.enabled {
+            return None;
+        }
+        let size = uvec2(image.width.into(), image.height.into());
+
+        let found = self
+            .pages
+            .iter_mut()
+            .find_map(|(texture, packer)| Some((*texture, packer.alloc(size)?)));
+        let (texture, pos) = match found {
+            Some(found) => found,
+            None => {
+                let mut packer = Packer::new(UVec2::splat(PAGE_SIZE));
+                let pos = packer.alloc(size)?;
+                let texture = Texture2D::from_rgba8(
+                    PAGE_SIZE as u16,
+                    PAGE_SIZE as u16,
+                    &vec![0; (PAGE_SIZE * PAGE_SIZE * 4) as usize],
+                );
+                texture.set_filter(filter);
+                self.pages.push((texture, packer));
+                log::debug!("atlas page {} created", self.pages.len());
+                (texture, pos)
+            }
+        };
+
+        texture.update_part(
+            image,
+            pos.x as i32,
+            pos.y as i32,
+            size.x as i32,
+            size.y as i32,
+        );
+        let source = Rect::new(pos.x as f32, pos.y as f32, size.x as f32, size.y as f32);
+        Some((texture, source))
+    }
+
+    /// Deletes all the pages, so only call this when dropping every sprite using them.
+    pub fn clear(&mut self) {
+        for (texture, _) in self.pages.drain(..) {
+            texture.delete();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_packer() {
+        let mut packer = Packer::new(uvec2(64, 64));
+
+        // first shelf is as tall as the first rect, plus padding
+        assert_eq!(packer.alloc(uvec2(16, 16)), Some(uvec2(0, 0)));
+        assert_eq!(packer.alloc(uvec2(16, 8)), Some(uvec2(17, 0)));
+        // too tall for the first shelf
+        assert_eq!(packer.alloc(uvec2(8, 24)), Some(uvec2(0, 17)));
+        // short rects go in the shortest shelf they fit in
+        assert_eq!(packer.alloc(uvec2(8, 8)), Some(uvec2(34, 0)));
+        // doesn't fit in the rest of the first shelf
+        assert_eq!(packer.alloc(uvec2(30, 16)), Some(uvec2(9, 17)));
+
+        // bigger than the page
+        assert_eq!(packer.alloc(uvec2(64, 8)), None);
+        assert_eq!(packer.alloc(uvec2(8, 64)), None);
+
+        // fills the rest of the page
+        assert_eq!(packer.alloc(uvec2(63, 21)), Some(uvec2(0, 42)));
+        assert_eq!(packer.alloc(uvec2(8, 8)), Some(uvec2(43, 0)));
+        assert_eq!(packer.alloc(uvec2(8, 8)), Some(uvec2(52, 0)));
+        // the first shelf is full, so the next shortest one
+        assert_eq!(packer.alloc(uvec2(8, 8)), Some(uvec2(40, 17)));
+        assert_eq!(packer.alloc(uvec2(24, 24)), None);
+    }
+}
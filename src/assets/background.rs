@@ -1,8 +1,8 @@
 use glam::{uvec2, UVec2};
-use macroquad::prelude::Texture2D;
+use macroquad::prelude::{FilterMode, Image, Texture2D};
 use serde::Serialize;
 
-use super::{texture_from_data, Asset};
+use super::{image_from_data, Asset, Atlas};
 
 #[derive(Serialize)]
 pub struct BackgroundAsset {
@@ -22,10 +22,21 @@ impl Drop for BackgroundAsset {
 
 impl Asset for BackgroundAsset {
     type Resource = gmk_file::Background;
+    type Decoded = Image;
 
-    fn load(def: &gmk_file::Background) -> Self {
+    fn decode(def: &gmk_file::Background) -> Image {
         let data = def.image.as_ref().unwrap().data.as_ref().unwrap();
-        let texture = texture_from_data(data, def.transparent.into());
+        image_from_data(data, def.transparent.into())
+    }
+
+    fn load(
+        def: &gmk_file::Background,
+        image: Image,
+        filter: FilterMode,
+        _atlas: &mut Atlas,
+    ) -> Self {
+        let texture = Texture2D::from_image(&image);
+        texture.set_filter(filter);
         // always present since GM 5.x
         let tiling = def.tiling.as_ref().unwrap();
 
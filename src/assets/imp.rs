@@ -1,11 +1,24 @@
+use macroquad::texture::FilterMode;
 use serde::{Serialize, Serializer};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::marker::PhantomData;
 
+use super::Atlas;
+
 pub trait Asset {
-    type Resource;
+    type Resource: Sync;
+    /// The CPU-side data, which unlike the GPU textures can be created off the main thread.
+    type Decoded: Send;
+
+    fn decode(res: &Self::Resource) -> Self::Decoded;
 
-    fn load(res: &Self::Resource) -> Self;
+    /// Creates the textures, which are sampled with `filter`, or adds the images to `atlas`.
+    fn load(
+        res: &Self::Resource,
+        decoded: Self::Decoded,
+        filter: FilterMode,
+        atlas: &mut Atlas,
+    ) -> Self;
 }
 
 pub struct AssetId<T>(u32, PhantomData<T>);
@@ -43,36 +56,178 @@ impl<T> Clone for AssetId<T> {
 impl<T> Copy for AssetId<T> {}
 
 #[derive(Serialize)]
-pub struct AssetSet<T> {
+pub struct AssetSet<T: Asset> {
     indices: HashMap<String, u32>,
     items: HashMap<u32, (String, T)>,
+    /// Decoded by `preload()`, waiting to be loaded on first use.
+    #[serde(skip)]
+    staging: HashMap<u32, T::Decoded>,
 }
 
-impl<T> Default for AssetSet<T> {
+impl<T: Asset> Default for AssetSet<T> {
     fn default() -> Self {
         Self {
             indices: Default::default(),
             items: Default::default(),
+            staging: Default::default(),
         }
     }
 }
 
 impl<T: Asset> AssetSet<T> {
+    /// `None` if `index` is out of range or deleted.
     pub(super) fn load(
         &mut self,
         chunk: &gmk_file::ResourceChunk<T::Resource>,
         index: u32,
-    ) -> AssetId<T> {
-        self.items.entry(index).or_insert_with(|| {
-            let item = chunk.items.get(index as usize).unwrap().as_ref().unwrap();
-            let name = item.name.0.clone();
-            self.indices.insert(name.clone(), index);
-            (name, T::load(&item.data))
-        });
-        AssetId::new(index)
+        filter: FilterMode,
+        atlas: &mut Atlas,
+    ) -> Option<AssetId<T>> {
+        if !self.items.contains_key(&index) {
+            let (name, res) = chunk.get_item(index)?;
+            self.indices.insert(name.to_string(), index);
+            let decoded = self
+                .staging
+                .remove(&index)
+                .unwrap_or_else(|| T::decode(res));
+            self.items.insert(
+                index,
+                (name.to_string(), T::load(res, decoded, filter, atlas)),
+            );
+        }
+        Some(AssetId::new(index))
+    }
+
+    /// Decodes the assets that aren't already loaded or staged in parallel.
+    pub(super) fn preload(
+        &mut self,
+        chunk: &gmk_file::ResourceChunk<T::Resource>,
+        indices: &BTreeSet<u32>,
+    ) {
+        use rayon::prelude::*;
+
+        let pending = indices
+            .iter()
+            .copied()
+            .filter(|index| !self.items.contains_key(index) && !self.staging.contains_key(index))
+            .filter_map(|index| Some((index, chunk.get_item(index)?.1)))
+            .collect::<Vec<_>>();
+
+        let decoded = pending
+            .into_par_iter()
+            .map(|(index, res)| (index, T::decode(res)))
+            .collect::<Vec<_>>();
+
+        self.staging.extend(decoded);
+    }
+
+    /// Drops all loaded and staged assets. Any existing [`AssetId`] must be loaded again
+    /// before it's used.
+    pub fn clear(&mut self) {
+        self.indices.clear();
+        self.items.clear();
+        self.staging.clear();
+    }
+
+    #[cfg(test)]
+    pub fn is_staged(&self, index: u32) -> bool {
+        self.staging.contains_key(&index)
     }
 
     pub fn get(&self, id: AssetId<T>) -> &T {
         &self.items[&id.0].1
     }
+
+    pub fn get_mut(&mut self, id: AssetId<T>) -> &mut T {
+        &mut self.items.get_mut(&id.0).unwrap().1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    thread_local! {
+        static DROPPED: Cell<u32> = const { Cell::new(0) };
+    }
+
+    /// Stands in for an asset owning a texture, counting how many are deleted.
+    struct MockAsset;
+
+    impl Asset for MockAsset {
+        type Resource = ();
+        type Decoded = ();
+
+        fn decode(_res: &Self::Resource) -> Self::Decoded {}
+
+        fn load(
+            _res: &Self::Resource,
+            _decoded: Self::Decoded,
+            _filter: FilterMode,
+            _atlas: &mut Atlas,
+        ) -> Self {
+            Self
+        }
+    }
+
+    impl Drop for MockAsset {
+        fn drop(&mut self) {
+            DROPPED.with(|dropped| dropped.set(dropped.get() + 1));
+        }
+    }
+
+    #[test]
+    fn test_clear_drops() {
+        let dropped = || DROPPED.with(Cell::get);
+        let chunk = gmk_file::ResourceChunk {
+            ver: 0,
+            items: ["a", "b"]
+                .map(|name| {
+                    Some(gmk_file::ResourceItem {
+                        name: gmk_file::String32(name.to_string()),
+                        data: (),
+                    })
+                })
+                .into(),
+        };
+
+        let mut set = AssetSet::<MockAsset>::default();
+        let mut atlas = Atlas::default();
+        set.load(&chunk, 0, FilterMode::Linear, &mut atlas);
+        set.load(&chunk, 1, FilterMode::Linear, &mut atlas);
+        // already loaded
+        set.load(&chunk, 0, FilterMode::Linear, &mut atlas);
+        assert_eq!(dropped(), 0);
+
+        set.clear();
+        assert_eq!(dropped(), 2);
+
+        // loading again after clearing doesn't drop anything else
+        set.load(&chunk, 1, FilterMode::Linear, &mut atlas);
+        assert_eq!(dropped(), 2);
+        drop(set);
+        assert_eq!(dropped(), 3);
+    }
+
+    #[test]
+    fn test_load_missing() {
+        let chunk = gmk_file::ResourceChunk {
+            ver: 0,
+            items: vec![None],
+        };
+        let mut set = AssetSet::<MockAsset>::default();
+        let mut atlas = Atlas::default();
+        // deleted
+        assert!(set
+            .load(&chunk, 0, FilterMode::Linear, &mut atlas)
+            .is_none());
+        // out of range
+        assert!(set
+            .load(&chunk, 1, FilterMode::Linear, &mut atlas)
+            .is_none());
+        set.preload(&chunk, &BTreeSet::from([0, 1]));
+        assert!(!set.is_staged(0) && !set.is_staged(1));
+    }
 }
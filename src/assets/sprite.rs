@@ -1,7 +1,10 @@
+use std::cell::Cell;
+
+use macroquad::models::Vertex;
 use macroquad::prelude::*;
 use serde::Serialize;
 
-use super::{texture_from_data, Asset};
+use super::{image_from_data, Asset, Atlas};
 use crate::state::serialize_rect;
 
 #[derive(Serialize)]
@@ -9,7 +12,7 @@ pub struct SpriteAsset {
     pub size: UVec2,
     pub origin: IVec2,
     #[serde(skip)]
-    pub textures: Vec<Texture2D>,
+    pub frames: Vec<SpriteFrame>,
     #[serde(serialize_with = "serialize_rect")]
     pub bbox: Rect,
 }
@@ -18,24 +21,152 @@ impl SpriteAsset {
     pub fn bounds(&self, pos: Vec2) -> Rect {
         self.bbox.offset(pos - self.origin.as_vec2())
     }
+
+    /// Where to draw the top-left of the sprite so its origin is at `pos`.
+    pub fn draw_pos(&self, pos: Vec2) -> Vec2 {
+        pos - self.origin.as_vec2()
+    }
+
+    /// The part of the sprite to draw for `draw_sprite_general()`, clipped to the sprite, and
+    /// the size to draw it at. Negative scales give negative sizes, which draw mirrored.
+    pub fn part(&self, source: Rect, scale: Vec2) -> Option<(Rect, Vec2)> {
+        let size = self.size.as_vec2();
+        let source = source.intersect(Rect::new(0.0, 0.0, size.x, size.y))?;
+        Some((source, source.size() * scale))
+    }
 }
 
 impl Drop for SpriteAsset {
     fn drop(&mut self) {
-        for t in &self.textures {
-            t.delete();
+        for frame in &self.frames {
+            if frame.owned {
+                frame.texture.delete();
+            }
         }
     }
 }
 
+/// A subimage of a sprite, which is usually part of an atlas page shared with other sprites.
+#[derive(Clone, Copy, Debug)]
+pub struct SpriteFrame {
+    pub texture: Texture2D,
+    /// Where the subimage is in `texture`.
+    pub source: Rect,
+    /// If `texture` is only this subimage, rather than an atlas page.
+    owned: bool,
+}
+
+impl SpriteFrame {
+    /// Like `draw_texture_ex()`, with `params.source` relative to the subimage.
+    pub fn draw(&self, x: f32, y: f32, color: Color, params: DrawTextureParams) {
+        let source = match params.source {
+            Some(part) => part.offset(self.source.point()),
+            None => self.source,
+        };
+        let params = DrawTextureParams {
+            source: Some(source),
+            ..params
+        };
+        count_texture(self.texture);
+        draw_texture_ex(self.texture, x, y, color, params);
+    }
+
+    /// Draws the subimage stretched to the corners: top-left, top-right, bottom-right and
+    /// bottom-left.
+    pub fn draw_quad(&self, corners: [Vec2; 4], color: Color) {
+        let uv = self.uv();
+        let uvs = [
+            vec2(uv.left(), uv.top()),
+            vec2(uv.right(), uv.top()),
+            vec2(uv.right(), uv.bottom()),
+            vec2(uv.left(), uv.bottom()),
+        ];
+        let vertices = corners
+            .into_iter()
+            .zip(uvs)
+            .map(|(pos, uv)| Vertex {
+                position: pos.extend(0.0),
+                uv,
+                color,
+            })
+            .collect();
+        count_texture(self.texture);
+        draw_mesh(&Mesh {
+            vertices,
+            indices: vec![0, 1, 2, 0, 2, 3],
+            texture: Some(self.texture),
+        });
+    }
+
+    /// The texture coordinates of the subimage, for drawing it with a mesh.
+    fn uv(&self) -> Rect {
+        let size = vec2(self.texture.width(), self.texture.height());
+        Rect::new(
+            self.source.x / size.x,
+            self.source.y / size.y,
+            self.source.w / size.x,
+            self.source.h / size.y,
+        )
+    }
+}
+
+thread_local! {
+    /// The texture of the last frame drawn, and how many times it changed, see
+    /// [`take_texture_switches()`].
+    static TEXTURE_SWITCHES: Cell<(Option<Texture2D>, u32)> = const { Cell::new((None, 0)) };
+}
+
+fn count_texture(texture: Texture2D) {
+    TEXTURE_SWITCHES.with(|cell| {
+        let (last, count) = cell.get();
+        if last != Some(texture) {
+            cell.set((Some(texture), count + 1));
+        }
+    });
+}
+
+/// How many times drawing a sprite frame used a different texture than the frame drawn
+/// before it, since the last call. macroquad can't batch those draws into one draw call, so
+/// this is how many the [`Atlas`] saves, compared with [`Atlas::enabled`] off.
+pub fn take_texture_switches() -> u32 {
+    TEXTURE_SWITCHES.with(|cell| cell.replace((None, 0)).1)
+}
+
 impl Asset for SpriteAsset {
     type Resource = gmk_file::Sprite;
+    type Decoded = Vec<Image>;
 
-    fn load(def: &gmk_file::Sprite) -> Self {
-        let textures = def
-            .subimages
+    fn decode(def: &gmk_file::Sprite) -> Vec<Image> {
+        def.subimages
             .iter()
-            .map(|image| texture_from_data(image.data.as_ref().unwrap(), def.transparent.into()))
+            .map(|image| image_from_data(image.data.as_ref().unwrap(), def.transparent.into()))
+            .collect()
+    }
+
+    fn load(
+        def: &gmk_file::Sprite,
+        images: Vec<Image>,
+        filter: FilterMode,
+        atlas: &mut Atlas,
+    ) -> Self {
+        let frames = images
+            .iter()
+            .map(|image| {
+                if let Some((texture, source)) = atlas.add(image, filter) {
+                    return SpriteFrame {
+                        texture,
+                        source,
+                        owned: false,
+                    };
+                }
+                let texture = Texture2D::from_image(image);
+                texture.set_filter(filter);
+                SpriteFrame {
+                    texture,
+                    source: Rect::new(0.0, 0.0, texture.width(), texture.height()),
+                    owned: true,
+                }
+            })
             .collect::<Vec<_>>();
 
         let bbox_origin = ivec2(def.bbox_left, def.bbox_top).as_vec2();
@@ -46,8 +177,55 @@ impl Asset for SpriteAsset {
         Self {
             size: uvec2(def.size.0, def.size.1),
             origin: ivec2(def.origin.0, def.origin.1),
-            textures,
+            frames,
             bbox,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_origin() {
+        let mut sprite = SpriteAsset {
+            size: uvec2(16, 16),
+            origin: ivec2(0, 0),
+            frames: vec![],
+            bbox: Rect::new(0.0, 0.0, 16.0, 16.0),
+        };
+        assert_eq!(sprite.draw_pos(vec2(10.0, 10.0)), vec2(10.0, 10.0));
+
+        // sprite_set_offset()
+        sprite.origin = ivec2(8, 16);
+        assert_eq!(sprite.draw_pos(vec2(10.0, 10.0)), vec2(2.0, -6.0));
+        assert_eq!(sprite.bounds(vec2(10.0, 10.0)).point(), vec2(2.0, -6.0));
+    }
+
+    #[test]
+    fn test_part() {
+        let sprite = SpriteAsset {
+            size: uvec2(32, 16),
+            origin: ivec2(0, 0),
+            frames: vec![],
+            bbox: Rect::new(0.0, 0.0, 32.0, 16.0),
+        };
+        let (source, size) = sprite
+            .part(Rect::new(8.0, 4.0, 16.0, 8.0), vec2(2.0, -1.0))
+            .unwrap();
+        assert_eq!(source, Rect::new(8.0, 4.0, 16.0, 8.0));
+        assert_eq!(size, vec2(32.0, -8.0));
+
+        // clipped to the sprite
+        let (source, size) = sprite
+            .part(Rect::new(24.0, -4.0, 16.0, 8.0), vec2(1.0, 1.0))
+            .unwrap();
+        assert_eq!(source, Rect::new(24.0, 0.0, 8.0, 4.0));
+        assert_eq!(size, vec2(8.0, 4.0));
+
+        assert!(sprite
+            .part(Rect::new(40.0, 0.0, 8.0, 8.0), vec2(1.0, 1.0))
+            .is_none());
+    }
+}
@@ -17,29 +17,153 @@ fn conf() -> Conf {
     }
 }
 
+const USAGE: &str = "usage: iji-rs [--no-parse-cache] [--no-atlas] [--strict] \
+    [--iteration-limit <count>] [--record-state <steps> <path>] [path/to/game.gmk]";
+
+struct Args {
+    path: std::path::PathBuf,
+    /// Keep parsed scripts next to the game, so later runs start faster.
+    parse_cache: bool,
+    /// Give each sprite frame its own texture, to compare the draw calls the atlas saves.
+    atlas: bool,
+    /// Error on implicit string conversions, to find scripts relying on them.
+    strict: bool,
+    /// Error on loops running more times than this, rather than hanging.
+    iteration_limit: Option<u32>,
+    record_state: Option<RecordState>,
+}
+
+/// `--record-state <steps> <path>`: runs `steps` fixed steps without input, then writes the
+/// state JSON (the same as the debug server's `/state`) to `path`, to diff against a golden
+/// file when checking a change doesn't affect the game.
+struct RecordState {
+    steps: u32,
+    path: std::path::PathBuf,
+}
+
+impl Args {
+    fn parse() -> Self {
+        let mut args = Self {
+            path: "ref/source code/iji.gmk".into(),
+            parse_cache: true,
+            atlas: true,
+            strict: false,
+            iteration_limit: None,
+            record_state: None,
+        };
+        let mut path = None;
+        let mut iter = std::env::args_os().skip(1);
+        while let Some(arg) = iter.next() {
+            match arg.to_str() {
+                Some("--no-parse-cache") => args.parse_cache = false,
+                Some("--no-atlas") => args.atlas = false,
+                Some("--strict") => args.strict = true,
+                Some("--iteration-limit") => {
+                    let count = iter.next().and_then(|count| count.to_str()?.parse().ok());
+                    let Some(count) = count else {
+                        usage_error("--iteration-limit needs a count")
+                    };
+                    args.iteration_limit = Some(count);
+                }
+                Some("--record-state") => {
+                    let steps = iter.next().and_then(|steps| steps.to_str()?.parse().ok());
+                    let (Some(steps), Some(path)) = (steps, iter.next()) else {
+                        usage_error("--record-state needs a step count and a path")
+                    };
+                    args.record_state = Some(RecordState {
+                        steps,
+                        path: path.into(),
+                    });
+                }
+                Some(flag) if flag.starts_with("--") => {
+                    usage_error(&format!("unknown flag {flag}"))
+                }
+                _ if path.is_none() => path = Some(arg.into()),
+                _ => usage_error("only one game path is allowed"),
+            }
+        }
+        if let Some(path) = path {
+            args.path = path;
+        }
+        args
+    }
+}
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("{message}\n{USAGE}");
+    std::process::exit(2);
+}
+
 fn main() {
-    let content = gmk_file::parse("ref/source code/iji.gmk");
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
 
-    macroquad::Window::from_config(conf(), run_main(content))
+    let args = Args::parse();
+    let content = gmk_file::parse(&args.path);
+    // file functions can only see files next to the game
+    let data_root = args.path.parent().unwrap_or(&args.path).to_path_buf();
+    let options = state::Options {
+        parse_cache: args
+            .parse_cache
+            .then(|| args.path.with_extension("parse-cache.json")),
+        strict: args.strict,
+        iteration_limit: args.iteration_limit,
+        fixed_step: args.record_state.is_some(),
+    };
+
+    macroquad::Window::from_config(
+        conf(),
+        run_main(content, data_root, options, args.atlas, args.record_state),
+    )
 }
 
-async fn run_main(content: gmk_file::Content) {
-    let global = state::Global::new(content);
+async fn run_main(
+    content: gmk_file::Content,
+    data_root: std::path::PathBuf,
+    options: state::Options,
+    atlas: bool,
+    record: Option<RecordState>,
+) {
+    let mut global = state::Global::new(content, data_root, options);
+    global.assets.get_mut().atlas.enabled = atlas;
 
-    let mut server = debug::Server::start(8000);
+    // recording still needs the window for textures, but nothing else outside the game
+    let mut server = if let Some(record) = &record {
+        log::info!("recording state after {} steps", record.steps);
+        global.dialogs = Box::new(state::ScriptedDialogs::default());
+        rand::srand(0);
+        None
+    } else {
+        Some(debug::Server::start(8000))
+    };
 
     global.goto_room_order(0);
 
+    let mut steps = 0;
     loop {
-        for &key in state::KEY_CODES {
-            if is_key_pressed(key) {
-                global.dispatch(Event::KeyPress(key));
+        if let Some(record) = &record {
+            if steps == record.steps {
+                std::fs::write(&record.path, global.state_json()).unwrap();
+                log::info!("wrote {}", record.path.display());
+                return;
+            }
+            steps += 1;
+        } else {
+            global.input.borrow_mut().poll();
+        }
+        // copy out the (mapped) key states, as events can remap keys
+        let keys = global.input.borrow().keys.clone();
+        for key in state::KEY_CODES {
+            let Some(state) = keys.get(key) else {
+                continue;
+            };
+            if state.pressed {
+                global.dispatch(Event::KeyPress(*key));
             }
-            if is_key_down(key) {
-                global.dispatch(Event::KeyDown(key));
+            if state.down {
+                global.dispatch(Event::KeyDown(*key));
             }
-            if is_key_released(key) {
-                global.dispatch(Event::KeyRelease(key));
+            if state.released {
+                global.dispatch(Event::KeyRelease(*key));
             }
         }
         global.step();
@@ -54,7 +178,32 @@ async fn run_main(content: gmk_file::Content) {
 
         global.cleanup();
 
-        server.pump(&global).unwrap();
+        if let Some(server) = &mut server {
+            server.pump(&global).unwrap();
+        }
+
+        for text in global.dialogs.take_pending() {
+            show_dialog(&global, &text).await;
+        }
+    }
+}
+
+/// Shows a dialog a script opened over the last frame, without stepping the game until the
+/// player dismisses it.
+async fn show_dialog(global: &state::Global, text: &str) {
+    loop {
+        let dismissed = is_key_pressed(KeyCode::Enter)
+            || is_key_pressed(KeyCode::Escape)
+            || is_mouse_button_pressed(MouseButton::Left);
+        global.redraw();
+        if !dismissed {
+            state::draw_dialog(text);
+        }
+        next_frame().await;
+        // wait a frame after dismissing, so the game doesn't see the same press
+        if dismissed {
+            return;
+        }
     }
 }
 
@@ -73,7 +222,7 @@ mod debug {
     impl Server {
         pub fn start(port: u16) -> Self {
             let server = tiny_http::Server::http((Ipv4Addr::LOCALHOST, port)).unwrap();
-            println!("Debug serving on http://localhost:{port}/");
+            log::info!("Debug serving on http://localhost:{port}/");
             Self(server)
         }
 
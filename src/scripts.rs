@@ -1,8 +1,14 @@
+use std::rc::Rc;
+
+use macroquad::models::Vertex;
 use macroquad::prelude::*;
 
-use gml::eval::{Context, Value};
+use gml::eval::{Context, Global as _, Object, Value};
 
-use crate::state::{key_code, Event, FontAsset, Global};
+use crate::state::{
+    ds, AssetId, BackgroundAsset, BlendMode, DsEntry, DsGrid, DsList, DsMap, Event, FontAsset,
+    Global, Instance, ObjectAsset, ObjectDefaults, SpriteAsset, TextGradient,
+};
 
 pub fn call(
     global: &Global,
@@ -11,7 +17,10 @@ pub fn call(
     args: Vec<Value>,
 ) -> gml::eval::Result<Value> {
     match id {
-        "floor" => Ok(args[0].to_float().floor().into()),
+        "random_set_seed" => {
+            rand::srand(args[0].to_int() as u64);
+            Ok(().into())
+        }
         "random" => {
             let range = args[0].to_float();
             Ok(rand::gen_range(0.0, range).into())
@@ -30,6 +39,7 @@ pub fn call(
             }
         }
         "string" => Ok(args[0].to_str().into()),
+        "real" => Ok(args[0].to_float().into()),
         "string_length" => Ok(i32::try_from(args[0].to_str().len())
             .expect("string too long")
             .into()),
@@ -40,44 +50,160 @@ pub fn call(
             Ok(char.map_or(().into(), |char| (char as i32).into()))
         }
 
-        "file_exists" => {
-            let _path = args[0].to_str();
-            Ok(false.into())
+        "show_message" => {
+            global.dialogs.show_message(&args[0].to_str());
+            Ok(().into())
         }
+        "show_question" => Ok(global.dialogs.show_question(&args[0].to_str()).into()),
+        "get_integer" => Ok(global
+            .dialogs
+            .get_integer(&args[0].to_str(), args[1].to_float())
+            .into()),
+        "get_string" => Ok(global
+            .dialogs
+            .get_string(&args[0].to_str(), &args[1].to_str())
+            .into()),
+
+        "file_exists" => Ok(global
+            .data_path(&args[0].to_str())
+            .is_some_and(|path| path.is_file())
+            .into()),
         "file_text_open_write"
         | "file_text_close"
         | "file_text_write_string"
         | "file_text_writeln"
         | "display_set_all"
         | "window_set_fullscreen"
-        | "sound_loop"
-        | "sound_stop"
-        | "sound_stop_all"
-        | "keyboard_set_map"
-        | "keyboard_unset_map"
         | "screen_redraw" => Ok(().into()),
 
+        "window_set_size" => {
+            request_new_screen_size(args[0].to_float() as f32, args[1].to_float() as f32);
+            Ok(().into())
+        }
+        "window_get_region_scale" => Ok(global.state.borrow().screen.region_scale().into()),
+        "window_set_region_scale" => {
+            let mut state = global.state.borrow_mut();
+            state.screen.set_region_scale(args[0].to_float());
+            if args[1].to_bool() {
+                state.screen.fit_window(global.room.borrow().view.size);
+            }
+            Ok(().into())
+        }
+
+        "ds_map_create" => Ok(global.new_instance(Rc::<DsMap>::default()).into()),
+        "ds_map_destroy" => destroy_ds::<DsMap>(global, &args[0]),
+        "ds_map_add" => with_ds(global, &args[0], |map: &DsMap| {
+            // doesn't replace existing keys
+            if map.get(&args[1]).is_none() {
+                map.insert(&args[1], DsEntry::Value(args[2].clone()));
+            }
+            ().into()
+        }),
+        "ds_map_replace" => with_ds(global, &args[0], |map: &DsMap| {
+            map.insert(&args[1], DsEntry::Value(args[2].clone()));
+            ().into()
+        }),
+        "ds_map_add_list" | "ds_map_add_map" => {
+            let nested = args[2].try_to_object_id()?;
+            let entry = if id == "ds_map_add_list" {
+                DsEntry::List(nested)
+            } else {
+                DsEntry::Map(nested)
+            };
+            with_ds(global, &args[0], |map: &DsMap| {
+                map.insert(&args[1], entry);
+                ().into()
+            })
+        }
+        "ds_map_delete" => with_ds(global, &args[0], |map: &DsMap| {
+            map.remove(&args[1]);
+            ().into()
+        }),
+        "ds_map_exists" => with_ds(global, &args[0], |map: &DsMap| {
+            map.get(&args[1]).is_some().into()
+        }),
+        "ds_map_find_value" => with_ds(global, &args[0], |map: &DsMap| {
+            map.get(&args[1])
+                .map_or(Value::Undefined, |entry| entry.value())
+        }),
+        "ds_map_size" => with_ds(global, &args[0], |map: &DsMap| {
+            (map.items.borrow().len() as i32).into()
+        }),
+
+        "ds_list_create" => Ok(global.new_instance(Rc::<DsList>::default()).into()),
+        "ds_list_destroy" => destroy_ds::<DsList>(global, &args[0]),
+        "ds_list_add" => with_ds(global, &args[0], |list: &DsList| {
+            let values = args[1..].iter().cloned().map(DsEntry::Value);
+            list.items.borrow_mut().extend(values);
+            ().into()
+        }),
+        "ds_list_find_value" => with_ds(global, &args[0], |list: &DsList| {
+            list.index(&args[1..2]).ok().flatten().unwrap_or_default()
+        }),
+        "ds_list_replace" => with_ds(global, &args[0], |list: &DsList| {
+            list.set_index(&args[1..2], args[2].clone()).ok();
+            ().into()
+        }),
+        "ds_list_mark_as_list" | "ds_list_mark_as_map" => {
+            let is_list = id == "ds_list_mark_as_list";
+            with_ds(global, &args[0], |list: &DsList| {
+                let mut items = list.items.borrow_mut();
+                let index = usize::try_from(args[1].to_int()).ok();
+                if let Some(item) = index.and_then(|index| items.get_mut(index)) {
+                    if let Some(nested) = item.value().as_object_id() {
+                        *item = if is_list {
+                            DsEntry::List(nested)
+                        } else {
+                            DsEntry::Map(nested)
+                        };
+                    }
+                }
+                ().into()
+            })
+        }
+        "ds_list_size" => with_ds(global, &args[0], |list: &DsList| {
+            (list.items.borrow().len() as i32).into()
+        }),
+
+        "ds_grid_create" => {
+            let width = args[0].to_int().max(0) as usize;
+            let height = args[1].to_int().max(0) as usize;
+            Ok(global
+                .new_instance(Rc::new(DsGrid::new(width, height)))
+                .into())
+        }
+        "ds_grid_destroy" => destroy_ds::<DsGrid>(global, &args[0]),
+        "ds_grid_width" => with_ds(global, &args[0], |grid: &DsGrid| (grid.width as i32).into()),
+        "ds_grid_height" => with_ds(global, &args[0], |grid: &DsGrid| {
+            (grid.height as i32).into()
+        }),
+        "ds_grid_get" => with_ds(global, &args[0], |grid: &DsGrid| {
+            grid.get(&args[1], &args[2])
+        }),
+        "ds_grid_set" => with_ds(global, &args[0], |grid: &DsGrid| {
+            grid.set(&args[1], &args[2], args[3].clone());
+            ().into()
+        }),
+        "ds_grid_clear" => with_ds(global, &args[0], |grid: &DsGrid| {
+            grid.clear(args[1].clone());
+            ().into()
+        }),
+
+        "json_encode" => Ok(ds::json_encode(global, args[0].try_to_object_id()?).into()),
+        "json_decode" => Ok(ds::json_decode(global, &args[0].to_str())),
+
         "place_meeting" => {
             let x = args[0].to_int();
             let y = args[1].to_int();
             let id = args[2].try_to_object_id()?;
 
             // todo: `with (all) place_meeting()` etc.
-            let context_instance = {
-                let room = global.room.borrow();
-                let object_instances = room.object_instances.borrow();
-                object_instances[context.instance_id.instance_id()].clone()
-            };
-
-            let context_state = context_instance.state.borrow();
-            let Some(context_sprite) = context_state.sprite_asset else {
+            let context_instance = context_instance(global, context)?;
+            let Some(context_bounds) =
+                global.instance_bounds(&context_instance, ivec2(x, y).as_dvec2())
+            else {
                 return Ok(false.into());
             };
-            let context_bounds = global
-                .assets()
-                .sprites
-                .get(context_sprite)
-                .bounds(ivec2(x, y).as_vec2());
 
             let object_type = global
                 .object_types
@@ -85,17 +211,11 @@ pub fn call(
                 .ok_or_else(|| gml::eval::Error::InvalidObject(id.into()))?;
 
             for other_instance in object_type.object.instances.borrow().values() {
-                let other_state = other_instance.state.borrow();
-                let Some(other_sprite) = other_state.sprite_asset else {
+                let other_pos = other_instance.state.borrow().pos;
+                let Some(other_bounds) = global.instance_bounds(other_instance, other_pos) else {
                     continue;
                 };
 
-                let other_bounds = global
-                    .assets()
-                    .sprites
-                    .get(other_sprite)
-                    .bounds(other_state.pos.as_vec2());
-
                 if context_bounds.overlaps(&other_bounds) {
                     return Ok(true.into());
                 }
@@ -104,11 +224,164 @@ pub fn call(
             Ok(false.into())
         }
 
-        "place_free" => {
-            let _x = args[0].to_int();
-            let _y = args[1].to_int();
-            Ok(true.into())
+        "point_in_rectangle" => {
+            let [px, py, x1, y1, x2, y2] = float_args(&args);
+            let rect = rect_from_corners(x1, y1, x2, y2);
+            Ok(point_in_rectangle(dvec2(px, py), rect).into())
+        }
+        "point_in_circle" => {
+            let [px, py, cx, cy, radius] = float_args(&args);
+            Ok(point_in_circle(dvec2(px, py), dvec2(cx, cy), radius).into())
+        }
+        "rectangle_in_rectangle" => {
+            let [sx1, sy1, sx2, sy2, dx1, dy1, dx2, dy2] = float_args(&args);
+            let src = rect_from_corners(sx1, sy1, sx2, sy2);
+            let dest = rect_from_corners(dx1, dy1, dx2, dy2);
+            Ok(rectangle_in_rectangle(src, dest).into())
+        }
+
+        "distance_to_object" => {
+            let object_index = args[0].to_int();
+            let distance = match u32::try_from(object_index) {
+                Ok(object_index) => {
+                    let instance = context_instance(global, context)?;
+                    global.distance_to_object(&instance, object_index)
+                }
+                Err(_) => 1_000_000.0,
+            };
+            Ok(distance.into())
+        }
+
+        "place_free" | "place_empty" => {
+            let [x, y] = float_args(&args);
+            let instance = context_instance(global, context)?;
+            let occupied = if id == "place_free" {
+                global.place_solid(&instance, dvec2(x, y))
+            } else {
+                global.place_occupied(&instance, dvec2(x, y))
+            };
+            Ok((!occupied).into())
+        }
+
+        "move_towards_point" => {
+            let x = args[0].to_float();
+            let y = args[1].to_float();
+            let speed = args[2].to_float();
+            context_instance(global, context)?.move_towards_point(dvec2(x, y), speed);
+            Ok(().into())
+        }
+        "move_snap" => {
+            let [hsnap, vsnap] = float_args(&args);
+            context_instance(global, context)?.move_snap(dvec2(hsnap, vsnap));
+            Ok(().into())
+        }
+        "move_wrap" => {
+            let hor = args[0].to_bool();
+            let vert = args[1].to_bool();
+            let margin = args[2].to_float();
+            let room_size = global.room.borrow().size.as_dvec2();
+            context_instance(global, context)?.move_wrap([hor, vert], room_size, margin);
+            Ok(().into())
+        }
+        "place_snapped" => {
+            let [hsnap, vsnap] = float_args(&args);
+            Ok(context_instance(global, context)?
+                .place_snapped(dvec2(hsnap, vsnap))
+                .into())
+        }
+        "move_contact_solid" => {
+            let direction = args[0].to_float();
+            let mut max_distance = args[1].to_float();
+            if max_distance <= 0.0 {
+                // GM uses a default when the max distance isn't given
+                max_distance = 1000.0;
+            }
+            context_instance(global, context)?.move_contact_solid(global, direction, max_distance);
+            Ok(().into())
+        }
+        "move_bounce_solid" => {
+            let _advanced = args[0].to_bool();
+            context_instance(global, context)?.move_bounce_solid(global);
+            Ok(().into())
+        }
+
+        "object_exists" => Ok(resource_exists(&global.content.objects, &args[0]).into()),
+        "sprite_exists" => Ok(resource_exists(&global.content.sprites, &args[0]).into()),
+        "sound_exists" => Ok(resource_exists(&global.content.sounds, &args[0]).into()),
+        "sound_play" | "sound_loop" => {
+            if let Ok(index) = u32::try_from(args[0].to_int()) {
+                let looping = id == "sound_loop";
+                global.state.borrow_mut().sounds.play(index, looping);
+            }
+            Ok(().into())
+        }
+        "sound_stop" => {
+            if let Ok(index) = u32::try_from(args[0].to_int()) {
+                global.state.borrow_mut().sounds.stop(index);
+            }
+            Ok(().into())
+        }
+        "sound_stop_all" => {
+            global.state.borrow_mut().sounds.stop_all();
+            Ok(().into())
+        }
+        "sound_isplaying" => {
+            let playing = u32::try_from(args[0].to_int())
+                .is_ok_and(|index| global.state.borrow().sounds.is_playing(index));
+            Ok(playing.into())
+        }
+        "sound_volume" => {
+            if let Ok(index) = u32::try_from(args[0].to_int()) {
+                global
+                    .state
+                    .borrow_mut()
+                    .sounds
+                    .set_volume(index, args[1].to_float());
+            }
+            Ok(().into())
+        }
+        "sound_pan" => {
+            if let Ok(index) = u32::try_from(args[0].to_int()) {
+                global
+                    .state
+                    .borrow_mut()
+                    .sounds
+                    .set_pan(index, args[1].to_float());
+            }
+            Ok(().into())
+        }
+        "background_exists" => Ok(resource_exists(&global.content.backgrounds, &args[0]).into()),
+        "font_exists" => Ok(resource_exists(&global.content.fonts, &args[0]).into()),
+        "path_exists" => Ok(resource_exists(&global.content.paths, &args[0]).into()),
+        "script_exists" => Ok(resource_exists(&global.content.scripts, &args[0]).into()),
+        "timeline_exists" => Ok(resource_exists(&global.content.timelines, &args[0]).into()),
+        "room_exists" => Ok(resource_exists(&global.content.rooms, &args[0]).into()),
+
+        "object_get_name" => Ok(resource_name(&global.content.objects, &args[0])),
+        "object_get_sprite" | "object_get_mask" | "object_get_solid" | "object_get_visible"
+        | "object_get_depth" => {
+            let object = object_arg(global, &args[0])?;
+            let name = ObjectDefaults::member_name(&id["object_get_".len()..]).unwrap();
+            let value = object.defaults.borrow().get(name);
+            Ok(value.unwrap_or_default())
+        }
+        "object_get_parent" => {
+            let object = object_arg(global, &args[0])?;
+            Ok(object.parent_index.map_or(-1, |index| index as i32).into())
         }
+        "object_get_persistent" => Ok(object_arg(global, &args[0])?.persistent.into()),
+        // existing instances of the object change too, like setting `obj.sprite_index`
+        "object_set_sprite" | "object_set_mask" | "object_set_solid" | "object_set_visible"
+        | "object_set_depth" => {
+            let object = object_arg(global, &args[0])?;
+            let name = ObjectDefaults::member_name(&id["object_set_".len()..]).unwrap();
+            object.defaults.borrow_mut().set(name, &args[1]);
+            object.object.set_member(name, args[1].clone())?;
+            Ok(().into())
+        }
+        "sprite_get_name" => Ok(resource_name(&global.content.sprites, &args[0])),
+        "room_get_name" => Ok(resource_name(&global.content.rooms, &args[0])),
+        "script_get_name" => Ok(resource_name(&global.content.scripts, &args[0])),
 
         "room_goto" => {
             let index = args[0].to_int().try_into().expect("invalid room index");
@@ -121,14 +394,51 @@ pub fn call(
             Ok(().into())
         }
 
+        "keyboard_set_map" => {
+            let from = args[0].to_int();
+            let to = args[1].to_int();
+            global.input.borrow_mut().keyboard_set_map(from, to);
+            Ok(().into())
+        }
+        "keyboard_unset_map" => {
+            global.input.borrow_mut().keyboard_unset_map();
+            Ok(().into())
+        }
         "keyboard_check" => {
             let key = args[0].to_int();
-            if let Ok(key) = key.try_into() {
-                Ok(is_key_pressed(key_code(key)).into())
-            } else {
-                Ok(false.into())
-            }
+            Ok(global.input.borrow().keyboard_check(key).into())
         }
+        "keyboard_check_pressed" => {
+            let key = args[0].to_int();
+            Ok(global.input.borrow().keyboard_check_pressed(key).into())
+        }
+        "keyboard_check_released" => {
+            let key = args[0].to_int();
+            Ok(global.input.borrow().keyboard_check_released(key).into())
+        }
+
+        "mouse_check_button" => {
+            let button = args[0].to_int();
+            Ok(global.input.borrow().mouse_check_button(button).into())
+        }
+        "mouse_check_button_pressed" => {
+            let button = args[0].to_int();
+            Ok(global
+                .input
+                .borrow()
+                .mouse_check_button_pressed(button)
+                .into())
+        }
+        "mouse_check_button_released" => {
+            let button = args[0].to_int();
+            Ok(global
+                .input
+                .borrow()
+                .mouse_check_button_released(button)
+                .into())
+        }
+        "mouse_wheel_up" => Ok(global.input.borrow().mouse_wheel_up().into()),
+        "mouse_wheel_down" => Ok(global.input.borrow().mouse_wheel_down().into()),
 
         "font_add_sprite" => {
             let sprite_index = args[0].to_int();
@@ -136,9 +446,7 @@ pub fn call(
             let _proportional = args[2].to_bool();
             let _sep = args[3].to_bool();
 
-            let id = global
-                .loader()
-                .get_sprite(sprite_index.try_into().expect("invalid sprite index"));
+            let id = sprite_arg(global, sprite_index)?;
             let id = global.state.borrow_mut().fonts.add(FontAsset::new(
                 id,
                 first.try_into().expect("invalid font char"),
@@ -159,24 +467,91 @@ pub fn call(
             global.state.borrow_mut().fonts.set(font_index);
             Ok(().into())
         }
+        // not GM functions, for libraries to put back the color and font they change
+        "draw_push_state" => {
+            global.state.borrow_mut().push_draw_state();
+            Ok(().into())
+        }
+        "draw_pop_state" => {
+            if global.state.borrow_mut().pop_draw_state() {
+                Ok(().into())
+            } else {
+                Err(gml::eval::Error::Custom(
+                    "draw_pop_state() without draw_push_state()".into(),
+                ))
+            }
+        }
 
-        "draw_text_ext" => {
+        "draw_text_ext" | "draw_text_ext_color" => {
             let x = args[0].to_int();
             let y = args[1].to_int();
             let string = args[2].to_str();
             let sep = args[3].to_int();
             let w = args[4].to_int();
+            let gradient = (id == "draw_text_ext_color").then(|| {
+                let alpha = args[9].to_float();
+                let colors = [5, 6, 7, 8].map(|i| gm_color(args[i].to_int(), alpha));
+                TextGradient::from_corners(colors)
+            });
             let state = global.state.borrow();
             if let Some(font) = state.fonts.get() {
-                font.draw_text(global, ivec2(x, y), &string, sep, w);
+                font.draw_text(global, ivec2(x, y), &string, sep, w, gradient);
+            }
+            Ok(().into())
+        }
+
+        "surface_create" => {
+            let w = args[0].to_int();
+            let h = args[1].to_int();
+            let (Ok(w), Ok(h)) = (u32::try_from(w), u32::try_from(h)) else {
+                return Ok((-1).into());
+            };
+            Ok(global
+                .state
+                .borrow_mut()
+                .surfaces
+                .create(uvec2(w, h))
+                .into())
+        }
+        "surface_exists" => {
+            let id = args[0].to_int();
+            Ok(global.state.borrow().surfaces.exists(id).into())
+        }
+        "surface_free" => {
+            let id = args[0].to_int();
+            global.state.borrow_mut().surfaces.free(id);
+            Ok(().into())
+        }
+        "surface_set_target" => {
+            let id = args[0].to_int();
+            Ok(global.state.borrow_mut().surfaces.set_target(id).into())
+        }
+        "surface_reset_target" => {
+            global.state.borrow_mut().surfaces.reset_target();
+            Ok(().into())
+        }
+        "draw_surface" => {
+            let id = args[0].to_int();
+            let x = args[1].to_float() as f32;
+            let y = args[2].to_float() as f32;
+            if let Some(texture) = global.state.borrow().surfaces.texture(id) {
+                draw_texture(texture, x, y, WHITE);
             }
             Ok(().into())
         }
 
+        "draw_clear" | "draw_clear_alpha" => {
+            let alpha = if id == "draw_clear_alpha" {
+                args[1].to_float()
+            } else {
+                1.0
+            };
+            clear_background(gm_color(args[0].to_int(), alpha));
+            Ok(().into())
+        }
         "draw_set_color" => {
             let value = args.get(0).map_or(0, Value::to_int);
-            let [r, g, b, _] = value.to_le_bytes();
-            global.state.borrow_mut().color = Color::from_rgba(r, g, b, 0xFF);
+            global.state.borrow_mut().color = gm_color(value, 1.0);
             Ok(().into())
         }
         "draw_rectangle" => {
@@ -195,6 +570,53 @@ pub fn call(
             }
             Ok(().into())
         }
+        "draw_rectangle_color" => {
+            let [x1, y1, x2, y2] = float_args::<4>(&args).map(|value| value as f32);
+            let alpha = global.state.borrow().color.a;
+            let colors = [4, 5, 6, 7].map(|i| gm_color(args[i].to_int(), alpha.into()));
+            let outline = args[8].to_bool();
+            let vertices = rectangle_vertices(vec2(x1, y1), vec2(x2, y2), colors);
+            if outline {
+                // macroquad lines are one color, so use the average of the ends
+                for (index, start) in vertices.iter().enumerate() {
+                    let end = &vertices[(index + 1) % vertices.len()];
+                    let color = Color::from_vec((start.color.to_vec() + end.color.to_vec()) / 2.0);
+                    let (start, end) = (start.position, end.position);
+                    draw_line(start.x, start.y, end.x, end.y, 1.0, color);
+                }
+            } else {
+                draw_mesh(&Mesh {
+                    vertices: vertices.into(),
+                    indices: vec![0, 1, 2, 0, 2, 3],
+                    texture: None,
+                });
+            }
+            Ok(().into())
+        }
+        "draw_point" => {
+            let [x, y] = float_args::<2>(&args).map(|value| value as f32);
+            let color = global.state.borrow().color;
+            draw_rectangle(x, y, 1.0, 1.0, color);
+            Ok(().into())
+        }
+        "draw_triangle" => {
+            let [x1, y1, x2, y2, x3, y3] = float_args::<6>(&args).map(|value| value as f32);
+            let outline = args[6].to_bool();
+            let [v1, v2, v3] = [vec2(x1, y1), vec2(x2, y2), vec2(x3, y3)];
+            let color = global.state.borrow().color;
+            if outline {
+                draw_triangle_lines(v1, v2, v3, 1.0, color);
+            } else {
+                draw_triangle(v1, v2, v3, color);
+            }
+            Ok(().into())
+        }
+        "draw_line_width" => {
+            let [x1, y1, x2, y2, w] = float_args::<5>(&args).map(|value| value as f32);
+            let color = global.state.borrow().color;
+            draw_line(x1, y1, x2, y2, w, color);
+            Ok(().into())
+        }
 
         "draw_sprite" => {
             let sprite_index = args[0].to_int();
@@ -202,55 +624,99 @@ pub fn call(
             let x = args[2].to_int();
             let y = args[3].to_int();
 
-            let image_index = usize::try_from(image_index).ok().unwrap_or_else(|| {
-                context
-                    .instance
-                    .member("image_index")
-                    .ok()
-                    .flatten()
-                    .unwrap_or_default()
-                    .to_int() as usize
-            });
+            let image_index = sprite_image_index(context, image_index);
+
+            let sprite = sprite_arg(global, sprite_index)?;
+            let assets = global.assets();
+            let sprite = assets.sprites.get(sprite);
+
+            let pos = sprite.draw_pos(ivec2(x, y).as_vec2());
+            sprite.frames[image_index].draw(pos.x, pos.y, WHITE, Default::default());
+
+            Ok(().into())
+        }
+        "draw_sprite_pos" => {
+            let sprite_index = args[0].to_int();
+            let image_index = args[1].to_int();
+            let corners = [2, 4, 6, 8]
+                .map(|i| vec2(args[i].to_float() as f32, args[i + 1].to_float() as f32));
+            let alpha = args[10].to_float();
+
+            let image_index = sprite_image_index(context, image_index);
+
+            let sprite = sprite_arg(global, sprite_index)?;
+            let assets = global.assets();
+            let sprite = assets.sprites.get(sprite);
+
+            let color = Color::new(1.0, 1.0, 1.0, alpha as f32);
+            sprite.frames[image_index].draw_quad(corners, color);
+
+            Ok(().into())
+        }
+        "draw_sprite_general" => {
+            let sprite_index = args[0].to_int();
+            let image_index = args[1].to_int();
+            let [left, top, width, height, x, y, xscale, yscale, rot] =
+                float_args::<9>(&args[2..]).map(|value| value as f32);
+            // macroquad can't tint each corner of a texture, so use their average
+            let corners = [11, 12, 13, 14].map(|i| gm_color(args[i].to_int(), 1.0));
+            let average =
+                |channel: fn(&Color) -> f32| corners.iter().map(channel).sum::<f32>() / 4.0;
+            let color = Color::new(
+                average(|c| c.r),
+                average(|c| c.g),
+                average(|c| c.b),
+                args[15].to_float() as f32,
+            );
+
+            let image_index = sprite_image_index(context, image_index);
 
-            let sprite = global.loader().get_sprite(sprite_index as u32);
+            let sprite = sprite_arg(global, sprite_index)?;
             let assets = global.assets();
             let sprite = assets.sprites.get(sprite);
 
-            draw_texture(sprite.textures[image_index], x as f32, y as f32, WHITE);
+            let source = Rect::new(left, top, width, height);
+            let Some((source, dest_size)) = sprite.part(source, vec2(xscale, yscale)) else {
+                return Ok(().into());
+            };
+            // GM rotates counter-clockwise in degrees around the top-left of the part
+            sprite.frames[image_index].draw(
+                x,
+                y,
+                color,
+                DrawTextureParams {
+                    dest_size: Some(dest_size),
+                    source: Some(source),
+                    rotation: -rot.to_radians(),
+                    pivot: Some(vec2(x, y)),
+                    ..Default::default()
+                },
+            );
 
             Ok(().into())
         }
-        "draw_sprite_stretched_ext" => {
+        "draw_sprite_stretched" | "draw_sprite_stretched_ext" => {
             let sprite_index = args[0].to_int();
             let image_index = args[1].to_int();
             let x = args[2].to_int();
             let y = args[3].to_int();
             let w = args[4].to_int();
             let h = args[5].to_int();
-            let [_, r, g, b] = (args[6].to_int() as u32).to_be_bytes();
-            let alpha = args[7].to_float();
-
-            let mut color = Color::from_rgba(r, g, b, 255);
-            color.a = alpha as f32;
-
-            let image_index = usize::try_from(image_index).ok().unwrap_or_else(|| {
-                context
-                    .instance
-                    .member("image_index")
-                    .ok()
-                    .flatten()
-                    .unwrap_or_default()
-                    .to_int() as usize
-            });
+            let color = if id == "draw_sprite_stretched_ext" {
+                gm_color(args[6].to_int(), args[7].to_float())
+            } else {
+                WHITE
+            };
+
+            let image_index = sprite_image_index(context, image_index);
 
-            let sprite = global.loader().get_sprite(sprite_index as u32);
+            let sprite = sprite_arg(global, sprite_index)?;
             let assets = global.assets();
             let sprite = assets.sprites.get(sprite);
 
             let pos = ivec2(x, y).as_vec2();
             let size = ivec2(w, h).as_vec2();
-            draw_texture_ex(
-                sprite.textures[image_index],
+            sprite.frames[image_index].draw(
                 pos.x,
                 pos.y,
                 color,
@@ -263,7 +729,83 @@ pub fn call(
             Ok(().into())
         }
 
-        "draw_set_blend_mode" => Ok(().into()),
+        "draw_background" | "draw_background_ext" => {
+            let background_index = args[0].to_int();
+            let x = args[1].to_float() as f32;
+            let y = args[2].to_float() as f32;
+            let (scale, rot, color) = if id == "draw_background_ext" {
+                let [xscale, yscale, rot] = float_args::<3>(&args[3..]).map(|value| value as f32);
+                let color = gm_color(args[6].to_int(), args[7].to_float());
+                (vec2(xscale, yscale), rot, color)
+            } else {
+                (vec2(1.0, 1.0), 0.0, WHITE)
+            };
+
+            let background = background_arg(global, background_index)?;
+            let assets = global.assets();
+            let background = assets.backgrounds.get(background);
+
+            // negative scales mirror around the origin, as in GM
+            draw_texture_ex(
+                background.texture,
+                x,
+                y,
+                color,
+                DrawTextureParams {
+                    dest_size: Some(background.size.as_vec2() * scale),
+                    rotation: -rot.to_radians(),
+                    pivot: Some(vec2(x, y)),
+                    ..Default::default()
+                },
+            );
+
+            Ok(().into())
+        }
+
+        "draw_set_blend_mode" => {
+            let mode = args[0].to_int();
+            let mode = BlendMode::from_gm(mode)
+                .ok_or_else(|| gml::eval::Error::Custom(format!("invalid blend mode {mode}")))?;
+            global.state.borrow_mut().blend.set(mode);
+            Ok(().into())
+        }
+
+        "sprite_set_offset" => {
+            let sprite_index = args[0].to_int();
+            let x = args[1].to_int();
+            let y = args[2].to_int();
+
+            let sprite = sprite_arg(global, sprite_index)?;
+            global.assets.borrow_mut().sprites.get_mut(sprite).origin = ivec2(x, y);
+            Ok(().into())
+        }
+        "sprite_collision_mask" => {
+            let sprite_index = args[0].to_int();
+            let _separate_masks = args[1].to_bool();
+            let bbox_mode = args[2].to_int();
+            let left = args[3].to_float() as f32;
+            let right = args[4].to_float() as f32;
+            let top = args[5].to_float() as f32;
+            let bottom = args[6].to_float() as f32;
+            let _kind = args[7].to_int();
+            let _tolerance = args[8].to_int();
+
+            let sprite = sprite_arg(global, sprite_index)?;
+            let mut assets = global.assets.borrow_mut();
+            let sprite = assets.sprites.get_mut(sprite);
+            match bbox_mode {
+                // automatic, keep the bbox from the resource
+                0 => {}
+                // full image
+                1 => {
+                    let size = sprite.size.as_vec2();
+                    sprite.bbox = Rect::new(0.0, 0.0, size.x, size.y);
+                }
+                // manual
+                _ => sprite.bbox = Rect::new(left, top, right - left, bottom - top),
+            }
+            Ok(().into())
+        }
 
         "instance_create" => {
             let x = args[0].to_int();
@@ -281,6 +823,16 @@ pub fn call(
             Ok(id.into())
         }
 
+        "instance_copy" => {
+            let perform_event = args.first().is_some_and(Value::to_bool);
+            let source = context_instance(global, context)?;
+            let instance = global.instance_copy(&source);
+            if perform_event {
+                instance.clone().dispatch(global, Event::Create);
+            }
+            Ok(instance.id.into())
+        }
+
         "instance_destroy" => {
             let id = args
                 .get(0)
@@ -300,10 +852,285 @@ pub fn call(
             }
         }
 
+        // there's no debug/release distinction when running the game file
+        "gml_release_mode" => Ok(false.into()),
+
+        "game_restart" => {
+            global.game_restart();
+            Ok(().into())
+        }
+
         "game_end" => {
             std::process::exit(0);
         }
 
-        _ => Err(gml::eval::Error::UndefinedFunction(id.to_string())),
+        // only reached when they're not evaluated directly
+        _ => gml::ast::Builtin::from_name(id)
+            .and_then(|builtin| gml::eval::call_builtin(builtin, &args))
+            .ok_or_else(|| gml::eval::Error::UndefinedFunction(id.to_string())),
+    }
+}
+
+fn context_instance(global: &Global, context: &Context<'_>) -> gml::eval::Result<Rc<Instance>> {
+    let id = context.instance_id;
+    let room = global.room.borrow();
+    let instance = room
+        .object_instances
+        .borrow()
+        .values
+        .get(&id.instance_id())
+        .cloned()
+        .or_else(|| {
+            room.added_instances
+                .borrow()
+                .get(&id.instance_id())
+                .cloned()
+        });
+    instance.ok_or_else(|| gml::eval::Error::InvalidObject(id.into()))
+}
+
+/// Negative image indices mean the current instance `image_index`.
+fn sprite_image_index(context: &Context<'_>, image_index: i32) -> usize {
+    usize::try_from(image_index).ok().unwrap_or_else(|| {
+        context
+            .instance
+            .member("image_index")
+            .ok()
+            .flatten()
+            .unwrap_or_default()
+            .to_int() as usize
+    })
+}
+
+/// Calls `f` with the data structure `id`, or errors if it's not a `T`, e.g. if it was
+/// destroyed.
+fn with_ds<T: 'static>(
+    global: &Global,
+    id: &Value,
+    f: impl FnOnce(&T) -> Value,
+) -> gml::eval::Result<Value> {
+    let object = id
+        .as_object_id()
+        .and_then(|object_id| global.room.borrow().script_instance(object_id));
+    object
+        .as_deref()
+        .and_then(|object| object.as_any()?.downcast_ref::<T>())
+        .map(f)
+        .ok_or_else(|| gml::eval::Error::InvalidObject(id.clone()))
+}
+
+/// Frees the data structure `id`, or errors if it's not a `T`.
+fn destroy_ds<T: 'static>(global: &Global, id: &Value) -> gml::eval::Result<Value> {
+    let room = global.room.borrow();
+    match id.as_object_id() {
+        Some(object_id) if ds::destroy_ds::<T>(&room.script_instances, object_id) => Ok(().into()),
+        _ => Err(gml::eval::Error::InvalidObject(id.clone())),
+    }
+}
+
+fn resource_item<'a, T>(
+    chunk: &'a gmk_file::ResourceChunk<T>,
+    index: &Value,
+) -> Option<(&'a str, &'a T)> {
+    u32::try_from(index.to_int())
+        .ok()
+        .and_then(|index| chunk.get_item(index))
+}
+
+/// The sprite `index` passed to a script, or an error if it doesn't exist.
+fn sprite_arg(global: &Global, index: i32) -> gml::eval::Result<AssetId<SpriteAsset>> {
+    global
+        .loader()
+        .try_get_sprite(index)
+        .ok_or_else(|| gml::eval::Error::Custom(format!("sprite {index} does not exist")))
+}
+
+/// The background `index` passed to a script, or an error if it doesn't exist.
+fn background_arg(global: &Global, index: i32) -> gml::eval::Result<AssetId<BackgroundAsset>> {
+    global
+        .loader()
+        .try_get_background(index)
+        .ok_or_else(|| gml::eval::Error::Custom(format!("background {index} does not exist")))
+}
+
+/// The object type `index` passed to a script, or an error if it doesn't exist.
+fn object_arg<'a>(global: &'a Global, index: &Value) -> gml::eval::Result<&'a ObjectAsset> {
+    u32::try_from(index.to_int())
+        .ok()
+        .and_then(|index| global.object_types.get(&index))
+        .ok_or_else(|| gml::eval::Error::InvalidObject(index.clone()))
+}
+
+/// Deleted resources leave a hole in the chunk, which doesn't exist.
+fn resource_exists<T>(chunk: &gmk_file::ResourceChunk<T>, index: &Value) -> bool {
+    resource_item(chunk, index).is_some()
+}
+
+/// The name of the resource at `index`, or an empty string if there isn't one.
+fn resource_name<T>(chunk: &gmk_file::ResourceChunk<T>, index: &Value) -> Value {
+    resource_item(chunk, index)
+        .map_or_else(String::new, |(name, _)| name.to_string())
+        .into()
+}
+
+fn float_args<const N: usize>(args: &[Value]) -> [f64; N] {
+    std::array::from_fn(|index| args.get(index).map_or(0.0, Value::to_float))
+}
+
+/// GM colors are packed as `0xBBGGRR`, like `make_color_rgb()` returns.
+pub fn gm_color(value: i32, alpha: f64) -> Color {
+    let [r, g, b, _] = value.to_le_bytes();
+    let mut color = Color::from_rgba(r, g, b, 255);
+    color.a = alpha as f32;
+    color
+}
+
+/// The corners of a rectangle from `p1` to `p2` with `colors` in GM's order: top-left,
+/// top-right, bottom-right then bottom-left.
+fn rectangle_vertices(p1: Vec2, p2: Vec2, colors: [Color; 4]) -> [Vertex; 4] {
+    let corners = [(p1.x, p1.y), (p2.x, p1.y), (p2.x, p2.y), (p1.x, p2.y)];
+    std::array::from_fn(|index| {
+        let (x, y) = corners[index];
+        Vertex {
+            position: vec3(x, y, 0.0),
+            uv: Vec2::ZERO,
+            color: colors[index],
+        }
+    })
+}
+
+/// GM accepts the corners in either order.
+fn rect_from_corners(x1: f64, y1: f64, x2: f64, y2: f64) -> (DVec2, DVec2) {
+    let (a, b) = (dvec2(x1, y1), dvec2(x2, y2));
+    (a.min(b), a.max(b))
+}
+
+/// Edges are inclusive.
+fn point_in_rectangle(point: DVec2, (min, max): (DVec2, DVec2)) -> bool {
+    point.cmpge(min).all() && point.cmple(max).all()
+}
+
+/// The boundary is inclusive.
+fn point_in_circle(point: DVec2, center: DVec2, radius: f64) -> bool {
+    point.distance_squared(center) <= radius * radius
+}
+
+/// 0 if `src` is outside `dest`, 1 if it's entirely inside, and 2 if they overlap.
+/// Touching edges count as overlapping.
+fn rectangle_in_rectangle(src: (DVec2, DVec2), dest: (DVec2, DVec2)) -> i32 {
+    let (src_min, src_max) = src;
+    let (dest_min, dest_max) = dest;
+    if src_max.cmplt(dest_min).any() || src_min.cmpgt(dest_max).any() {
+        0
+    } else if point_in_rectangle(src_min, dest) && point_in_rectangle(src_max, dest) {
+        1
+    } else {
+        2
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gm_color() {
+        // c_orange, make_color_rgb(255, 160, 64)
+        let color = gm_color(0x40A0FF, 0.5);
+        assert_eq!(
+            [color.r, color.g, color.b],
+            [1.0, 160.0 / 255.0, 64.0 / 255.0]
+        );
+        assert_eq!(color.a, 0.5);
+    }
+
+    #[test]
+    fn test_point_in_rectangle() {
+        let rect = rect_from_corners(10.0, 10.0, 0.0, 0.0);
+        assert!(point_in_rectangle(dvec2(5.0, 5.0), rect));
+        assert!(point_in_rectangle(dvec2(10.0, 0.0), rect));
+        assert!(point_in_rectangle(dvec2(0.0, 10.0), rect));
+        assert!(!point_in_rectangle(dvec2(10.5, 5.0), rect));
+    }
+
+    #[test]
+    fn test_point_in_circle() {
+        let center = dvec2(0.0, 0.0);
+        assert!(point_in_circle(dvec2(3.0, 4.0), center, 5.0));
+        assert!(point_in_circle(dvec2(0.0, -5.0), center, 5.0));
+        assert!(!point_in_circle(dvec2(3.0, 4.1), center, 5.0));
+    }
+
+    #[test]
+    fn test_rectangle_in_rectangle() {
+        let dest = rect_from_corners(0.0, 0.0, 10.0, 10.0);
+        let rect = |x1, y1, x2, y2| rect_from_corners(x1, y1, x2, y2);
+        assert_eq!(rectangle_in_rectangle(rect(2.0, 2.0, 8.0, 8.0), dest), 1);
+        assert_eq!(rectangle_in_rectangle(rect(0.0, 0.0, 10.0, 10.0), dest), 1);
+        assert_eq!(rectangle_in_rectangle(rect(5.0, 5.0, 15.0, 15.0), dest), 2);
+        assert_eq!(
+            rectangle_in_rectangle(rect(10.0, 10.0, 20.0, 20.0), dest),
+            2
+        );
+        assert_eq!(rectangle_in_rectangle(rect(11.0, 0.0, 20.0, 10.0), dest), 0);
+    }
+
+    fn test_chunk() -> gmk_file::ResourceChunk<()> {
+        gmk_file::ResourceChunk {
+            ver: 0,
+            items: vec![
+                Some(gmk_file::ResourceItem {
+                    name: gmk_file::String32("obj_player".to_string()),
+                    data: (),
+                }),
+                None,
+                Some(gmk_file::ResourceItem {
+                    name: gmk_file::String32("obj_enemy".to_string()),
+                    data: (),
+                }),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_resource_name() {
+        let chunk = test_chunk();
+        let name = |index: i32| resource_name(&chunk, &index.into()).to_str();
+        assert_eq!(name(0), "obj_player");
+        assert_eq!(name(1), "");
+        assert_eq!(name(2), "obj_enemy");
+        assert_eq!(name(3), "");
+        assert_eq!(name(-1), "");
+    }
+
+    #[test]
+    fn test_resource_exists() {
+        let chunk = test_chunk();
+        let exists = |index: i32| resource_exists(&chunk, &index.into());
+        assert!(exists(0));
+        // deleted
+        assert!(!exists(1));
+        assert!(exists(2));
+        assert!(!exists(3));
+        assert!(!exists(-1));
+    }
+
+    #[test]
+    fn test_rectangle_vertices() {
+        let vertices = rectangle_vertices(
+            vec2(10.0, 20.0),
+            vec2(30.0, 40.0),
+            [RED, GREEN, BLUE, WHITE],
+        );
+        let corners = vertices.map(|v| (v.position.truncate(), v.color));
+        assert_eq!(
+            corners,
+            [
+                (vec2(10.0, 20.0), RED),
+                (vec2(30.0, 20.0), GREEN),
+                (vec2(30.0, 40.0), BLUE),
+                (vec2(10.0, 40.0), WHITE),
+            ]
+        );
     }
 }
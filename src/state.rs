@@ -1,5 +1,6 @@
 #![allow(dead_code)]
 
+use std::cell::Cell;
 use std::collections::HashMap;
 
 use macroquad::prelude::*;
@@ -8,12 +9,19 @@ use serde::{Serialize, Serializer};
 
 use gml::eval::Global as _;
 
-pub use self::global::{Action, Event, FontAsset, Global, ObjectAsset};
+pub use self::ds::{DsEntry, DsGrid, DsList, DsMap};
+pub use self::global::{
+    draw_dialog, Action, BlendMode, Event, FontAsset, Global, ObjectAsset, ObjectDefaults, Options,
+    ScriptedDialogs, TextGradient,
+};
+pub use self::input::Input;
 pub use self::instance::{Instance, InstanceAlarm, InstanceState};
 pub use self::room::Room;
 pub use crate::assets::*;
 
+pub mod ds;
 mod global;
+mod input;
 mod instance;
 mod room;
 
@@ -26,6 +34,13 @@ fn default<T: Default>() -> T {
     Default::default()
 }
 
+/// Script errors are logged rather than propagated, so one bad event doesn't stop the game.
+fn log_script_result<T>(result: gml::eval::Result<T>) {
+    if let Err(error) = result {
+        log::error!("{error}");
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct DoubleMap<V> {
     pub names: HashMap<String, u32>,
@@ -57,6 +72,8 @@ impl<V> std::ops::Index<&str> for DoubleMap<V> {
     }
 }
 
+/// Drawn at room positions, as the view's camera is set, see
+/// [`Screen::begin()`](global::Screen::begin).
 trait Draw {
     fn draw(&self, assets: &Global, view: &View);
 }
@@ -67,6 +84,19 @@ pub struct View {
     pub size: UVec2,
 }
 
+impl View {
+    /// Where a position in the view, like the mouse once the window scaling is undone, is
+    /// in the room.
+    pub fn screen_to_room(&self, pos: Vec2) -> Vec2 {
+        pos + self.offset.as_vec2()
+    }
+
+    /// Where to draw a room position in the view, the inverse of [`Self::screen_to_room()`].
+    pub fn room_to_screen(&self, pos: Vec2) -> Vec2 {
+        pos - self.offset.as_vec2()
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct Layer {
     pub enabled: bool,
@@ -75,6 +105,17 @@ pub struct Layer {
     #[serde(skip)]
     pub source: Option<Rect>,
     pub tile: bool,
+    /// Pixels the layer scrolls each step.
+    pub speed: IVec2,
+    /// How far the layer has scrolled from `pos` so far.
+    pub scroll_offset: Cell<IVec2>,
+}
+
+impl Layer {
+    pub fn scroll(&self) {
+        self.scroll_offset
+            .set(self.scroll_offset.get() + self.speed);
+    }
 }
 
 impl Draw for Layer {
@@ -85,9 +126,8 @@ impl Draw for Layer {
         let assets = global.assets.borrow();
         let bg = assets.backgrounds.get(self.asset);
 
-        let pos = self.pos - view.offset;
+        let pos = (self.pos + self.scroll_offset.get()).as_vec2();
         if !self.tile {
-            let pos = pos.as_vec2();
             draw_texture_ex(
                 bg.texture,
                 pos.x,
@@ -99,10 +139,15 @@ impl Draw for Layer {
                 },
             )
         } else {
-            let tiles = (view.size + bg.size - uvec2(1, 1)) % bg.size;
+            // start from the tile that covers the view's top-left corner, wherever the
+            // layer has scrolled to
+            let pos = view.room_to_screen(pos).as_ivec2();
+            let size = bg.size.as_ivec2().max(ivec2(1, 1));
+            let start = ivec2(pos.x.rem_euclid(size.x), pos.y.rem_euclid(size.y)) - size;
+            let tiles = (view.size.as_ivec2() - start + size - ivec2(1, 1)) / size;
             for ix in 0..tiles.x {
                 for iy in 0..tiles.y {
-                    let pos = (pos + (view.size * uvec2(ix, iy)).as_ivec2()).as_vec2();
+                    let pos = view.screen_to_room((start + size * ivec2(ix, iy)).as_vec2());
                     draw_texture_ex(
                         bg.texture,
                         pos.x,
@@ -138,13 +183,19 @@ where
     s.end()
 }
 
+/// Shortest distance between the edges of two rects, 0 if they overlap.
+fn rect_distance(a: &Rect, b: &Rect) -> f32 {
+    let dx = (a.left() - b.right()).max(b.left() - a.right()).max(0.0);
+    let dy = (a.top() - b.bottom()).max(b.top() - a.bottom()).max(0.0);
+    dx.hypot(dy)
+}
+
 impl Draw for Tile {
-    fn draw(&self, global: &Global, view: &View) {
-        let pos = (self.pos - view.offset).as_vec2();
+    fn draw(&self, global: &Global, _view: &View) {
         draw_texture_ex(
             global.assets.borrow().backgrounds.get(self.asset).texture,
-            pos.x,
-            pos.y,
+            self.pos.x as f32,
+            self.pos.y as f32,
             WHITE,
             DrawTextureParams {
                 source: Some(self.source),
@@ -250,6 +301,13 @@ pub const KEY_CODES: &[KeyCode] = &[
     KeyCode::F12,
 ];
 
+/// The GM virtual key for `code`, the reverse of [`key_code()`].
+pub fn key_vk(code: KeyCode) -> i32 {
+    (0..=255)
+        .find(|&vk| gmk_file::Key::try_from(vk).is_ok_and(|key| key_code(key) == code))
+        .unwrap_or_default()
+}
+
 pub fn key_code(vk: gmk_file::Key) -> KeyCode {
     use gmk_file::Key;
 
@@ -348,3 +406,103 @@ pub fn key_code(vk: gmk_file::Key) -> KeyCode {
         _ => KeyCode::Unknown,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use macroquad::prelude::Rect;
+
+    use super::*;
+
+    thread_local! {
+        static LOGGED: RefCell<Vec<String>> = RefCell::default();
+    }
+
+    /// Keeps the messages logged by each thread, as tests run in parallel.
+    struct CaptureLogger;
+
+    impl log::Log for CaptureLogger {
+        fn enabled(&self, _metadata: &log::Metadata<'_>) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record<'_>) {
+            let message = format!("{}: {}", record.level(), record.args());
+            LOGGED.with(|logged| logged.borrow_mut().push(message));
+        }
+
+        fn flush(&self) {}
+    }
+
+    static LOGGER: CaptureLogger = CaptureLogger;
+
+    /// The messages logged on this thread while running `f`.
+    fn capture_logs(f: impl FnOnce()) -> Vec<String> {
+        // only the first test to get here installs it
+        log::set_logger(&LOGGER).ok();
+        log::set_max_level(log::LevelFilter::Trace);
+        LOGGED.with(|logged| logged.borrow_mut().clear());
+        f();
+        LOGGED.with(|logged| logged.take())
+    }
+
+    #[test]
+    fn test_script_error_logged() {
+        let mut game = global::tests::TestGame::default();
+        let object_index = game.object(
+            "obj_broken",
+            &[(gmk_file::EventId::Create, "missing_function();")],
+        );
+        game.room("rm_test", &[(object_index, 0, 0)]);
+
+        let messages = capture_logs(|| {
+            game.start();
+        });
+        let [message] = &messages[..] else {
+            panic!("expected one message: {messages:?}");
+        };
+        assert!(message.starts_with(r#"ERROR: function "missing_function" has no definition"#));
+        assert!(message.ends_with("in object obj_broken, Create event"));
+    }
+
+    #[test]
+    fn test_layer_scroll() {
+        let layer = Layer {
+            enabled: true,
+            asset: AssetId::new(0),
+            pos: ivec2(10, 0),
+            source: None,
+            tile: true,
+            speed: ivec2(-2, 1),
+            scroll_offset: default(),
+        };
+        layer.scroll();
+        assert_eq!(layer.scroll_offset.get(), ivec2(-2, 1));
+        layer.scroll();
+        assert_eq!(layer.scroll_offset.get(), ivec2(-4, 2));
+        assert_eq!(layer.pos, ivec2(10, 0));
+    }
+
+    #[test]
+    fn test_view_conversion() {
+        let view = View {
+            offset: ivec2(320, -40),
+            size: uvec2(640, 480),
+        };
+        assert_eq!(view.screen_to_room(vec2(10.0, 20.0)), vec2(330.0, -20.0));
+        assert_eq!(view.room_to_screen(vec2(330.0, -20.0)), vec2(10.0, 20.0));
+        assert_eq!(view.room_to_screen(vec2(320.0, -40.0)), Vec2::ZERO);
+    }
+
+    #[test]
+    fn test_rect_distance() {
+        let a = Rect::new(0.0, 0.0, 10.0, 10.0);
+        // 5 pixel gap to the right
+        assert_eq!(rect_distance(&a, &Rect::new(15.0, 0.0, 10.0, 10.0)), 5.0);
+        // 3, 4 gap diagonally
+        assert_eq!(rect_distance(&a, &Rect::new(-13.0, 14.0, 10.0, 10.0)), 5.0);
+        assert_eq!(rect_distance(&a, &Rect::new(5.0, 5.0, 10.0, 10.0)), 0.0);
+        assert_eq!(rect_distance(&a, &Rect::new(10.0, 0.0, 10.0, 10.0)), 0.0);
+    }
+}
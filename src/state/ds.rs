@@ -0,0 +1,419 @@
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+
+use gml::eval::{Global, Object, ObjectId, Value};
+
+/// A value stored in a data structure. Nested structures are just ids to scripts, but
+/// `ds_map_add_list()` etc. mark them so `json_encode()` knows to follow them.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DsEntry {
+    Value(Value),
+    List(ObjectId),
+    Map(ObjectId),
+}
+
+impl DsEntry {
+    pub fn value(&self) -> Value {
+        match self {
+            Self::Value(value) => value.clone(),
+            Self::List(id) | Self::Map(id) => (*id).into(),
+        }
+    }
+
+    fn to_json(&self, global: &dyn Global) -> serde_json::Value {
+        use serde_json::Value as Json;
+
+        match self {
+            Self::Value(Value::Undefined) => Json::Null,
+            Self::Value(Value::Bool(value)) => Json::Bool(*value),
+            Self::Value(Value::Int(value)) => Json::from(*value),
+            Self::Value(Value::Float(value)) => Json::from(*value),
+            Self::Value(Value::String(value)) => Json::from(value.as_str()),
+            Self::List(id) => {
+                with_ds(global, *id, |list: &DsList| list.to_json(global)).unwrap_or(Json::Null)
+            }
+            Self::Map(id) => {
+                with_ds(global, *id, |map: &DsMap| map.to_json(global)).unwrap_or(Json::Null)
+            }
+        }
+    }
+
+    /// Arrays and objects are created as new lists and maps, and like GM booleans become 1 or 0.
+    fn from_json(global: &dyn Global, json: serde_json::Value) -> Self {
+        use serde_json::Value as Json;
+
+        match json {
+            Json::Null => Self::Value(Value::Undefined),
+            Json::Bool(value) => Self::Value(i32::from(value).into()),
+            Json::Number(value) => Self::Value(
+                match value.as_i64().and_then(|value| i32::try_from(value).ok()) {
+                    Some(value) => value.into(),
+                    None => value.as_f64().unwrap_or_default().into(),
+                },
+            ),
+            Json::String(value) => Self::Value(value.into()),
+            Json::Array(items) => {
+                let list = DsList::default();
+                list.items
+                    .borrow_mut()
+                    .extend(items.into_iter().map(|item| Self::from_json(global, item)));
+                Self::List(global.new_instance(Rc::new(list)))
+            }
+            Json::Object(items) => {
+                Self::Map(global.new_instance(Rc::new(DsMap::from_json(global, items))))
+            }
+        }
+    }
+}
+
+/// Calls `f` with the data structure `id`, if it exists and is a `T`.
+pub fn with_ds<T: 'static, R>(
+    global: &dyn Global,
+    id: ObjectId,
+    f: impl FnOnce(&T) -> R,
+) -> Option<R> {
+    let object = global.instance(id)?;
+    let ds = object.as_any()?.downcast_ref::<T>()?;
+    Some(f(ds))
+}
+
+/// Removes `id` from `instances` if it's a `T`, so it's freed once nothing else holds it.
+/// Returns whether it was removed.
+pub fn destroy_ds<T: 'static>(
+    instances: &RefCell<HashMap<ObjectId, Rc<dyn Object>>>,
+    id: ObjectId,
+) -> bool {
+    let mut instances = instances.borrow_mut();
+    let is_ds = instances
+        .get(&id)
+        .and_then(|object| object.as_any())
+        .is_some_and(|any| any.is::<T>());
+    if is_ds {
+        instances.remove(&id);
+    }
+    is_ds
+}
+
+#[derive(Default, Debug)]
+pub struct DsList {
+    pub items: RefCell<Vec<DsEntry>>,
+}
+
+impl DsList {
+    fn to_json(&self, global: &dyn Global) -> serde_json::Value {
+        let items = self.items.borrow();
+        items.iter().map(|item| item.to_json(global)).collect()
+    }
+}
+
+impl Object for DsList {
+    fn debug_index_length(&self) -> Option<usize> {
+        Some(self.items.borrow().len())
+    }
+
+    fn debug_entries(&self) -> Option<Vec<(String, Value)>> {
+        let items = self.items.borrow();
+        Some(gml::eval::index_entries(items.iter().map(DsEntry::value)))
+    }
+
+    fn index(&self, args: &[Value]) -> gml::eval::Result<Option<Value>> {
+        let index = args.first().cloned().unwrap_or_default().to_int();
+        Ok(usize::try_from(index)
+            .ok()
+            .and_then(|index| self.items.borrow().get(index).map(DsEntry::value)))
+    }
+
+    fn set_index(&self, args: &[Value], value: Value) -> gml::eval::Result {
+        let index = args.first().cloned().unwrap_or_default().to_int();
+        let mut items = self.items.borrow_mut();
+        if let Some(item) = usize::try_from(index)
+            .ok()
+            .and_then(|index| items.get_mut(index))
+        {
+            *item = DsEntry::Value(value);
+        }
+        Ok(())
+    }
+
+    fn as_any(&self) -> Option<&dyn Any> {
+        Some(self)
+    }
+}
+
+/// Keys are compared as strings, so `1` and `"1"` are the same key.
+#[derive(Default, Debug)]
+pub struct DsMap {
+    pub items: RefCell<BTreeMap<String, DsEntry>>,
+}
+
+impl DsMap {
+    pub fn get(&self, key: &Value) -> Option<DsEntry> {
+        self.items.borrow().get(&key.to_str()).cloned()
+    }
+
+    pub fn insert(&self, key: &Value, entry: DsEntry) {
+        self.items.borrow_mut().insert(key.to_str(), entry);
+    }
+
+    pub fn remove(&self, key: &Value) {
+        self.items.borrow_mut().remove(&key.to_str());
+    }
+
+    fn to_json(&self, global: &dyn Global) -> serde_json::Value {
+        let items = self.items.borrow();
+        items
+            .iter()
+            .map(|(key, item)| (key.clone(), item.to_json(global)))
+            .collect::<serde_json::Map<_, _>>()
+            .into()
+    }
+
+    fn from_json(global: &dyn Global, items: serde_json::Map<String, serde_json::Value>) -> Self {
+        let map = Self::default();
+        map.items.borrow_mut().extend(
+            items
+                .into_iter()
+                .map(|(key, item)| (key, DsEntry::from_json(global, item))),
+        );
+        map
+    }
+}
+
+impl Object for DsMap {
+    fn debug_entries(&self) -> Option<Vec<(String, Value)>> {
+        let items = self.items.borrow();
+        Some(
+            items
+                .iter()
+                .map(|(key, entry)| (key.clone(), entry.value()))
+                .collect(),
+        )
+    }
+
+    fn index(&self, args: &[Value]) -> gml::eval::Result<Option<Value>> {
+        let key = args.first().cloned().unwrap_or_default();
+        Ok(self.get(&key).map(|entry| entry.value()))
+    }
+
+    fn set_index(&self, args: &[Value], value: Value) -> gml::eval::Result {
+        let key = args.first().cloned().unwrap_or_default();
+        self.insert(&key, DsEntry::Value(value));
+        Ok(())
+    }
+
+    fn as_any(&self) -> Option<&dyn Any> {
+        Some(self)
+    }
+}
+
+/// A fixed size 2D grid of values, initially 0.
+#[derive(Debug)]
+pub struct DsGrid {
+    pub width: usize,
+    pub height: usize,
+    items: RefCell<Vec<Value>>,
+}
+
+impl DsGrid {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            items: RefCell::new(vec![0.into(); width * height]),
+        }
+    }
+
+    fn offset(&self, x: &Value, y: &Value) -> Option<usize> {
+        let x = usize::try_from(x.to_int())
+            .ok()
+            .filter(|&x| x < self.width)?;
+        let y = usize::try_from(y.to_int())
+            .ok()
+            .filter(|&y| y < self.height)?;
+        Some(y * self.width + x)
+    }
+
+    /// Undefined if `x` or `y` is out of bounds.
+    pub fn get(&self, x: &Value, y: &Value) -> Value {
+        self.offset(x, y)
+            .map(|offset| self.items.borrow()[offset].clone())
+            .unwrap_or_default()
+    }
+
+    /// Ignored if `x` or `y` is out of bounds.
+    pub fn set(&self, x: &Value, y: &Value, value: Value) {
+        if let Some(offset) = self.offset(x, y) {
+            self.items.borrow_mut()[offset] = value;
+        }
+    }
+
+    pub fn clear(&self, value: Value) {
+        self.items.borrow_mut().fill(value);
+    }
+}
+
+impl Object for DsGrid {
+    fn index(&self, args: &[Value]) -> gml::eval::Result<Option<Value>> {
+        let [x, y] = grid_args(args);
+        Ok(Some(self.get(&x, &y)))
+    }
+
+    fn set_index(&self, args: &[Value], value: Value) -> gml::eval::Result {
+        let [x, y] = grid_args(args);
+        self.set(&x, &y, value);
+        Ok(())
+    }
+
+    fn as_any(&self) -> Option<&dyn Any> {
+        Some(self)
+    }
+}
+
+fn grid_args(args: &[Value]) -> [Value; 2] {
+    [0, 1].map(|index| args.get(index).cloned().unwrap_or_default())
+}
+
+/// The JSON for map `id` and any marked nested structures, or an empty string if it's not a map.
+pub fn json_encode(global: &dyn Global, id: ObjectId) -> String {
+    with_ds(global, id, |map: &DsMap| map.to_json(global).to_string()).unwrap_or_default()
+}
+
+/// Creates a map from JSON, or -1 if it's invalid. Like GM, anything but an object
+/// is wrapped in a map with the key `"default"`.
+pub fn json_decode(global: &dyn Global, text: &str) -> Value {
+    let map = match serde_json::from_str(text) {
+        Ok(serde_json::Value::Object(items)) => DsMap::from_json(global, items),
+        Ok(json) => {
+            let map = DsMap::default();
+            map.insert(
+                &"default".to_string().into(),
+                DsEntry::from_json(global, json),
+            );
+            map
+        }
+        Err(_) => return (-1).into(),
+    };
+    global.new_instance(Rc::new(map)).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::global::tests::TestGame;
+
+    fn test_global() -> crate::state::Global {
+        let mut game = TestGame::default();
+        game.room("rm_test", &[]);
+        game.start()
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let global = test_global();
+
+        let list = DsList::default();
+        list.items.borrow_mut().extend([
+            DsEntry::Value(1.into()),
+            DsEntry::Value("two".to_string().into()),
+        ]);
+        let list_id = global.new_instance(Rc::new(list));
+
+        let map = DsMap::default();
+        map.insert(
+            &"name".to_string().into(),
+            DsEntry::Value("iji".to_string().into()),
+        );
+        map.insert(&"health".to_string().into(), DsEntry::Value(2.5.into()));
+        map.insert(&"items".to_string().into(), DsEntry::List(list_id));
+        let map_id = global.new_instance(Rc::new(map));
+
+        let json = json_encode(&global, map_id);
+        assert_eq!(json, r#"{"health":2.5,"items":[1,"two"],"name":"iji"}"#);
+
+        let decoded_id = json_decode(&global, &json).as_object_id().unwrap();
+        assert_ne!(decoded_id, map_id);
+        assert_eq!(json_encode(&global, decoded_id), json);
+
+        let items = with_ds(&global, decoded_id, |map: &DsMap| {
+            map.get(&"items".to_string().into())
+        });
+        assert!(matches!(items, Some(Some(DsEntry::List(_)))));
+    }
+
+    #[test]
+    fn test_json_decode_conventions() {
+        let global = test_global();
+        assert_eq!(json_decode(&global, "{"), Value::Int(-1));
+
+        let id = json_decode(&global, "[true, false]")
+            .as_object_id()
+            .unwrap();
+        assert_eq!(json_encode(&global, id), r#"{"default":[1,0]}"#);
+    }
+
+    #[test]
+    fn test_debug_entries() {
+        let map = DsMap::default();
+        map.insert(&"b".to_string().into(), DsEntry::Value(2.into()));
+        map.insert(&1.into(), DsEntry::Value("one".to_string().into()));
+        map.insert(&"list".to_string().into(), DsEntry::List(ObjectId(100001)));
+        assert_eq!(
+            map.debug_entries().unwrap(),
+            [
+                ("1".to_string(), Value::String("one".into())),
+                ("b".to_string(), Value::Int(2)),
+                ("list".to_string(), Value::Int(100001)),
+            ]
+        );
+
+        let list = DsList::default();
+        list.items.borrow_mut().push(DsEntry::Value(5.into()));
+        assert_eq!(
+            list.debug_entries().unwrap(),
+            [("0".to_string(), Value::Int(5))]
+        );
+    }
+
+    #[test]
+    fn test_destroy() {
+        let global = test_global();
+        let list_id = global.new_instance(Rc::<DsList>::default());
+        let map_id = global.new_instance(Rc::<DsMap>::default());
+
+        let room = global.room.borrow();
+        let count = room.script_instances.borrow().len();
+
+        // the wrong kind isn't destroyed
+        assert!(!destroy_ds::<DsMap>(&room.script_instances, list_id));
+        assert!(destroy_ds::<DsList>(&room.script_instances, list_id));
+        assert!(with_ds(&global, list_id, |_: &DsList| ()).is_none());
+        assert!(!destroy_ds::<DsList>(&room.script_instances, list_id));
+
+        assert!(with_ds(&global, map_id, |_: &DsMap| ()).is_some());
+        assert_eq!(room.script_instances.borrow().len(), count - 1);
+    }
+
+    #[test]
+    fn test_grid() {
+        let grid = DsGrid::new(3, 2);
+        assert_eq!((grid.width, grid.height), (3, 2));
+        assert_eq!(grid.get(&2.into(), &1.into()), Value::Int(0));
+
+        grid.set(&2.into(), &1.into(), "x".to_string().into());
+        assert_eq!(grid.get(&2.into(), &1.into()), Value::String("x".into()));
+        assert_eq!(grid.get(&1.into(), &0.into()), Value::Int(0));
+
+        // out of bounds
+        assert_eq!(grid.get(&3.into(), &0.into()), Value::Undefined);
+        assert_eq!(grid.get(&(-1).into(), &0.into()), Value::Undefined);
+        grid.set(&0.into(), &2.into(), 1.into());
+
+        grid.clear(5.into());
+        assert_eq!(
+            grid.index(&[0.into(), 0.into()]).unwrap(),
+            Some(Value::Int(5))
+        );
+    }
+}
@@ -1,5 +1,6 @@
 use std::cell::{Ref, RefCell};
 use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
 use std::rc::Rc;
 use std::sync::atomic;
 use std::sync::atomic::AtomicU32;
@@ -12,28 +13,70 @@ use serde::Serialize;
 
 use super::*;
 use crate::assets::{Assets, Loader};
-
-pub use fonts::FontAsset;
-pub use objects::{Action, Event, ObjectAsset, ObjectType};
-
+use parse_cache::ParseCache;
+
+pub use blend::{BlendMaterials, BlendMode};
+#[cfg(test)]
+pub use dialogs::DialogAnswer;
+pub use dialogs::{draw_dialog, Dialogs, OverlayDialogs, ScriptedDialogs};
+pub use fonts::{FontAsset, TextGradient};
+pub use objects::{Action, Event, ObjectAsset, ObjectDefaults, ObjectType};
+pub use screen::Screen;
+pub use sounds::SoundMap;
+pub use surfaces::SurfaceMap;
+
+mod blend;
+mod dialogs;
 mod fonts;
 mod objects;
+mod parse_cache;
+mod screen;
+mod sounds;
+mod surfaces;
+
+/// How to run the game, separately from the game's own settings.
+#[derive(Default, Debug)]
+pub struct Options {
+    /// Where to keep the [`ParseCache`] between runs, if anywhere.
+    pub parse_cache: Option<PathBuf>,
+    /// Error on implicit string conversions in scripts, see [`gml::Context::strict`].
+    pub strict: bool,
+    /// Error when a loop runs more times than this, see [`gml::Context::iteration_limit`].
+    pub iteration_limit: Option<u32>,
+    /// Run exactly one step per frame, rather than as many as the room speed and frame
+    /// time call for, so runs are repeatable.
+    pub fixed_step: bool,
+}
 
 #[derive(Serialize)]
 pub struct Global {
     #[serde(skip)]
     pub content: gmk_file::Content,
+    /// The directory script file functions are restricted to, see [`Self::data_path()`].
+    pub data_root: PathBuf,
     pub assets: RefCell<Assets>,
     pub object_types: HashMap<u32, ObjectAsset>,
     pub consts: gml::eval::Namespace,
+    /// Resource names to their ids. Unlike `consts`, global variables can shadow these.
+    pub resources: gml::eval::Namespace,
     pub vars: gml::eval::Namespace,
     #[serde(skip)]
     pub scripts: DoubleMap<gml::ast::Script>,
     pub room_order_index: RefCell<usize>,
     pub room: RefCell<Room>,
     pub next_room_index: RefCell<Option<u32>>,
+    /// Set by `game_restart()`, handled with the room change.
+    pub restarting: RefCell<bool>,
+    /// Events raised while scripts were running, dispatched in [`Self::cleanup()`].
+    pub pending_events: RefCell<Vec<Event>>,
     pub state: RefCell<GlobalState>,
+    pub input: RefCell<Input>,
     pub last_instance_id: AtomicU32,
+    /// Replace with a [`ScriptedDialogs`] to run headless.
+    #[serde(skip)]
+    pub dialogs: Box<dyn Dialogs>,
+    #[serde(skip)]
+    pub options: Options,
 }
 
 impl std::fmt::Debug for Global {
@@ -47,24 +90,43 @@ impl std::fmt::Debug for Global {
 }
 
 impl Global {
-    pub fn new(content: gmk_file::Content) -> Self {
-        let consts = define_consts(&content);
+    pub fn new(content: gmk_file::Content, data_root: PathBuf, options: Options) -> Self {
+        let resources = define_resources(&content);
+        let consts = define_consts(&content, &resources);
         let object_types = define_objects(&content);
-        let scripts = define_scripts(&content);
+        let scripts = define_scripts(&content, options.parse_cache.as_deref());
         let last_instance_id = AtomicU32::new(content.last_instance_id);
+        let state = GlobalState {
+            fonts: fonts::FontMap::new(&content.fonts),
+            screen: Screen::new(content.settings.scaling),
+            sounds: SoundMap::new(
+                content
+                    .sounds
+                    .iter()
+                    .map(|(index, _, sound)| (index, sound.volume, sound.pan)),
+            ),
+            ..default()
+        };
 
         Self {
             content,
+            data_root,
             assets: default(),
             object_types,
             consts,
+            resources,
             vars: default(),
             scripts,
             room_order_index: RefCell::new(0),
             room: RefCell::new(Room::new(default())),
             next_room_index: default(),
-            state: default(),
+            restarting: default(),
+            pending_events: default(),
+            state: RefCell::new(state),
+            input: default(),
             last_instance_id,
+            dialogs: Box::<OverlayDialogs>::default(),
+            options,
         }
     }
 
@@ -80,6 +142,11 @@ impl Global {
         self.assets.borrow()
     }
 
+    /// Where a script file path is under [`Self::data_root`], see [`sandbox_path()`].
+    pub fn data_path(&self, path: &str) -> Option<PathBuf> {
+        sandbox_path(&self.data_root, path)
+    }
+
     pub fn next_instance_id(&self) -> u32 {
         self.last_instance_id
             .fetch_add(1, atomic::Ordering::Relaxed)
@@ -102,6 +169,13 @@ impl Global {
         *self.next_room_index.borrow_mut() = Some(index);
     }
 
+    /// Goes to the first room without keeping any instances or loaded assets.
+    pub fn game_restart(&self) {
+        *self.restarting.borrow_mut() = true;
+        *self.room_order_index.borrow_mut() = 0;
+        self.goto_room_order(0);
+    }
+
     fn cleanup_room_goto(&self) {
         let Some(index) = self.next_room_index.borrow_mut().take() else {
             return;
@@ -111,7 +185,25 @@ impl Global {
 
         let def = &self.content.rooms[index];
         assert_eq!(&*def.creation_code, "");
+        let restarting = self.restarting.replace(false);
+        let persistent_instances = if restarting {
+            vec![]
+        } else {
+            room.take_persistent_instances()
+        };
         *room = Room::new(index);
+        // alarms are script objects of the room, so they'd be dropped with it
+        room.script_instances.borrow_mut().extend(
+            persistent_instances
+                .iter()
+                .map(|instance| (instance.alarm_id, instance.alarm.clone() as Rc<dyn Object>)),
+        );
+        if restarting {
+            // nothing refers to the old assets once the old room and added fonts are dropped
+            self.state.borrow_mut().fonts = fonts::FontMap::new(&self.content.fonts);
+            self.assets.borrow_mut().clear();
+        }
+        self.loader().preload_room(def);
         room.load(self, def);
         // drop mut borrow because instance_create() wants to borrow room
         drop(room);
@@ -119,12 +211,23 @@ impl Global {
         for object_type in self.object_types.values() {
             object_type.object.instances.borrow_mut().clear();
         }
+        for instance in &persistent_instances {
+            self.object_types[&instance.object_index]
+                .object
+                .instances
+                .borrow_mut()
+                .insert(instance.id, instance.clone());
+        }
         for res in &def.instances {
-            let instance = self.instance_create(
-                ObjectId::new(res.id),
-                ivec2(res.pos.0, res.pos.1),
-                res.object_index,
-            );
+            // like GM, a persistent instance isn't placed again when coming back to its room
+            let id = ObjectId::new(res.id);
+            if persistent_instances
+                .iter()
+                .any(|instance| instance.id == id)
+            {
+                continue;
+            }
+            let instance = self.instance_create(id, ivec2(res.pos.0, res.pos.1), res.object_index);
             if !res.creation_code.is_empty() {
                 let creation_script = gml::parse(
                     &format!("room/{index}/{id}", index = index, id = res.id),
@@ -132,9 +235,7 @@ impl Global {
                 )
                 .expect("invalid script");
                 let mut ctx = Context::new(self, instance.id, instance.clone());
-                if let Err(error) = ctx.exec_script(&creation_script, &[]) {
-                    eprintln!("{error}");
-                }
+                log_script_result(ctx.exec_script(&creation_script, &[]));
             }
         }
         let room = self.room.borrow();
@@ -148,26 +249,87 @@ impl Global {
         self.vars.insert("face", dummy);
 
         self.dispatch(Event::Create);
+
+        // persistent instances were already created in a previous room
+        room.object_instances.borrow_mut().values.extend(
+            persistent_instances
+                .into_iter()
+                .map(|instance| (instance.id.instance_id(), instance)),
+        );
+        self.dispatch(Event::RoomStart);
     }
 
     pub fn dispatch(&self, event: Event) {
+        if !self
+            .object_types
+            .values()
+            .any(|object| object.handles(event))
+        {
+            return;
+        }
         self.room.borrow().dispatch(self, event);
     }
 
+    /// Whether instances of `object_index` have an `event` event, including from parents.
+    pub fn object_has_event(&self, object_index: u32, event: Event) -> bool {
+        objects::inherits_event(&self.object_types, object_index, event)
+    }
+
+    /// See [`objects::collision_event()`].
+    pub fn collision_event(&self, object_index: u32, other_index: u32) -> Option<Event> {
+        objects::collision_event(&self.object_types, object_index, other_index)
+    }
+
     pub fn step(&self) {
         self.room.borrow().step(self);
     }
 
     pub fn draw(&self) {
-        self.room.borrow().draw(self);
+        let room = self.room.borrow();
+        {
+            let mut state = self.state.borrow_mut();
+            let camera = state.screen.begin(&room.view);
+            state.surfaces.set_screen_camera(camera);
+        }
+        room.draw(self);
+        self.assets.borrow_mut().texture_switches = take_texture_switches();
+        let mut state = self.state.borrow_mut();
+        // don't let a missing surface_reset_target() redirect the next frame
+        state.surfaces.reset_target();
+        state.screen.end();
+    }
+
+    /// Where a window position, like the mouse, is in the room, after undoing the
+    /// [`Screen`] scaling. See [`View::screen_to_room()`].
+    pub fn screen_to_room(&self, pos: Vec2) -> Vec2 {
+        let pos = self.state.borrow().screen.window_to_view(pos);
+        self.room.borrow().view.screen_to_room(pos)
+    }
+
+    /// Draws the last frame again, without running Draw events, e.g. while a dialog has the
+    /// game paused.
+    pub fn redraw(&self) {
+        self.state.borrow_mut().screen.end();
     }
 
     pub fn dump(&self) {
         println!("{self:#?}");
     }
 
+    /// The same state as the debug server's `/state`, but with sorted keys, so recordings of
+    /// the same run are identical and can be diffed.
+    pub fn state_json(&self) -> String {
+        let value = serde_json::to_value(self).unwrap();
+        serde_json::to_string_pretty(&value).unwrap() + "\n"
+    }
+
     pub fn cleanup(&self) {
         self.room.borrow().cleanup(self);
+        // the room is iterating instances while scripts run, so these couldn't be dispatched then
+        let events = self.pending_events.take();
+        for event in events {
+            self.dispatch(event);
+        }
         self.cleanup_room_goto();
     }
 
@@ -181,6 +343,65 @@ impl Global {
         }
     }
 
+    /// Collision bounds of an instance if it were at `pos`, if it has a mask or sprite.
+    pub fn instance_bounds(&self, instance: &Instance, pos: DVec2) -> Option<Rect> {
+        // not the sprite_asset cached by drawing, so instances that haven't been drawn yet
+        // still collide
+        let sprite_index = instance.state.borrow().collision_sprite_index();
+        let sprite = self.loader().try_get_sprite(sprite_index)?;
+        Some(self.assets().sprites.get(sprite).bounds(pos.as_vec2()))
+    }
+
+    /// Would `instance` overlap any solid instance if it were at `pos`, the opposite of
+    /// `place_free()`.
+    pub fn place_solid(&self, instance: &Instance, pos: DVec2) -> bool {
+        self.place_overlaps(instance, pos, true)
+    }
+
+    /// Would `instance` overlap any other instance if it were at `pos`, the opposite of
+    /// `place_empty()`.
+    pub fn place_occupied(&self, instance: &Instance, pos: DVec2) -> bool {
+        self.place_overlaps(instance, pos, false)
+    }
+
+    fn place_overlaps(&self, instance: &Instance, pos: DVec2, solid_only: bool) -> bool {
+        let Some(bounds) = self.instance_bounds(instance, pos) else {
+            return false;
+        };
+        let instances = self.room.borrow().instances();
+        overlaps_any(instance, bounds, &instances, solid_only, |other| {
+            let other_pos = other.state.borrow().pos;
+            self.instance_bounds(other, other_pos)
+        })
+    }
+
+    /// Shortest distance between the bounds of `instance` and any instance of `object_index`.
+    /// Instances without a sprite or mask are treated as a point.
+    pub fn distance_to_object(&self, instance: &Instance, object_index: u32) -> f64 {
+        // what GM returns when there are no instances
+        const NO_INSTANCES: f64 = 1_000_000.0;
+
+        let bounds = |instance: &Instance| {
+            let pos = instance.state.borrow().pos;
+            self.instance_bounds(instance, pos)
+                .unwrap_or_else(|| Rect::new(pos.x as f32, pos.y as f32, 0.0, 0.0))
+        };
+
+        let Some(object_type) = self.object_types.get(&object_index) else {
+            return NO_INSTANCES;
+        };
+        let self_bounds = bounds(instance);
+        object_type
+            .object
+            .instances
+            .borrow()
+            .values()
+            .filter(|other| other.id != instance.id)
+            .map(|other| f64::from(rect_distance(&self_bounds, &bounds(other))))
+            .reduce(f64::min)
+            .unwrap_or(NO_INSTANCES)
+    }
+
     pub fn instance_number(&self, object_index: u32) -> i32 {
         self.object_types.get(&object_index).map_or(0, |o| {
             o.object
@@ -193,22 +414,28 @@ impl Global {
     }
 
     pub fn instance_create(&self, id: ObjectId, pos: IVec2, object_index: u32) -> Rc<Instance> {
-        let obj = &self.content.objects[object_index];
-
-        assert!(obj.mask_sprite_index < 0);
-        let parent_object_index = obj.parent_object_index.try_into().ok();
+        let object_type = &self.object_types[&object_index];
+        let parent_object_index = object_type.parent_index;
+        let defaults = object_type.defaults.borrow().clone();
 
         let alarm = Rc::<InstanceAlarm>::default();
         let alarm_id = self.new_instance(alarm.clone());
 
-        let instance = Rc::new(Instance {
+        self.add_instance(Instance {
             id,
             state: RefCell::new(InstanceState {
                 pos: pos.as_dvec2(),
-                depth: obj.depth,
+                previous_pos: pos.as_dvec2(),
+                depth: defaults.depth,
                 velocity: default(),
-                visible: obj.visible.into(),
-                sprite_index: obj.sprite_index,
+                friction: 0.0,
+                gravity: 0.0,
+                gravity_direction: 270.0,
+                solid: defaults.solid,
+                visible: defaults.visible,
+                persistent: object_type.persistent,
+                sprite_index: defaults.sprite_index,
+                mask_index: defaults.mask_index,
                 sprite_asset: None,
                 image_speed: 1.0,
                 image_index: 0.0,
@@ -219,7 +446,21 @@ impl Global {
             vars: default(),
             alarm_id,
             alarm,
-        });
+        })
+    }
+
+    /// Creates a copy of `instance` with the same state and variables, at the same position.
+    pub fn instance_copy(&self, instance: &Instance) -> Rc<Instance> {
+        let id = ObjectId::new(self.next_instance_id());
+        let alarm = Rc::new((*instance.alarm).clone());
+        let alarm_id = self.new_instance(alarm.clone());
+        self.add_instance(instance.copy(id, alarm_id, alarm))
+    }
+
+    fn add_instance(&self, instance: Instance) -> Rc<Instance> {
+        let id = instance.id;
+        let object_index = instance.object_index;
+        let instance = Rc::new(instance);
 
         self.room
             .borrow()
@@ -247,67 +488,111 @@ impl gml::eval::Global for Global {
                     .try_into()
                     .expect("invalid room value"),
             ))),
+            "room_width" => Ok(Some((self.room.borrow().size.x as i32).into())),
+            "room_height" => Ok(Some((self.room.borrow().size.y as i32).into())),
+            "score" | "lives" | "health" => Ok(Some(self.state.borrow().stats.get(name))),
+            "keyboard_string" => Ok(Some(self.input.borrow().keyboard_string.clone().into())),
+            "keyboard_lastchar" => Ok(Some(self.input.borrow().keyboard_lastchar.clone().into())),
+            "mouse_x" | "mouse_y" => {
+                let pos = self.screen_to_room(self.input.borrow().mouse_pos);
+                let value = if name == "mouse_x" { pos.x } else { pos.y };
+                Ok(Some(f64::from(value).into()))
+            }
             _ => {
                 if let Some(id) = self.scripts.names.get(name) {
                     Ok(Some(Value::Int((*id).try_into().expect("invalid id"))))
                 } else if let Some(value) = self.consts.get(name) {
                     Ok(Some(value))
+                } else if let Some(value) = self.vars.get(name) {
+                    Ok(Some(value))
                 } else {
-                    self.vars.member(name)
+                    Ok(self.resources.get(name))
                 }
             }
         }
     }
 
     fn set(&self, name: &str, value: Value) -> gml::eval::Result {
-        if self.consts.get(name).is_some() {
+        if name == "room" {
+            let index = value.to_int();
+            let index = u32::try_from(index)
+                .ok()
+                .filter(|&index| self.content.rooms.get_item(index).is_some())
+                .ok_or_else(|| gml::eval::Error::Custom(format!("room {index} does not exist")))?;
+            self.goto_room(index);
+            Ok(())
+        } else if name == "keyboard_string" {
+            self.input.borrow_mut().keyboard_string = value.to_str();
+            Ok(())
+        } else if name == "keyboard_lastchar" {
+            self.input.borrow_mut().keyboard_lastchar = value.to_str();
+            Ok(())
+        } else if matches!(name, "score" | "lives" | "health") {
+            let event = self.state.borrow_mut().stats.set(name, value);
+            self.pending_events.borrow_mut().extend(event);
+            Ok(())
+        } else if matches!(name, "mouse_x" | "mouse_y") || self.consts.get(name).is_some() {
             Err(gml::eval::Error::AssignToValue)
         } else {
             self.vars.set_member(name, value)
         }
     }
 
-    fn instances_all(&self, id: ObjectId) -> Vec<Rc<dyn Object>> {
+    fn strict(&self) -> bool {
+        self.options.strict
+    }
+
+    fn iteration_limit(&self) -> Option<u32> {
+        self.options.iteration_limit
+    }
+
+    fn builtin_var(&self, name: &str) -> bool {
+        matches!(
+            name,
+            "room"
+                | "score"
+                | "lives"
+                | "health"
+                | "keyboard_string"
+                | "keyboard_lastchar"
+                | "mouse_x"
+                | "mouse_y"
+        )
+    }
+
+    fn instance_ids(&self, id: ObjectId) -> Vec<ObjectId> {
         // todo: ObjectId::ALL, etc...
         assert!(id.0 > 0);
         if let Some(object) = self.object_types.get(&id.instance_id()) {
-            // doesn't understand that boxed clones can be a subtype
-            #[allow(clippy::map_clone)]
-            object
-                .object
-                .instances
-                .borrow()
-                .values()
-                .map(|instance| -> Rc<dyn Object> { instance.clone() })
-                .collect()
+            object.object.instances.borrow().keys().copied().collect()
         } else {
-            self.instance(id).into_iter().collect()
+            self.instance(id).map(|_| id).into_iter().collect()
         }
     }
 
     fn instance(&self, id: ObjectId) -> Option<Rc<dyn Object>> {
         let room = self.room.borrow();
-        if let Some(asset) = self.object_types.get(&id.instance_id()) {
+        if room.is_destroyed(id) {
+            None
+        } else if let Some(asset) = self.object_types.get(&id.instance_id()) {
             Some(asset.object.clone())
-        } else if let Some(object) = room.added_instances.borrow().get(&id.instance_id()) {
-            Some(object.clone())
-        } else if let Some(object) = room.script_instances.borrow().get(&id) {
-            Some(object.clone())
-        } else if let Some(object) = room.object_instances.borrow().values.get(&id.instance_id()) {
-            Some(object.clone())
+        } else if let Some(object) = room.instance(id) {
+            Some(object)
         } else {
-            println!("missing instance id: {id:?}");
-            println!("added instance ids this event:");
-            for id in room.added_instances.borrow().keys() {
-                println!("  {id}");
-            }
-            println!("existing instance ids before this event:");
-            for id in room.object_instances.borrow().values.keys() {
-                println!("  {id}");
-            }
-            println!("script object ids:");
-            for id in room.script_instances.borrow().keys() {
-                println!("  {id:?}");
+            log::warn!("missing instance id: {id:?}");
+            if log::log_enabled!(log::Level::Debug) {
+                log::debug!("added instance ids this event:");
+                for id in room.added_instances.borrow().keys() {
+                    log::debug!("  {id}");
+                }
+                log::debug!("existing instance ids before this event:");
+                for id in room.object_instances.borrow().values.keys() {
+                    log::debug!("  {id}");
+                }
+                log::debug!("script object ids:");
+                for id in room.script_instances.borrow().keys() {
+                    log::debug!("  {id:?}");
+                }
             }
             None
         }
@@ -344,9 +629,128 @@ pub struct GlobalState {
     pub color: Color,
     #[serde(skip)]
     pub fonts: fonts::FontMap,
+    #[serde(skip)]
+    pub surfaces: SurfaceMap,
+    #[serde(skip)]
+    pub screen: Screen,
+    #[serde(skip)]
+    pub sounds: SoundMap,
+    #[serde(skip)]
+    pub blend: BlendMaterials,
+    pub stats: GameStats,
+    /// Saved by `draw_push_state()`.
+    #[serde(skip)]
+    pub draw_states: Vec<DrawState>,
+}
+
+impl GlobalState {
+    pub fn push_draw_state(&mut self) {
+        self.draw_states.push(DrawState {
+            color: self.color,
+            font_index: self.fonts.index(),
+        });
+    }
+
+    /// Restores the last pushed draw state, returning false if there isn't one.
+    pub fn pop_draw_state(&mut self) -> bool {
+        let Some(state) = self.draw_states.pop() else {
+            return false;
+        };
+        self.color = state.color;
+        self.fonts.set(state.font_index);
+        true
+    }
 }
 
-fn define_scripts(content: &gmk_file::Content) -> DoubleMap<gml::ast::Script> {
+/// The draw settings libraries change and want to put back afterwards.
+#[derive(Copy, Clone, Debug)]
+pub struct DrawState {
+    pub color: Color,
+    pub font_index: i32,
+}
+
+/// GM's built-in `score`, `lives` and `health` globals.
+#[derive(Debug, Serialize)]
+pub struct GameStats {
+    pub score: Value,
+    pub lives: Value,
+    pub health: Value,
+}
+
+impl Default for GameStats {
+    fn default() -> Self {
+        Self {
+            score: 0.into(),
+            lives: (-1).into(),
+            health: 100.into(),
+        }
+    }
+}
+
+impl GameStats {
+    /// Panics if `name` isn't `score`, `lives` or `health`.
+    pub fn get(&self, name: &str) -> Value {
+        match name {
+            "score" => self.score.clone(),
+            "lives" => self.lives.clone(),
+            "health" => self.health.clone(),
+            _ => unreachable!("not a game stat: {name}"),
+        }
+    }
+
+    /// Same names as [`Self::get()`]. Returns the event to dispatch if `lives` or `health` just
+    /// dropped to 0 or below.
+    pub fn set(&mut self, name: &str, value: Value) -> Option<Event> {
+        let (stat, event) = match name {
+            "score" => (&mut self.score, None),
+            "lives" => (&mut self.lives, Some(Event::NoMoreLives)),
+            "health" => (&mut self.health, Some(Event::NoMoreHealth)),
+            _ => unreachable!("not a game stat: {name}"),
+        };
+        let ran_out = stat.to_float() > 0.0 && value.to_float() <= 0.0;
+        *stat = value;
+        event.filter(|_| ran_out)
+    }
+}
+
+/// Resolves a script file path relative to `root`, or `None` if it's absolute or uses `..`,
+/// so game data can't read outside `root`. GM paths use `\` separators.
+fn sandbox_path(root: &Path, path: &str) -> Option<PathBuf> {
+    let path = path.replace('\\', "/");
+    let mut result = root.to_path_buf();
+    for component in Path::new(&path).components() {
+        match component {
+            Component::Normal(name) => result.push(name),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(result)
+}
+
+/// Whether `bounds` of `instance` overlaps any of the other `instances`, or only the solid
+/// ones. `other_bounds` is only called for instances that could collide.
+fn overlaps_any(
+    instance: &Instance,
+    bounds: Rect,
+    instances: &[Rc<Instance>],
+    solid_only: bool,
+    other_bounds: impl Fn(&Instance) -> Option<Rect>,
+) -> bool {
+    instances.iter().any(|other| {
+        if other.id == instance.id || (solid_only && !other.state.borrow().solid) {
+            return false;
+        }
+        other_bounds(other).is_some_and(|other_bounds| bounds.overlaps(&other_bounds))
+    })
+}
+
+fn define_scripts(
+    content: &gmk_file::Content,
+    cache_path: Option<&Path>,
+) -> DoubleMap<gml::ast::Script> {
+    let cache = cache_path.map(ParseCache::load).unwrap_or_default();
+
     // scripts parsed in parallel
     use rayon::prelude::*;
     let scripts = content
@@ -356,24 +760,69 @@ fn define_scripts(content: &gmk_file::Content) -> DoubleMap<gml::ast::Script> {
         .enumerate()
         .flat_map(|(index, item)| {
             item.as_ref().map(|item| {
-                let script = gml::parse(&item.name.0, &item.data.script.0).unwrap();
-                (index as u32, item.name.0.clone(), script)
+                let cached = cache.get(&item.name.0, &item.data.script.0);
+                let parsed = cached.is_none();
+                let script = cached
+                    .map_or_else(|| gml::parse(&item.name.0, &item.data.script.0), Ok)
+                    .unwrap();
+                (index as u32, item, script, parsed)
             })
         })
         .collect::<Vec<_>>();
 
     let mut result = DoubleMap::default();
-
-    for (index, name, script) in scripts {
-        result.names.insert(name, index);
+    // only the current scripts, so the cache doesn't keep growing as scripts change
+    let stale = cache.len() != scripts.len();
+    let mut parsed_any = false;
+    let mut cache = ParseCache::default();
+
+    for (index, item, script, parsed) in scripts {
+        parsed_any |= parsed;
+        cache.insert(&item.name.0, &item.data.script.0, script.clone());
+        result.names.insert(item.name.0.clone(), index);
         result.values.insert(index, script);
     }
 
+    if let Some(cache_path) = cache_path {
+        if parsed_any || stale {
+            cache.save(cache_path);
+        }
+    }
     result
 }
 
-fn define_consts(content: &gmk_file::Content) -> gml::eval::Namespace {
-    let mut vars = gml::eval::Namespace::default();
+fn define_resources(content: &gmk_file::Content) -> gml::eval::Namespace {
+    let vars = gml::eval::Namespace::default();
+    insert_resources(&vars, &content.objects);
+    insert_resources(&vars, &content.rooms);
+    insert_resources(&vars, &content.scripts);
+    insert_resources(&vars, &content.backgrounds);
+    insert_resources(&vars, &content.sprites);
+    insert_resources(&vars, &content.sounds);
+    insert_resources(&vars, &content.paths);
+    insert_resources(&vars, &content.fonts);
+    insert_resources(&vars, &content.timelines);
+    vars
+}
+
+/// Resource names of all types share one namespace, so if a name is used again, the
+/// resource inserted first keeps it.
+fn insert_resources<T>(vars: &gml::eval::Namespace, chunk: &gmk_file::ResourceChunk<T>) {
+    for (index, name, _) in chunk {
+        if vars.get(name).is_some() {
+            log::warn!("resource name {name} is already used, index {index} is unnamed");
+            continue;
+        }
+        vars.insert(name, ObjectId::new(index));
+    }
+}
+
+/// Project constants can use `resources`, but they aren't included.
+fn define_consts(
+    content: &gmk_file::Content,
+    resources: &gml::eval::Namespace,
+) -> gml::eval::Namespace {
+    let vars = gml::eval::Namespace::default();
     use gmk_file::Key;
 
     // vk
@@ -424,6 +873,17 @@ fn define_consts(content: &gmk_file::Content) -> gml::eval::Namespace {
     vars.insert("vk_decimal", vk(Key::Decimal));
     vars.insert("vk_divide", vk(Key::Divide));
 
+    // mouse buttons
+    vars.insert("mb_any", input::MB_ANY);
+    vars.insert("mb_none", input::MB_NONE);
+    vars.insert("mb_left", input::MB_LEFT);
+    vars.insert("mb_right", input::MB_RIGHT);
+    vars.insert("mb_middle", input::MB_MIDDLE);
+
+    // instances
+    vars.insert("self", ObjectId::SELF);
+    vars.insert("other", ObjectId::OTHER);
+
     // colors
     vars.insert("c_aqua", 16776960);
     vars.insert("c_black", 0);
@@ -445,34 +905,179 @@ fn define_consts(content: &gmk_file::Content) -> gml::eval::Namespace {
     vars.insert("c_white", 16777215);
     vars.insert("c_yellow", 65535);
 
-    resources(&mut vars, &content.objects);
-    resources(&mut vars, &content.rooms);
-    resources(&mut vars, &content.scripts);
-    resources(&mut vars, &content.backgrounds);
-    resources(&mut vars, &content.sprites);
-    resources(&mut vars, &content.sounds);
+    define_os_consts(&vars);
+    define_draw_consts(&vars);
+
+    // the GM version the game was saved with, e.g. 800
+    vars.insert("GM_version", content.version().number() as i32);
+
+    define_project_consts(&vars, resources, &content.settings.constants);
+
+    // room order, -1 if there are no rooms like other missing resources
+    let room_order = &content.room_order.items;
+    let room_id = |index: Option<&u32>| index.map_or(ObjectId(-1), |&index| ObjectId::new(index));
+    vars.insert("room_first", room_id(room_order.first()));
+    vars.insert("room_last", room_id(room_order.last()));
+
+    // one past the last index, including deleted resources, for `*_exists()` loops
+    fn count<T>(chunk: &gmk_file::ResourceChunk<T>) -> Value {
+        (chunk.items.len() as i32).into()
+    }
+    vars.insert("sound_count", count(&content.sounds));
+    vars.insert("sprite_count", count(&content.sprites));
+    vars.insert("background_count", count(&content.backgrounds));
+    vars.insert("path_count", count(&content.paths));
+    vars.insert("script_count", count(&content.scripts));
+    vars.insert("font_count", count(&content.fonts));
+    vars.insert("timeline_count", count(&content.timelines));
+    vars.insert("object_count", count(&content.objects));
+    vars.insert("room_count", count(&content.rooms));
+
+    vars
+}
+
+/// Project constants are expressions that can use other constants, including ones defined
+/// after them, so they're evaluated until no more can be resolved.
+fn define_project_consts(
+    vars: &gml::eval::Namespace,
+    resources: &gml::eval::Namespace,
+    constants: &[gmk_file::Constant],
+) {
+    let mut pending = constants
+        .iter()
+        .filter_map(|constant| match gml::parse_expr(&constant.value) {
+            Ok(expr) => Some((constant.name.0.as_str(), expr)),
+            Err(error) => {
+                log::warn!("invalid constant {}: {error}", constant.name.0);
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let global = ConstsGlobal::new(vars, resources);
+    let instance = Rc::<gml::eval::Namespace>::default();
+    loop {
+        let count = pending.len();
+        pending.retain(|(name, expr)| {
+            let mut ctx = Context::new(&global, ObjectId::NOONE, instance.clone());
+            match ctx.eval(expr) {
+                Ok(value) => {
+                    global.vars.insert(*name, value.clone());
+                    vars.insert(*name, value);
+                    false
+                }
+                Err(_) => true,
+            }
+        });
+        if pending.is_empty() || pending.len() == count {
+            break;
+        }
+    }
+    for (name, _) in pending {
+        log::warn!("constant {name} could not be evaluated");
+    }
+}
 
-    return vars;
+/// Evaluates constant expressions, which can only read resources and constants defined
+/// so far.
+struct ConstsGlobal {
+    vars: gml::eval::Namespace,
+    resources: gml::eval::Namespace,
+}
 
-    fn resources<T>(vars: &mut gml::eval::Namespace, chunk: &gmk_file::ResourceChunk<T>) {
-        for (index, name, _) in chunk {
-            let id = ObjectId::new(index);
-            vars.insert(name, id);
+impl ConstsGlobal {
+    /// Globals must be `'static`, so this has copies of the values.
+    fn new(vars: &gml::eval::Namespace, resources: &gml::eval::Namespace) -> Self {
+        Self {
+            vars: vars.clone_values(),
+            resources: resources.clone_values(),
         }
     }
 }
 
+impl gml::eval::Global for ConstsGlobal {
+    fn get(&self, name: &str) -> gml::eval::Result<Option<Value>> {
+        match self.vars.get(name).or_else(|| self.resources.get(name)) {
+            Some(value) => Ok(Some(value)),
+            None => Err(gml::eval::Error::Custom(format!("{name} is not defined"))),
+        }
+    }
+
+    fn set(&self, _name: &str, _value: Value) -> gml::eval::Result {
+        Err(gml::eval::Error::AssignToValue)
+    }
+
+    fn instance_ids(&self, _id: ObjectId) -> Vec<ObjectId> {
+        vec![]
+    }
+
+    fn instance(&self, _id: ObjectId) -> Option<Rc<dyn Object>> {
+        None
+    }
+
+    fn new_instance(&self, _object: Rc<dyn Object>) -> ObjectId {
+        ObjectId::NOONE
+    }
+
+    fn call(
+        &self,
+        _context: &mut Context<'_>,
+        id: &str,
+        _args: Vec<Value>,
+    ) -> gml::eval::Result<Value> {
+        Err(gml::eval::Error::UndefinedFunction(id.to_string()))
+    }
+}
+
+fn define_draw_consts(vars: &gml::eval::Namespace) {
+    // blend modes, see BlendMode::from_gm()
+    vars.insert("bm_normal", 0);
+    vars.insert("bm_add", 1);
+    vars.insert("bm_max", 2);
+    vars.insert("bm_subtract", 3);
+
+    // primitive kinds
+    vars.insert("pr_pointlist", 1);
+    vars.insert("pr_linelist", 2);
+    vars.insert("pr_linestrip", 3);
+    vars.insert("pr_trianglelist", 4);
+    vars.insert("pr_trianglestrip", 5);
+    vars.insert("pr_trianglefan", 6);
+}
+
+/// GM 8 only runs on Windows, but later versions' scripts check `os_type` to pick a branch.
+fn define_os_consts(vars: &gml::eval::Namespace) {
+    vars.insert("os_win32", 0);
+    vars.insert("os_windows", 0);
+    vars.insert("os_macosx", 1);
+    vars.insert("os_type", 0);
+}
+
 fn define_objects(content: &gmk_file::Content) -> HashMap<u32, ObjectAsset> {
     let mut result = HashMap::new();
 
     for (object_index, name, def) in &content.objects {
+        // break parent loops so walking the parents doesn't hang
+        let parent_index = match content
+            .object_parents(object_index)
+            .collect::<Result<Vec<_>, _>>()
+        {
+            Ok(parents) => parents.first().copied(),
+            Err(error) => {
+                log::error!("{name}: {error}, ignoring parent");
+                None
+            }
+        };
         let mut object = ObjectAsset {
             name: name.to_string(),
-            parent_index: def.parent_object_index.try_into().ok(),
+            parent_index,
+            persistent: def.persistent.into(),
+            defaults: RefCell::new(ObjectDefaults::new(def)),
             ..Default::default()
         };
 
         for (event_id, event) in &def.events {
+            let numbered = event.actions.len() > 1;
             object.events.insert(
                 {
                     use gmk_file::{DrawEventId, EventId, StepEventId};
@@ -488,17 +1093,25 @@ fn define_objects(content: &gmk_file::Content) -> HashMap<u32, ObjectAsset> {
                         EventId::KeyRelease(key) => Event::KeyRelease(key_code(*key)),
                         EventId::Keyboard(key) => Event::KeyDown(key_code(*key)),
                         EventId::Collision(object_index) => Event::Collision(*object_index),
+                        EventId::Other(gmk_file::OtherEventId::RoomStart) => Event::RoomStart,
+                        EventId::Other(gmk_file::OtherEventId::NoMoreLives) => Event::NoMoreLives,
+                        EventId::Other(gmk_file::OtherEventId::NoMoreHealth) => Event::NoMoreHealth,
                         _ => unimplemented!("EventId: {event_id:?}"),
                     }
                 },
                 event
                     .actions
                     .iter()
-                    .flat_map(|action| {
+                    .enumerate()
+                    .flat_map(|(action_index, action)| {
                         Some(match action.kind {
                             gmk_file::ActionKind::Code => Action::ScriptInline(
                                 gml::parse(
-                                    &format!("{name}/{event_id:?}"),
+                                    &event_script_name(
+                                        name,
+                                        event_id,
+                                        numbered.then_some(action_index),
+                                    ),
                                     action.argument_values[0].0.as_str(),
                                 )
                                 .unwrap(),
@@ -542,5 +1155,634 @@ fn define_objects(content: &gmk_file::Content) -> HashMap<u32, ObjectAsset> {
         result.insert(object_index, object);
     }
 
+    objects::define_key_events(&mut result);
     result
 }
+
+/// The script name errors in object event code are reported with. Positions are relative
+/// to the action's code, so the action is included when the event has several.
+fn event_script_name(
+    object: &str,
+    event_id: &gmk_file::EventId,
+    action_index: Option<usize>,
+) -> String {
+    match action_index {
+        Some(index) => format!("object {object}, {event_id:?} event, action {}", index + 1),
+        None => format!("object {object}, {event_id:?} event"),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A game made up by a test, to run scripts and events through a real [`Global`].
+    pub(crate) struct TestGame {
+        pub content: gmk_file::Content,
+    }
+
+    impl Default for TestGame {
+        fn default() -> Self {
+            let mut content = gmk_file::Content::default();
+            content.last_instance_id = 100001;
+            Self { content }
+        }
+    }
+
+    impl TestGame {
+        /// Adds an object with events running the code, returning its index.
+        pub fn object(&mut self, name: &str, events: &[(gmk_file::EventId, &str)]) -> u32 {
+            let events = events
+                .iter()
+                .map(|&(id, code)| {
+                    let event = gmk_file::Event {
+                        ver: 400,
+                        actions: vec![code_action(code)],
+                    };
+                    (id, event)
+                })
+                .collect();
+            let object = gmk_file::Object {
+                ver: 430,
+                sprite_index: -1,
+                solid: gmk_file::Bool32::False,
+                visible: gmk_file::Bool32::True,
+                depth: 0,
+                persistent: gmk_file::Bool32::False,
+                parent_object_index: -1,
+                mask_sprite_index: -1,
+                events,
+            };
+            push_item(&mut self.content.objects, name, object)
+        }
+
+        pub fn script(&mut self, name: &str, code: &str) -> u32 {
+            let script = gmk_file::Script {
+                ver: 800,
+                script: gmk_file::String32(code.to_string()),
+            };
+            push_item(&mut self.content.scripts, name, script)
+        }
+
+        /// Adds a sprite without any images, which is enough for collisions, with its
+        /// bounding box covering its `size`.
+        pub fn sprite(&mut self, name: &str, size: (u32, u32)) -> u32 {
+            let sprite = gmk_file::Sprite {
+                ver: 800,
+                size: gmk_file::Pair(size.0, size.1),
+                bbox_left: 0,
+                bbox_right: size.0 as i32,
+                bbox_bottom: size.1 as i32,
+                bbox_top: 0,
+                transparent: gmk_file::Bool32::False,
+                smooth_edges: None,
+                preload_texture: None,
+                bbox: 0,
+                precise_collision: gmk_file::Bool32::False,
+                use_video_memory: None,
+                on_demand: None,
+                origin: gmk_file::Pair(0, 0),
+                subimages: vec![],
+            };
+            push_item(&mut self.content.sprites, name, sprite)
+        }
+
+        /// Adds a room, next in the room order, with instances of the objects at the
+        /// positions, returning its index.
+        pub fn room(&mut self, name: &str, instances: &[(u32, i32, i32)]) -> u32 {
+            let instances = instances
+                .iter()
+                .map(|&(object_index, x, y)| {
+                    let id = self.content.last_instance_id;
+                    self.content.last_instance_id += 1;
+                    gmk_file::RoomInstance {
+                        pos: gmk_file::Pair(x, y),
+                        object_index,
+                        id,
+                        creation_code: default(),
+                        locked: gmk_file::Bool32::False,
+                    }
+                })
+                .collect();
+            let room = gmk_file::Room {
+                size: gmk_file::Pair(640, 480),
+                speed: 30,
+                instances,
+                ..default()
+            };
+            let index = push_item(&mut self.content.rooms, name, room);
+            self.content.room_order.items.push(index);
+            index
+        }
+
+        /// The game, in the first room.
+        pub fn start(self) -> Global {
+            let global = Global::new(self.content, std::env::temp_dir(), default());
+            global.goto_room_order(0);
+            global.cleanup();
+            global
+        }
+    }
+
+    fn push_item<T>(chunk: &mut gmk_file::ResourceChunk<T>, name: &str, data: T) -> u32 {
+        chunk.items.push(Some(gmk_file::ResourceItem {
+            name: gmk_file::String32(name.to_string()),
+            data,
+        }));
+        (chunk.items.len() - 1) as u32
+    }
+
+    /// An "Execute code" action.
+    fn code_action(code: &str) -> gmk_file::Action {
+        gmk_file::Action {
+            ver: 440,
+            library_id: 1,
+            action_id: 603,
+            kind: gmk_file::ActionKind::Code,
+            can_be_relative: gmk_file::Bool32::False,
+            is_a_question: gmk_file::Bool32::False,
+            has_target: gmk_file::Bool32::True,
+            exec: gmk_file::ActionExec::Code,
+            function_name: default(),
+            code: default(),
+            argument_count: 1,
+            argument_kinds: vec![gmk_file::ArgumentKind::String],
+            target_object_index: -1,
+            relative: gmk_file::Bool32::False,
+            argument_values: vec![gmk_file::String32(code.to_string())],
+            not: gmk_file::Bool32::False,
+        }
+    }
+
+    /// Runs `source` as the instance `id`, like its event code would.
+    pub(crate) fn run(global: &Global, id: ObjectId, source: &str) -> gml::eval::Result<Value> {
+        let instance = global.instance(id).expect("missing instance");
+        let script = gml::parse("test", source).unwrap();
+        Context::new(global, id, instance).exec_script(&script, &[])
+    }
+
+    #[test]
+    fn test_os_consts() {
+        let vars = gml::eval::Namespace::default();
+        define_os_consts(&vars);
+        assert_eq!(vars.get("os_type"), vars.get("os_windows"));
+        assert!(vars.get("os_type").is_some());
+    }
+
+    #[test]
+    fn test_draw_consts() {
+        use macroquad::miniquad::{BlendFactor, BlendState, BlendValue, Equation};
+
+        let vars = gml::eval::Namespace::default();
+        define_draw_consts(&vars);
+        let mode = |name: &str| BlendMode::from_gm(vars.get(name).unwrap().to_int());
+        assert_eq!(mode("bm_normal"), Some(BlendMode::Normal));
+        assert_eq!(mode("bm_add"), Some(BlendMode::Add));
+        assert_eq!(mode("bm_max"), Some(BlendMode::Max));
+        assert_eq!(mode("bm_subtract"), Some(BlendMode::Subtract));
+        assert_eq!(BlendMode::from_gm(4), None);
+        assert_eq!(vars.get("pr_trianglefan"), Some(Value::Int(6)));
+
+        assert_eq!(BlendMode::Normal.blend_state(), None);
+        assert_eq!(
+            BlendMode::Add.blend_state(),
+            Some(BlendState::new(
+                Equation::Add,
+                BlendFactor::Value(BlendValue::SourceAlpha),
+                BlendFactor::One
+            ))
+        );
+    }
+
+    #[test]
+    fn test_project_consts() {
+        let constant = |name: &str, value: &str| gmk_file::Constant {
+            name: gmk_file::String32(name.to_string()),
+            value: gmk_file::String32(value.to_string()),
+        };
+        let vars = gml::eval::Namespace::default();
+        let resources = gml::eval::Namespace::default();
+        resources.insert("spr_player", ObjectId::new(3));
+        define_project_consts(
+            &vars,
+            &resources,
+            &[
+                constant("PLAYER_SPRITE", "spr_player"),
+                constant("MAX_HP", "100"),
+                // uses a constant defined after it
+                constant("HALF_MAX_AMMO", "MAX_AMMO / 2"),
+                constant("MAX_AMMO", "MAX_HP * 3"),
+                constant("BROKEN", "MISSING + 1"),
+            ],
+        );
+        assert_eq!(vars.get("HALF_MAX_AMMO"), Some(Value::Int(150)));
+        assert_eq!(vars.get("BROKEN"), None);
+        assert_eq!(vars.get("PLAYER_SPRITE"), resources.get("spr_player"));
+        assert_eq!(vars.get("spr_player"), None);
+
+        let script = gml::parse("test", "return MAX_HP - 1;").unwrap();
+        let global = ConstsGlobal::new(&vars, &resources);
+        let instance = Rc::<gml::eval::Namespace>::default();
+        let mut ctx = Context::new(&global, ObjectId::NOONE, instance);
+        assert_eq!(ctx.exec_script(&script, &[]).unwrap(), Value::Int(99));
+    }
+
+    #[test]
+    fn test_event_script_name() {
+        let event_id = gmk_file::EventId::Step(gmk_file::StepEventId::Normal);
+        let name = event_script_name("obj_player", &event_id, None);
+        let script = gml::parse(&name, "x = 1;\nundefined_fn();").unwrap();
+
+        let vars = gml::eval::Namespace::default();
+        let resources = gml::eval::Namespace::default();
+        let global = ConstsGlobal::new(&vars, &resources);
+        let instance = Rc::<gml::eval::Namespace>::default();
+        let mut ctx = Context::new(&global, ObjectId::NOONE, instance);
+        let message = ctx.exec_script(&script, &[]).unwrap_err().to_string();
+        assert!(
+            message.contains("in object obj_player, Step(Normal) event"),
+            "{message}"
+        );
+        assert!(message.contains("at 2:"), "{message}");
+
+        assert_eq!(
+            event_script_name("obj_player", &event_id, Some(1)),
+            "object obj_player, Step(Normal) event, action 2"
+        );
+    }
+
+    #[test]
+    fn test_shadow_resource_name() {
+        let chunk = gmk_file::ResourceChunk {
+            ver: 0,
+            items: vec![
+                None,
+                Some(gmk_file::ResourceItem {
+                    name: gmk_file::String32("spr_player".to_string()),
+                    data: (),
+                }),
+            ],
+        };
+        let resources = gml::eval::Namespace::default();
+        insert_resources(&resources, &chunk);
+        let vars = gml::eval::Namespace::default();
+        let global = ConstsGlobal::new(&vars, &resources);
+
+        let script = gml::parse("test", "return spr_player;").unwrap();
+        let shadow = gml::parse(
+            "test",
+            "var spr_player; spr_player = 5; return spr_player * 2;",
+        )
+        .unwrap();
+        let instance = Rc::<gml::eval::Namespace>::default();
+        let mut ctx = Context::new(&global, ObjectId::NOONE, instance);
+        assert_eq!(ctx.exec_script(&shadow, &[]).unwrap(), Value::Int(10));
+        // still the resource outside the script
+        assert_eq!(
+            ctx.exec_script(&script, &[]).unwrap(),
+            ObjectId::new(1).into()
+        );
+    }
+
+    #[test]
+    fn test_path_resource_name() {
+        let chunk = |names: &[&str]| gmk_file::ResourceChunk {
+            ver: 0,
+            items: names
+                .iter()
+                .map(|name| {
+                    Some(gmk_file::ResourceItem {
+                        name: gmk_file::String32(name.to_string()),
+                        data: (),
+                    })
+                })
+                .collect(),
+        };
+        let resources = gml::eval::Namespace::default();
+        // sprites, then paths
+        insert_resources(&resources, &chunk(&["spr_player", "spr_enemy"]));
+        insert_resources(&resources, &chunk(&["path_patrol", "spr_player"]));
+        assert_eq!(resources.get("path_patrol"), Some(ObjectId::new(0).into()));
+        assert_eq!(resources.get("spr_player"), Some(ObjectId::new(0).into()));
+        assert_eq!(resources.get("spr_enemy"), Some(ObjectId::new(1).into()));
+    }
+
+    #[test]
+    fn test_place_free() {
+        use crate::state::instance::tests::test_instance;
+
+        let player = Rc::new(test_instance());
+        let instances = [(100002, true), (100003, false)].map(|(id, solid)| {
+            let instance = test_instance();
+            instance.state.borrow_mut().solid = solid;
+            Rc::new(Instance {
+                id: ObjectId(id),
+                ..instance
+            })
+        });
+        let mut instances = instances.to_vec();
+        // the instance itself is never in the way
+        instances.push(player.clone());
+        let bounds = HashMap::from([
+            (ObjectId(100001), Rect::new(0.0, 0.0, 16.0, 16.0)),
+            (ObjectId(100002), Rect::new(32.0, 0.0, 16.0, 16.0)),
+            (ObjectId(100003), Rect::new(0.0, 32.0, 16.0, 16.0)),
+        ]);
+        let overlaps = |x, y, solid_only| {
+            let rect = Rect::new(x, y, 16.0, 16.0);
+            overlaps_any(&player, rect, &instances, solid_only, |other| {
+                bounds.get(&other.id).copied()
+            })
+        };
+        let place_free = |x, y| !overlaps(x, y, true);
+        let place_empty = |x, y| !overlaps(x, y, false);
+
+        assert!(!place_free(24.0, 0.0));
+        assert!(place_free(8.0, 0.0));
+        assert!(place_free(0.0, 0.0));
+        // only solid instances are in the way
+        assert!(place_free(0.0, 24.0));
+        assert!(!place_empty(0.0, 24.0));
+        assert!(!place_empty(24.0, 0.0));
+        assert!(place_empty(0.0, 0.0));
+        assert!(place_empty(100.0, 100.0));
+    }
+
+    #[test]
+    fn test_sandbox_path() {
+        let root = Path::new("game");
+        assert_eq!(
+            sandbox_path(root, "saves\\save1.dat"),
+            Some(root.join("saves").join("save1.dat"))
+        );
+        assert_eq!(
+            sandbox_path(root, "./config.ini"),
+            Some(root.join("config.ini"))
+        );
+        assert_eq!(sandbox_path(root, "../secret.txt"), None);
+        assert_eq!(sandbox_path(root, "saves/../../secret.txt"), None);
+        assert_eq!(sandbox_path(root, "/etc/passwd"), None);
+    }
+
+    #[test]
+    fn test_draw_state() {
+        use macroquad::color::{BLUE, GREEN, RED};
+
+        let mut state = GlobalState {
+            color: RED,
+            ..default()
+        };
+        state.fonts.set(2);
+
+        state.push_draw_state();
+        state.color = BLUE;
+        state.fonts.set(5);
+        state.push_draw_state();
+        state.color = GREEN;
+
+        assert!(state.pop_draw_state());
+        assert_eq!(state.color, BLUE);
+        assert_eq!(state.fonts.index(), 5);
+        assert!(state.pop_draw_state());
+        assert_eq!(state.color, RED);
+        assert_eq!(state.fonts.index(), 2);
+        assert!(!state.pop_draw_state());
+        assert_eq!(state.color, RED);
+    }
+
+    #[test]
+    fn test_game_stats() {
+        let mut stats = GameStats::default();
+        assert_eq!(stats.get("health"), Value::Int(100));
+
+        assert_eq!(stats.set("score", 0.into()), None);
+        assert_eq!(stats.set("lives", 2.into()), None);
+        assert_eq!(stats.set("lives", 1.into()), None);
+        let event = stats.set("lives", 0.into());
+        assert_eq!(event, Some(Event::NoMoreLives));
+        assert_eq!(event.unwrap().gm_id(), (7, 6));
+        assert_eq!(stats.get("lives"), Value::Int(0));
+        // only when they run out, not while they stay out
+        assert_eq!(stats.set("lives", (-1).into()), None);
+
+        assert_eq!(stats.set("health", 0.5.into()), None);
+        assert_eq!(stats.set("health", (-3).into()), Some(Event::NoMoreHealth));
+    }
+
+    #[test]
+    fn test_builtin_vars() {
+        use gmk_file::{EventId, OtherEventId};
+
+        let mut game = TestGame::default();
+        let object_index = game.object(
+            "obj_control",
+            &[(EventId::Other(OtherEventId::NoMoreLives), "game_over = 1;")],
+        );
+        let first = game.room("rm_first", &[(object_index, 0, 0)]);
+        let second = game.room("rm_second", &[]);
+        let global = game.start();
+        let id = ObjectId(100001);
+
+        // unprefixed, like most game code, not instance variables
+        run(&global, id, "lives = 2; lives -= 1; score += 10;").unwrap();
+        assert_eq!(global.state.borrow().stats.get("score"), Value::Int(10));
+        run(&global, id, "lives -= 1;").unwrap();
+        global.cleanup();
+        let source = "return game_over * 100 + lives;";
+        assert_eq!(run(&global, id, source).unwrap(), Value::Int(100));
+        assert_eq!(global.instance(id).unwrap().member("lives").unwrap(), None);
+
+        assert_eq!(
+            run(&global, id, "return room;").unwrap(),
+            Value::Int(first as i32)
+        );
+        assert!(run(&global, id, "room = 5;").is_err());
+        run(&global, id, "room = rm_second;").unwrap();
+        global.cleanup();
+        assert_eq!(global.room.borrow().index, second);
+    }
+
+    #[test]
+    fn test_dialogs() {
+        let mut game = TestGame::default();
+        let object_index = game.object("obj_control", &[]);
+        game.room("rm_test", &[(object_index, 0, 0)]);
+        let mut global = game.start();
+        global.dialogs = Box::new(ScriptedDialogs::new([DialogAnswer::Question(true)]));
+        let id = ObjectId(100001);
+
+        let source = r#"return show_question("Continue?");"#;
+        assert_eq!(run(&global, id, source).unwrap(), Value::Bool(true));
+        // out of answers
+        assert_eq!(run(&global, id, source).unwrap(), Value::Bool(false));
+    }
+
+    #[test]
+    fn test_assign_resource_name() {
+        let mut game = TestGame::default();
+        let object_index = game.object("obj_player", &[]);
+        game.room("rm_test", &[(object_index, 0, 0)]);
+        let global = game.start();
+        let id = ObjectId(100001);
+
+        assert_eq!(
+            run(&global, id, "return obj_player;").unwrap(),
+            ObjectId::new(object_index).into()
+        );
+        // a global variable is found before the resource with the same name
+        let source = "global.obj_player = 7; return obj_player * 10 + global.obj_player;";
+        assert_eq!(run(&global, id, source).unwrap(), Value::Int(77));
+        // but constants still can't be assigned
+        assert!(run(&global, id, "global.c_red = 7;").is_err());
+    }
+
+    #[test]
+    fn test_resource_counts() {
+        let mut game = TestGame::default();
+        game.object("obj_deleted", &[]);
+        let object_index = game.object("obj_control", &[]);
+        game.content.objects.items[0] = None;
+        game.room("rm_test", &[(object_index, 0, 0)]);
+        let global = game.start();
+
+        let source = "
+            var count; count = 0;
+            for (i = 0; i < object_count; i += 1) count += object_exists(i);
+            return object_count * 10 + count;";
+        assert_eq!(
+            run(&global, ObjectId(100001), source).unwrap(),
+            Value::Int(21)
+        );
+        let source = "return room_count * 10 + sprite_count;";
+        assert_eq!(
+            run(&global, ObjectId(100001), source).unwrap(),
+            Value::Int(10)
+        );
+    }
+
+    #[test]
+    fn test_persistent_room_change() {
+        let mut game = TestGame::default();
+        let player = game.object(
+            "obj_player",
+            &[
+                (gmk_file::EventId::Create, "hp = 3; rang = 0;"),
+                (gmk_file::EventId::Alarm(0), "rang = 1;"),
+            ],
+        );
+        let def = game.content.objects.items[player as usize]
+            .as_mut()
+            .unwrap();
+        def.data.persistent = gmk_file::Bool32::True;
+        let first = game.room("rm_first", &[(player, 16, 16)]);
+        let second = game.room("rm_second", &[]);
+        let global = game.start();
+        let goto = |index| {
+            global.goto_room(index);
+            global.cleanup();
+        };
+        let id = ObjectId(100001);
+        run(&global, id, "hp -= 1;").unwrap();
+
+        // carried over along with its alarms
+        goto(second);
+        run(&global, id, "alarm[0] = 1;").unwrap();
+        let instance = global.room.borrow().object_instances.borrow().values[&100001].clone();
+        instance.step(&global);
+        assert_eq!(run(&global, id, "return rang;").unwrap(), Value::Int(1));
+
+        // and not placed again, or created again, when going back
+        goto(first);
+        let room = global.room.borrow();
+        assert_eq!(room.instances().len(), 1);
+        let object_instances = global.object_types[&player].object.instances.borrow();
+        assert_eq!(object_instances.len(), 1);
+        assert!(Rc::ptr_eq(&object_instances[&id], &room.instances()[0]));
+        drop((room, object_instances));
+        assert_eq!(run(&global, id, "return hp;").unwrap(), Value::Int(2));
+    }
+
+    #[test]
+    fn test_persistent_at_runtime() {
+        let mut game = TestGame::default();
+        let object_index = game.object("obj_crate", &[]);
+        game.room("rm_first", &[(object_index, 0, 0), (object_index, 32, 0)]);
+        let second = game.room("rm_second", &[]);
+        let global = game.start();
+        run(&global, ObjectId(100002), "persistent = 1; hp = 5;").unwrap();
+
+        global.goto_room(second);
+        global.cleanup();
+        let ids = global
+            .room
+            .borrow()
+            .instances()
+            .iter()
+            .map(|instance| instance.id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, [ObjectId(100002)]);
+        assert_eq!(global.instance_number(object_index), 1);
+        let source = "return hp * 100 + x;";
+        assert_eq!(
+            run(&global, ObjectId(100002), source).unwrap(),
+            Value::Float(532.0)
+        );
+    }
+
+    /// Runs a tiny game for a few steps and compares the state with the recording in
+    /// `golden/tiny_game.json`, like `--record-state` does for a real game. Set
+    /// `UPDATE_GOLDEN=1` to write it again after a change that's meant to affect it.
+    #[test]
+    fn test_golden_state() {
+        let mut game = TestGame::default();
+        let spark = game.object(
+            "obj_spark",
+            &[
+                (gmk_file::EventId::Create, "life = 2;"),
+                (
+                    gmk_file::EventId::Step(gmk_file::StepEventId::Normal),
+                    "life -= 1; if (life <= 0) instance_destroy();",
+                ),
+            ],
+        );
+        let counter = game.object(
+            "obj_counter",
+            &[
+                (
+                    gmk_file::EventId::Create,
+                    "count = 0; global.total = 0; alarm[0] = 3;",
+                ),
+                (
+                    gmk_file::EventId::Step(gmk_file::StepEventId::Normal),
+                    "count += 1; global.total += count; x += 2;",
+                ),
+                (
+                    gmk_file::EventId::Alarm(0),
+                    "instance_create(x, y + 8, obj_spark); alarm[0] = 3;",
+                ),
+            ],
+        );
+        game.room("rm_test", &[(counter, 16, 16), (spark, 64, 64)]);
+        let mut global = game.start();
+        global.options.fixed_step = true;
+        // the temp dir differs between machines
+        global.data_root = "game".into();
+        for _ in 0..10 {
+            global.step();
+            global.cleanup();
+        }
+
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("src/state/golden/tiny_game.json");
+        let json = global.state_json();
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::write(&path, &json).unwrap();
+        }
+        let golden = std::fs::read_to_string(&path).unwrap();
+        assert!(
+            json == golden,
+            "state differs from {}:\n{json}",
+            path.display()
+        );
+    }
+}
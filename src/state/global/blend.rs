@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+
+use macroquad::miniquad::{BlendFactor, BlendState, BlendValue, Equation};
+use macroquad::prelude::*;
+
+/// GM `bm_*` blend modes.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum BlendMode {
+    Normal,
+    Add,
+    Max,
+    Subtract,
+}
+
+impl BlendMode {
+    pub fn from_gm(value: i32) -> Option<Self> {
+        match value {
+            0 => Some(Self::Normal),
+            1 => Some(Self::Add),
+            2 => Some(Self::Max),
+            3 => Some(Self::Subtract),
+            _ => None,
+        }
+    }
+
+    /// The color blending GM uses for this mode, or `None` for macroquad's default alpha
+    /// blending, which is the same as `bm_normal`.
+    pub fn blend_state(self) -> Option<BlendState> {
+        let src_alpha = BlendFactor::Value(BlendValue::SourceAlpha);
+        let inv_src_color = BlendFactor::OneMinusValue(BlendValue::SourceColor);
+        match self {
+            Self::Normal => None,
+            Self::Add => Some(BlendState::new(Equation::Add, src_alpha, BlendFactor::One)),
+            Self::Max => Some(BlendState::new(Equation::Add, src_alpha, inv_src_color)),
+            Self::Subtract => Some(BlendState::new(
+                Equation::Add,
+                BlendFactor::Zero,
+                inv_src_color,
+            )),
+        }
+    }
+}
+
+/// Materials for the blend modes other than normal, created on first use.
+#[derive(Default, Debug)]
+pub struct BlendMaterials {
+    items: HashMap<BlendMode, Material>,
+}
+
+impl BlendMaterials {
+    /// Draws with `mode` until it's set again.
+    pub fn set(&mut self, mode: BlendMode) {
+        let Some(color_blend) = mode.blend_state() else {
+            gl_use_default_material();
+            return;
+        };
+        let material = *self.items.entry(mode).or_insert_with(|| {
+            let params = MaterialParams {
+                pipeline_params: PipelineParams {
+                    color_blend: Some(color_blend),
+                    ..Default::default()
+                },
+                ..Default::default()
+            };
+            load_material(VERTEX, FRAGMENT, params).expect("invalid blend shader")
+        });
+        gl_use_material(material);
+    }
+}
+
+// the same as macroquad's default material
+const VERTEX: &str = r#"#version 100
+attribute vec3 position;
+attribute vec2 texcoord;
+attribute vec4 color0;
+
+varying lowp vec2 uv;
+varying lowp vec4 color;
+
+uniform mat4 Model;
+uniform mat4 Projection;
+
+void main() {
+    gl_Position = Projection * Model * vec4(position, 1);
+    color = color0 / 255.0;
+    uv = texcoord;
+}"#;
+
+const FRAGMENT: &str = r#"#version 100
+varying lowp vec4 color;
+varying lowp vec2 uv;
+
+uniform sampler2D Texture;
+
+void main() {
+    gl_FragColor = color * texture2D(Texture, uv);
+}"#;
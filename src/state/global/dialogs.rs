@@ -0,0 +1,185 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+
+use macroquad::prelude::*;
+
+/// The modal dialogs scripts can show, e.g. `show_question()`.
+pub trait Dialogs {
+    /// Returns once the message is dismissed.
+    fn show_message(&self, text: &str);
+    fn show_question(&self, text: &str) -> bool;
+    fn get_integer(&self, text: &str, default: f64) -> f64;
+    fn get_string(&self, text: &str, default: &str) -> String;
+
+    /// The dialogs shown since the last call, to show over the game until each is
+    /// dismissed, see [`OverlayDialogs`].
+    fn take_pending(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+/// Scripts run inside a frame, and macroquad can only wait for input between frames, so these
+/// are shown from the next frame, with the game paused until each is dismissed. Scripts can't
+/// wait for an answer that late, so questions get the default answer, which the dialog shows.
+#[derive(Default, Debug)]
+pub struct OverlayDialogs {
+    pending: RefCell<Vec<String>>,
+}
+
+impl OverlayDialogs {
+    fn show(&self, text: String) {
+        log::info!("{}", text.replace('\n', " "));
+        self.pending.borrow_mut().push(text);
+    }
+}
+
+impl Dialogs for OverlayDialogs {
+    fn show_message(&self, text: &str) {
+        self.show(text.to_string());
+    }
+
+    fn show_question(&self, text: &str) -> bool {
+        self.show(format!("{text}\n\n(answered no)"));
+        false
+    }
+
+    fn get_integer(&self, text: &str, default: f64) -> f64 {
+        self.show(format!("{text}\n\n(answered {default})"));
+        default
+    }
+
+    fn get_string(&self, text: &str, default: &str) -> String {
+        self.show(format!("{text}\n\n(answered {default:?})"));
+        default.to_string()
+    }
+
+    fn take_pending(&self) -> Vec<String> {
+        self.pending.take()
+    }
+}
+
+/// Draws a dialog from [`Dialogs::take_pending()`] in the middle of the window. Like GM, `#`
+/// starts a new line.
+pub fn draw_dialog(text: &str) {
+    const FONT_SIZE: f32 = 20.0;
+    const PADDING: f32 = 16.0;
+    let lines = text
+        .split(['\n', '#'])
+        .chain(["", "Press Enter to continue"])
+        .collect::<Vec<_>>();
+    let width = lines
+        .iter()
+        .map(|line| measure_text(line, None, FONT_SIZE as u16, 1.0).width)
+        .fold(0.0, f32::max);
+    let size = vec2(width, lines.len() as f32 * FONT_SIZE) + PADDING * 2.0;
+    let pos = ((vec2(screen_width(), screen_height()) - size) / 2.0).round();
+    draw_rectangle(pos.x, pos.y, size.x, size.y, Color::new(0.0, 0.0, 0.0, 0.8));
+    draw_rectangle_lines(pos.x, pos.y, size.x, size.y, 2.0, WHITE);
+    for (index, line) in lines.iter().enumerate() {
+        let y = pos.y + PADDING + (index + 1) as f32 * FONT_SIZE - 4.0;
+        draw_text(line, pos.x + PADDING, y, FONT_SIZE, WHITE);
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DialogAnswer {
+    Question(bool),
+    Integer(f64),
+    String(String),
+}
+
+/// For running headless: answers dialogs from a queue, and records what was shown. Dialogs
+/// without a matching answer next in the queue get the default answer.
+#[derive(Default, Debug)]
+pub struct ScriptedDialogs {
+    pub answers: RefCell<VecDeque<DialogAnswer>>,
+    pub shown: RefCell<Vec<String>>,
+}
+
+impl ScriptedDialogs {
+    pub fn new(answers: impl IntoIterator<Item = DialogAnswer>) -> Self {
+        Self {
+            answers: RefCell::new(answers.into_iter().collect()),
+            shown: Default::default(),
+        }
+    }
+
+    fn answer<T>(&self, text: &str, f: impl FnOnce(&DialogAnswer) -> Option<T>) -> Option<T> {
+        self.shown.borrow_mut().push(text.to_string());
+        let mut answers = self.answers.borrow_mut();
+        let answer = f(answers.front()?)?;
+        answers.pop_front();
+        Some(answer)
+    }
+}
+
+impl Dialogs for ScriptedDialogs {
+    fn show_message(&self, text: &str) {
+        self.shown.borrow_mut().push(text.to_string());
+    }
+
+    fn show_question(&self, text: &str) -> bool {
+        self.answer(text, |answer| match answer {
+            DialogAnswer::Question(value) => Some(*value),
+            _ => None,
+        })
+        .unwrap_or(false)
+    }
+
+    fn get_integer(&self, text: &str, default: f64) -> f64 {
+        self.answer(text, |answer| match answer {
+            DialogAnswer::Integer(value) => Some(*value),
+            _ => None,
+        })
+        .unwrap_or(default)
+    }
+
+    fn get_string(&self, text: &str, default: &str) -> String {
+        self.answer(text, |answer| match answer {
+            DialogAnswer::String(value) => Some(value.clone()),
+            _ => None,
+        })
+        .unwrap_or_else(|| default.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scripted_dialogs() {
+        let dialogs = ScriptedDialogs::new([
+            DialogAnswer::Question(true),
+            DialogAnswer::String("Iji".to_string()),
+        ]);
+        dialogs.show_message("Welcome");
+        assert!(dialogs.show_question("Continue?"));
+        // the next answer is a string, so this gets the default
+        assert_eq!(dialogs.get_integer("Level?", 1.0), 1.0);
+        assert_eq!(dialogs.get_string("Name?", ""), "Iji");
+        // out of answers
+        assert!(!dialogs.show_question("Quit?"));
+        assert_eq!(
+            *dialogs.shown.borrow(),
+            ["Welcome", "Continue?", "Level?", "Name?", "Quit?"]
+        );
+    }
+
+    #[test]
+    fn test_overlay_dialogs() {
+        let dialogs = OverlayDialogs::default();
+        dialogs.show_message("Welcome");
+        assert!(!dialogs.show_question("Continue?"));
+        assert_eq!(dialogs.get_integer("Level?", 1.0), 1.0);
+        assert_eq!(
+            dialogs.take_pending(),
+            [
+                "Welcome",
+                "Continue?\n\n(answered no)",
+                "Level?\n\n(answered 1)"
+            ]
+        );
+        assert!(dialogs.take_pending().is_empty());
+    }
+}
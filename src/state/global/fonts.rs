@@ -1,34 +1,193 @@
 use glam::IVec2;
-use macroquad::color::WHITE;
-use macroquad::prelude::draw_texture;
+use macroquad::color::{Color, WHITE};
+use macroquad::prelude::{draw_text, measure_text};
 use std::collections::HashMap;
 
 use super::Global;
 use crate::assets::{AssetId, SpriteAsset};
 
+/// Resource fonts use their resource index, fonts added by scripts are numbered after them.
 #[derive(Default, Debug)]
 pub struct FontMap {
     last_index: i32,
-    items: HashMap<i32, FontAsset>,
+    items: HashMap<i32, Font>,
     index: i32,
 }
 
 impl FontMap {
+    pub fn new(chunk: &gmk_file::ResourceChunk<gmk_file::Font>) -> Self {
+        let items = chunk
+            .iter()
+            .map(|(index, _, def)| (index as i32, Font::Resource(ResourceFontAsset::new(def))))
+            .collect();
+        Self {
+            last_index: chunk.items.len() as i32 - 1,
+            items,
+            index: -1,
+        }
+    }
+
     pub fn add(&mut self, item: FontAsset) -> i32 {
         self.last_index += 1;
-        self.items.insert(self.last_index, item);
+        self.items.insert(self.last_index, Font::Sprite(item));
         self.last_index
     }
 
-    pub fn get(&self) -> Option<&FontAsset> {
+    pub fn get(&self) -> Option<&Font> {
         self.items.get(&self.index)
     }
 
+    /// The font index set by `draw_set_font()`, -1 if none.
+    pub fn index(&self) -> i32 {
+        self.index
+    }
+
     pub fn set(&mut self, index: i32) {
         self.index = index;
     }
 }
 
+#[derive(Debug)]
+pub enum Font {
+    /// From `font_add_sprite()`.
+    Sprite(FontAsset),
+    /// A font resource.
+    Resource(ResourceFontAsset),
+}
+
+impl Font {
+    /// Draws with `gradient`, or the draw color if it's `None`.
+    pub fn draw_text(
+        &self,
+        global: &Global,
+        pos: IVec2,
+        string: &str,
+        sep: i32,
+        w: i32,
+        gradient: Option<TextGradient>,
+    ) {
+        match self {
+            Self::Sprite(font) => font.draw_text(global, pos, string, sep, w, gradient),
+            Self::Resource(font) => font.draw_text(global, pos, string, sep, w, gradient),
+        }
+    }
+}
+
+/// The colors from `draw_text_ext_color()`. GM blends the four corner colors across the
+/// text, but text is drawn a line at a time here, so this blends from the top line to the
+/// bottom line, averaging the left and right colors.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TextGradient {
+    pub top: Color,
+    pub bottom: Color,
+}
+
+impl TextGradient {
+    /// `colors` are top-left, top-right, bottom-right then bottom-left, like GM.
+    pub fn from_corners(colors: [Color; 4]) -> Self {
+        Self {
+            top: lerp_color(colors[0], colors[1], 0.5),
+            bottom: lerp_color(colors[3], colors[2], 0.5),
+        }
+    }
+
+    /// The color of line `index` of `count`.
+    pub fn line(&self, index: usize, count: usize) -> Color {
+        if count < 2 {
+            return self.top;
+        }
+        lerp_color(self.top, self.bottom, index as f32 / (count - 1) as f32)
+    }
+}
+
+fn lerp_color(a: Color, b: Color, t: f32) -> Color {
+    let lerp = |a: f32, b: f32| a + (b - a) * t;
+    Color::new(
+        lerp(a.r, b.r),
+        lerp(a.g, b.g),
+        lerp(a.b, b.b),
+        lerp(a.a, b.a),
+    )
+}
+
+/// Game files only have the font name and size, not the glyphs GM renders from the system
+/// font when the game starts, so these draw with the built-in font at the same size.
+#[derive(Debug)]
+pub struct ResourceFontAsset {
+    /// In pixels, GM sizes are in points.
+    pub font_size: u16,
+    pub first: u32,
+    pub last: u32,
+}
+
+impl ResourceFontAsset {
+    pub fn new(def: &gmk_file::Font) -> Self {
+        Self {
+            font_size: (def.size * 4 / 3).try_into().unwrap_or(u16::MAX),
+            first: def.character_range_begin,
+            last: def.character_range_end,
+        }
+    }
+
+    pub fn text_width(&self, string: &str) -> f32 {
+        measure_text(string, None, self.font_size, 1.0).width
+    }
+
+    /// Splits on `#` like GM, and between words if a line is wider than `w` when it's positive.
+    fn lines<'a>(&self, string: &'a str, w: i32) -> Vec<&'a str> {
+        let mut lines = vec![];
+        for line in string.split('#') {
+            let mut start = 0;
+            let mut end = 0;
+            for (index, _) in line.match_indices(' ').chain([(line.len(), "")]) {
+                if w > 0 && end > start && self.text_width(&line[start..index]) > w as f32 {
+                    lines.push(&line[start..end]);
+                    start = end + 1;
+                }
+                end = index;
+            }
+            lines.push(&line[start..]);
+        }
+        lines
+    }
+
+    pub fn draw_text(
+        &self,
+        global: &Global,
+        pos: IVec2,
+        string: &str,
+        sep: i32,
+        w: i32,
+        gradient: Option<TextGradient>,
+    ) {
+        let color = global.state.borrow().color;
+        let string = string
+            .chars()
+            .filter(|&c| c == '#' || (self.first..=self.last).contains(&u32::from(c)))
+            .collect::<String>();
+        let sep = if sep < 0 {
+            i32::from(self.font_size)
+        } else {
+            sep
+        };
+
+        let mut y = pos.y as f32;
+        let lines = self.lines(&string, w);
+        for (index, line) in lines.iter().enumerate() {
+            let color = gradient.map_or(color, |gradient| gradient.line(index, lines.len()));
+            // macroquad draws from the baseline, GM from the top
+            draw_text(
+                line,
+                pos.x as f32,
+                y + f32::from(self.font_size),
+                f32::from(self.font_size),
+                color,
+            );
+            y += sep as f32;
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct FontAsset {
     sprite: AssetId<SpriteAsset>,
@@ -40,11 +199,23 @@ impl FontAsset {
         Self { sprite, first }
     }
 
-    pub fn draw_text(&self, global: &Global, pos: IVec2, string: &str, sep: i32, w: i32) {
+    pub fn draw_text(
+        &self,
+        global: &Global,
+        pos: IVec2,
+        string: &str,
+        sep: i32,
+        w: i32,
+        gradient: Option<TextGradient>,
+    ) {
         let assets = global.assets.borrow();
         let sprite = assets.sprites.get(self.sprite);
 
-        let wrap_chars = w as usize / sprite.size.x as usize;
+        // GM uses -1 for no wrapping
+        let wrap_chars = match u32::try_from(w) {
+            Ok(w) if w > 0 => (w / sprite.size.x.max(1)).max(1) as usize,
+            _ => 0,
+        };
 
         let chars = string
             .chars()
@@ -57,32 +228,24 @@ impl FontAsset {
             })
             .collect::<Vec<_>>();
 
-        let mut lines = vec![];
-        let mut line_index = 0;
-        loop {
-            line_index += wrap_chars;
-            if line_index >= chars.len() {
-                lines.push(chars.len());
-                break;
-            }
-            // is_space
-            while !chars[line_index - 1].0 {
-                line_index -= 1;
-            }
-            lines.push(line_index);
-        }
+        let spaces = chars
+            .iter()
+            .map(|&(is_space, _)| is_space)
+            .collect::<Vec<_>>();
+        let lines = wrap_lines(&spaces, wrap_chars);
 
         let mut y = pos.y;
         let mut start_index = 0;
-        for end_index in lines {
+        for (line, &end_index) in lines.iter().enumerate() {
             let mut x = pos.x;
+            let color = gradient.map_or(WHITE, |gradient| gradient.line(line, lines.len()));
 
             for (_, index) in &chars[start_index..end_index] {
-                let Some(texture) = sprite.textures.get(*index) else {
+                let Some(frame) = sprite.frames.get(*index) else {
                     continue;
                 };
 
-                draw_texture(*texture, x as f32, y as f32, WHITE);
+                frame.draw(x as f32, y as f32, color, Default::default());
                 x += sprite.size.x as i32;
             }
 
@@ -92,3 +255,90 @@ impl FontAsset {
         }
     }
 }
+
+/// Where each line ends in text where `spaces` is whether each char is a space. Lines end
+/// after the last space within `wrap_chars`, or mid-word if there isn't one. 0 doesn't wrap.
+fn wrap_lines(spaces: &[bool], wrap_chars: usize) -> Vec<usize> {
+    let mut lines = vec![];
+    let mut start = 0;
+    loop {
+        if wrap_chars == 0 || spaces.len() - start <= wrap_chars {
+            lines.push(spaces.len());
+            return lines;
+        }
+        let limit = start + wrap_chars;
+        let end = (start + 1..=limit)
+            .rev()
+            .find(|&end| spaces[end - 1])
+            .unwrap_or(limit);
+        lines.push(end);
+        start = end;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resource_font() {
+        let chunk = gmk_file::ResourceChunk {
+            ver: 0,
+            items: vec![Some(gmk_file::ResourceItem {
+                name: gmk_file::String32("fnt_main".to_string()),
+                data: gmk_file::Font {
+                    ver: 0,
+                    font_name: gmk_file::String32("Arial".to_string()),
+                    size: 12,
+                    bold: gmk_file::Bool32::False,
+                    italic: gmk_file::Bool32::False,
+                    character_range_begin: 32,
+                    character_range_end: 127,
+                },
+            })],
+        };
+        let mut fonts = FontMap::new(&chunk);
+        assert!(fonts.get().is_none());
+
+        fonts.set(0);
+        let Some(Font::Resource(font)) = fonts.get() else {
+            panic!("expected resource font");
+        };
+        // 12pt
+        assert_eq!(font.font_size, 16);
+        assert_eq!((font.first, font.last), (32, 127));
+
+        // sprite fonts are numbered after resource fonts
+        assert_eq!(fonts.add(FontAsset::new(AssetId::new(0), 32)), 1);
+        fonts.set(1);
+        assert!(matches!(fonts.get(), Some(Font::Sprite(_))));
+    }
+
+    #[test]
+    fn test_wrap_lines() {
+        let spaces = |text: &str| text.chars().map(|c| c == ' ').collect::<Vec<_>>();
+
+        // the space stays at the end of the line
+        assert_eq!(wrap_lines(&spaces("ab cd ef"), 6), [6, 8]);
+        assert_eq!(wrap_lines(&spaces("ab cd ef"), 5), [3, 8]);
+        assert_eq!(wrap_lines(&spaces("ab cd ef"), 0), [8]);
+        assert_eq!(wrap_lines(&spaces("ab cd"), 5), [5]);
+        assert_eq!(wrap_lines(&spaces(""), 3), [0]);
+
+        // words longer than a line are broken
+        assert_eq!(wrap_lines(&spaces("abcdefgh"), 3), [3, 6, 8]);
+        assert_eq!(wrap_lines(&spaces("a bcdefgh i"), 3), [2, 5, 8, 11]);
+        assert_eq!(wrap_lines(&spaces("abc"), 1), [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_text_gradient() {
+        let black = Color::new(0.0, 0.0, 0.0, 1.0);
+        let gradient = TextGradient::from_corners([WHITE, black, black, black]);
+        assert_eq!(gradient.top, Color::new(0.5, 0.5, 0.5, 1.0));
+        assert_eq!(gradient.line(0, 3), gradient.top);
+        assert_eq!(gradient.line(1, 3), Color::new(0.25, 0.25, 0.25, 1.0));
+        assert_eq!(gradient.line(2, 3), black);
+        assert_eq!(gradient.line(0, 1), gradient.top);
+    }
+}
@@ -1,5 +1,5 @@
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 
 use gml::eval::{Object, ObjectId, Value};
@@ -7,7 +7,7 @@ use macroquad::input::KeyCode;
 use serde::ser::SerializeStruct;
 use serde::{Serialize, Serializer};
 
-use crate::state::Instance;
+use crate::state::{key_vk, Instance};
 
 #[derive(Default)]
 pub struct ObjectAsset {
@@ -15,6 +15,133 @@ pub struct ObjectAsset {
     pub object: Rc<ObjectType>,
     pub events: HashMap<Event, Vec<Action>>,
     pub parent_index: Option<u32>,
+    /// Instances are carried over to the next room instead of being discarded.
+    pub persistent: bool,
+    /// Key events handled by this object or its parents, see `define_key_events()`.
+    pub key_events: HashSet<Event>,
+    pub defaults: RefCell<ObjectDefaults>,
+}
+
+impl ObjectAsset {
+    /// Whether dispatching `event` to instances could do anything. Only key events are
+    /// checked, as there are a lot of keys to dispatch each frame.
+    pub fn handles(&self, event: Event) -> bool {
+        !event.is_key() || self.key_events.contains(&event)
+    }
+}
+
+/// The variables new instances of an object start with, which `object_set_*()` changes.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ObjectDefaults {
+    pub sprite_index: i32,
+    pub mask_index: i32,
+    pub solid: bool,
+    pub visible: bool,
+    pub depth: i32,
+}
+
+impl ObjectDefaults {
+    pub fn new(def: &gmk_file::Object) -> Self {
+        Self {
+            sprite_index: def.sprite_index,
+            mask_index: def.mask_sprite_index.max(-1),
+            solid: def.solid.into(),
+            visible: def.visible.into(),
+            depth: def.depth,
+        }
+    }
+
+    /// The instance variable an `object_get_*()` or `object_set_*()` function is for, from
+    /// the rest of its name, e.g. `sprite_index` for `sprite`.
+    pub fn member_name(function_suffix: &str) -> Option<&'static str> {
+        Some(match function_suffix {
+            "sprite" => "sprite_index",
+            "mask" => "mask_index",
+            "solid" => "solid",
+            "visible" => "visible",
+            "depth" => "depth",
+            _ => return None,
+        })
+    }
+
+    /// `None` if `name` isn't one of the [`Self::member_name()`]s.
+    pub fn get(&self, name: &str) -> Option<Value> {
+        Some(match name {
+            "sprite_index" => self.sprite_index.into(),
+            "mask_index" => self.mask_index.into(),
+            "solid" => self.solid.into(),
+            "visible" => self.visible.into(),
+            "depth" => self.depth.into(),
+            _ => return None,
+        })
+    }
+
+    /// Returns false if `name` isn't one of the [`Self::member_name()`]s.
+    pub fn set(&mut self, name: &str, value: &Value) -> bool {
+        match name {
+            "sprite_index" => self.sprite_index = value.to_int(),
+            "mask_index" => self.mask_index = value.to_int(),
+            "solid" => self.solid = value.to_bool(),
+            "visible" => self.visible = value.to_bool(),
+            "depth" => self.depth = value.to_int(),
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// `object_index` and its parents, nearest first.
+fn ancestors(
+    objects: &HashMap<u32, ObjectAsset>,
+    object_index: u32,
+) -> impl Iterator<Item = (u32, &ObjectAsset)> {
+    let mut next_index = Some(object_index);
+    std::iter::from_fn(move || {
+        let index = next_index?;
+        let object = objects.get(&index)?;
+        next_index = object.parent_index;
+        Some((index, object))
+    })
+}
+
+/// Whether `object_index` or one of its parents has an `event` event.
+pub fn inherits_event(
+    objects: &HashMap<u32, ObjectAsset>,
+    object_index: u32,
+    event: Event,
+) -> bool {
+    ancestors(objects, object_index).any(|(_, object)| object.events.contains_key(&event))
+}
+
+/// The `Event::Collision` that instances of `object_index` should get when colliding with
+/// an instance of `other_index`, if any. Events for the other object's parents also apply.
+pub fn collision_event(
+    objects: &HashMap<u32, ObjectAsset>,
+    object_index: u32,
+    other_index: u32,
+) -> Option<Event> {
+    ancestors(objects, object_index).find_map(|(_, object)| {
+        ancestors(objects, other_index)
+            .map(|(target, _)| Event::Collision(target as i32))
+            .find(|event| object.events.contains_key(event))
+    })
+}
+
+/// Fills in `key_events` for each object, including those inherited from parents.
+pub fn define_key_events(objects: &mut HashMap<u32, ObjectAsset>) {
+    let key_events = objects
+        .keys()
+        .map(|&object_index| {
+            let events = ancestors(objects, object_index)
+                .flat_map(|(_, object)| object.events.keys().copied().filter(Event::is_key))
+                .collect::<HashSet<_>>();
+            (object_index, events)
+        })
+        .collect::<Vec<_>>();
+
+    for (object_index, events) in key_events {
+        objects.get_mut(&object_index).unwrap().key_events = events;
+    }
 }
 
 impl Serialize for ObjectAsset {
@@ -27,6 +154,7 @@ impl Serialize for ObjectAsset {
         s.serialize_field("object", &*self.object)?;
         s.skip_field("events")?;
         s.serialize_field("parent_index", &self.parent_index)?;
+        s.serialize_field("defaults", &self.defaults)?;
         s.end()
     }
 }
@@ -41,11 +169,11 @@ impl Serialize for ObjectType {
     where
         S: Serializer,
     {
+        let mut ids = self.instances.borrow().keys().copied().collect::<Vec<_>>();
+        // sorted, so the state JSON is the same each run
+        ids.sort_by_key(|id| id.0);
         let mut s = serializer.serialize_struct("ObjectType", 1)?;
-        s.serialize_field(
-            "instances",
-            &self.instances.borrow().keys().collect::<Vec<_>>(),
-        )?;
+        s.serialize_field("instances", &ids)?;
         s.end()
     }
 }
@@ -81,6 +209,38 @@ pub enum Event {
     KeyRelease(#[serde(skip)] KeyCode),
     KeyDown(#[serde(skip)] KeyCode),
     Collision(i32),
+    RoomStart,
+    NoMoreLives,
+    NoMoreHealth,
+}
+
+impl Event {
+    pub fn is_key(&self) -> bool {
+        matches!(
+            self,
+            Self::KeyPress(_) | Self::KeyRelease(_) | Self::KeyDown(_)
+        )
+    }
+
+    /// GM's `event_type` and `event_number` for this event.
+    pub fn gm_id(&self) -> (i32, i32) {
+        match *self {
+            Self::Create => (0, 0),
+            Self::Destroy => (1, 0),
+            Self::Alarm(index) => (2, index),
+            Self::StepNormal => (3, 0),
+            Self::StepBegin => (3, 1),
+            Self::StepEnd => (3, 2),
+            Self::Collision(object_index) => (4, object_index),
+            Self::KeyDown(code) => (5, key_vk(code)),
+            Self::RoomStart => (7, gmk_file::OtherEventId::RoomStart as i32),
+            Self::NoMoreLives => (7, gmk_file::OtherEventId::NoMoreLives as i32),
+            Self::NoMoreHealth => (7, gmk_file::OtherEventId::NoMoreHealth as i32),
+            Self::Draw => (8, 0),
+            Self::KeyPress(code) => (9, key_vk(code)),
+            Self::KeyRelease(code) => (10, key_vk(code)),
+        }
+    }
 }
 
 #[derive(Serialize)]
@@ -93,3 +253,125 @@ pub enum Action {
     Move(u32, f32),
     SetVariable(String, Box<gml::ast::Expr>),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn object(parent_index: Option<u32>, events: &[Event]) -> ObjectAsset {
+        ObjectAsset {
+            events: events.iter().map(|&event| (event, vec![])).collect(),
+            parent_index,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_object_defaults() {
+        let def = gmk_file::Object {
+            ver: 430,
+            sprite_index: 12,
+            solid: gmk_file::Bool32::True,
+            visible: gmk_file::Bool32::False,
+            depth: -5,
+            persistent: gmk_file::Bool32::False,
+            parent_object_index: -1,
+            mask_sprite_index: -1,
+            events: Default::default(),
+        };
+        let mut defaults = ObjectDefaults::new(&def);
+        let sprite = ObjectDefaults::member_name("sprite").unwrap();
+        assert_eq!(defaults.get(sprite), Some(Value::Int(12)));
+        assert_eq!(defaults.get("solid"), Some(Value::Bool(true)));
+        assert_eq!(defaults.get("depth"), Some(Value::Int(-5)));
+
+        assert!(defaults.set(sprite, &3.into()));
+        assert_eq!(defaults.sprite_index, 3);
+        assert!(!defaults.set("x", &3.into()));
+        assert_eq!(ObjectDefaults::member_name("x"), None);
+    }
+
+    #[test]
+    fn test_object_functions() {
+        use crate::state::global::tests::{run, TestGame};
+        use gml::eval::Global as _;
+
+        let mut game = TestGame::default();
+        // object 0 is also the id of global, so instance_ids() rejects it
+        game.object("obj_other", &[]);
+        let object_index = game.object("obj_player", &[]);
+        game.content.objects.items[object_index as usize]
+            .as_mut()
+            .unwrap()
+            .data
+            .depth = -5;
+        game.room("rm_test", &[(object_index, 0, 0), (object_index, 0, 0)]);
+        let global = game.start();
+        let id = ObjectId(100001);
+
+        let source = "return object_get_sprite(obj_player) * 100 + object_get_depth(obj_player);";
+        assert_eq!(run(&global, id, source).unwrap(), Value::Int(-105));
+
+        // existing instances change too
+        run(&global, id, "object_set_depth(obj_player, 10);").unwrap();
+        for other in global.instance_ids(ObjectId::new(object_index)) {
+            let instance = global.instance(other).unwrap();
+            assert_eq!(instance.member("depth").unwrap(), Some(Value::Int(10)));
+        }
+        let source = "return object_get_depth(obj_player);";
+        assert_eq!(run(&global, id, source).unwrap(), Value::Int(10));
+    }
+
+    #[test]
+    fn test_key_events() {
+        let left = Event::KeyDown(KeyCode::Left);
+        let right = Event::KeyDown(KeyCode::Right);
+        let mut objects = HashMap::from([
+            (0, object(None, &[left, Event::StepNormal])),
+            (1, object(Some(0), &[])),
+            (2, object(None, &[Event::Create])),
+        ]);
+        define_key_events(&mut objects);
+
+        assert!(objects[&0].handles(left));
+        assert!(!objects[&0].handles(right));
+        // inherited
+        assert!(objects[&1].handles(left));
+        assert!(!objects[&1].handles(Event::KeyPress(KeyCode::Left)));
+        assert!(!objects[&2].handles(left));
+        // other events are always dispatched
+        assert!(objects[&2].handles(Event::StepNormal));
+
+        assert!(inherits_event(&objects, 1, Event::StepNormal));
+        assert!(!inherits_event(&objects, 2, Event::StepNormal));
+    }
+
+    #[test]
+    fn test_collision_event() {
+        let objects = HashMap::from([
+            (0, object(None, &[Event::Collision(2)])),
+            (1, object(Some(0), &[Event::Collision(3)])),
+            (2, object(None, &[])),
+            (3, object(Some(2), &[])),
+        ]);
+        assert_eq!(collision_event(&objects, 0, 2), Some(Event::Collision(2)));
+        // events for parents of the other object apply
+        assert_eq!(collision_event(&objects, 0, 3), Some(Event::Collision(2)));
+        // but the most specific event is used
+        assert_eq!(collision_event(&objects, 1, 3), Some(Event::Collision(3)));
+        assert_eq!(collision_event(&objects, 1, 2), Some(Event::Collision(2)));
+        assert_eq!(collision_event(&objects, 2, 0), None);
+    }
+
+    #[test]
+    fn test_event_gm_id() {
+        assert_eq!(Event::StepNormal.gm_id(), (3, 0));
+        assert_eq!(Event::StepEnd.gm_id(), (3, 2));
+        assert_eq!(Event::Alarm(4).gm_id(), (2, 4));
+        assert_eq!(Event::RoomStart.gm_id(), (7, 4));
+        assert_eq!(
+            Event::KeyPress(KeyCode::Left).gm_id(),
+            (9, gmk_file::Key::Left.into())
+        );
+    }
+}
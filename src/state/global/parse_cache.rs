@@ -0,0 +1,111 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+/// Scripts parsed by a previous run, so startup only needs to parse scripts that changed.
+#[derive(Serialize, Deserialize)]
+pub struct ParseCache {
+    /// The [`gml::ast::VERSION`] the scripts were parsed with.
+    version: u32,
+    /// [`source_hash()`] to the script parsed from that source.
+    scripts: HashMap<u64, gml::ast::Script>,
+}
+
+impl Default for ParseCache {
+    fn default() -> Self {
+        Self {
+            version: gml::ast::VERSION,
+            scripts: HashMap::new(),
+        }
+    }
+}
+
+impl ParseCache {
+    /// An empty cache if the file is missing, can't be read, or is from another parser version.
+    pub fn load(path: &Path) -> Self {
+        let Ok(json) = std::fs::read(path) else {
+            return Self::default();
+        };
+        match serde_json::from_slice::<Self>(&json) {
+            Ok(cache) if cache.version == gml::ast::VERSION => cache,
+            Ok(_) => Self::default(),
+            Err(error) => {
+                log::warn!("ignoring parse cache {}: {error}", path.display());
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self, path: &Path) {
+        let result = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|()| std::fs::write(path, serde_json::to_vec(self)?));
+        if let Err(error) = result {
+            log::warn!("could not write parse cache {}: {error}", path.display());
+        }
+    }
+
+    pub fn get(&self, name: &str, source: &str) -> Option<gml::ast::Script> {
+        self.scripts.get(&source_hash(name, source)).cloned()
+    }
+
+    pub fn len(&self) -> usize {
+        self.scripts.len()
+    }
+
+    pub fn insert(&mut self, name: &str, source: &str, script: gml::ast::Script) {
+        self.scripts.insert(source_hash(name, source), script);
+    }
+}
+
+/// Scripts are keyed by name too, as the AST includes it.
+fn source_hash(name: &str, source: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (name, source).hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cache() {
+        let source = "var i; for (i = 0; i < 3; i += 1) { x += i * 0.5; }";
+        let mut cache = ParseCache::default();
+        cache.insert("scr_test", source, gml::parse("scr_test", source).unwrap());
+
+        let json = serde_json::to_vec(&cache).unwrap();
+        let cache: ParseCache = serde_json::from_slice(&json).unwrap();
+        let cached = cache.get("scr_test", source).unwrap();
+        let fresh = gml::parse("scr_test", source).unwrap();
+        assert_eq!(format!("{cached:?}"), format!("{fresh:?}"));
+
+        // changed or renamed scripts are parsed again
+        assert_eq!(cache.len(), 1);
+        assert!(cache.get("scr_test", "x = 1;").is_none());
+        assert!(cache.get("scr_other", source).is_none());
+    }
+
+    #[test]
+    fn test_parse_cache_version() {
+        let path = std::env::temp_dir().join(format!("parse-cache-{}.json", std::process::id()));
+        let source = "x = 1;";
+        let mut cache = ParseCache::default();
+        cache.insert("scr_test", source, gml::parse("scr_test", source).unwrap());
+        cache.save(&path);
+        assert_eq!(ParseCache::load(&path).len(), 1);
+
+        // parses from another parser version are dropped
+        cache.version += 1;
+        cache.save(&path);
+        assert_eq!(ParseCache::load(&path).len(), 0);
+
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(ParseCache::load(&path).len(), 0);
+    }
+}
@@ -0,0 +1,182 @@
+use macroquad::prelude::*;
+
+use crate::state::View;
+
+/// The view is drawn to a texture of its own size, which is then scaled to fit the window,
+/// so the game looks the same whatever size the window is.
+#[derive(Debug, Default)]
+pub struct Screen {
+    /// GM's `scaling` game setting, see [`letterbox()`].
+    pub scaling: i32,
+    target: Option<RenderTarget>,
+    /// Where the view was last drawn in the window.
+    rect: Option<Rect>,
+}
+
+impl Screen {
+    pub fn new(scaling: i32) -> Self {
+        Self {
+            scaling,
+            ..Default::default()
+        }
+    }
+
+    /// Redirects drawing to the view texture, until [`Self::end()`], with a camera on the
+    /// part of the room the view shows, so everything is drawn at room positions. Returns the
+    /// camera, to set again after drawing to a surface.
+    pub fn begin(&mut self, view: &View) -> Camera2D {
+        let size = view.size;
+        let target = match self.target {
+            Some(target) if self.size() == Some(size) => target,
+            _ => {
+                if let Some(target) = self.target.take() {
+                    target.texture.delete();
+                }
+                let target = render_target(size.x, size.y);
+                target.texture.set_filter(FilterMode::Nearest);
+                self.target = Some(target);
+                target
+            }
+        };
+        let mut camera = view_camera(view);
+        camera.render_target = Some(target);
+        set_camera(&camera);
+        camera
+    }
+
+    /// Draws the view texture to the window, with black bars around it.
+    pub fn end(&mut self) {
+        set_default_camera();
+        gl_use_default_material();
+        clear_background(BLACK);
+        let (Some(target), Some(size)) = (self.target, self.size()) else {
+            return;
+        };
+        let window = vec2(screen_width(), screen_height());
+        let rect = letterbox(window, size.as_vec2(), self.scaling);
+        let params = DrawTextureParams {
+            dest_size: Some(rect.size()),
+            ..Default::default()
+        };
+        draw_texture_ex(target.texture, rect.x, rect.y, WHITE, params);
+        self.rect = Some(rect);
+    }
+
+    /// Where a window position, like the mouse, is in the view.
+    pub fn window_to_view(&self, pos: Vec2) -> Vec2 {
+        match (self.rect, self.size()) {
+            (Some(rect), Some(size)) => (pos - rect.point()) * size.as_vec2() / rect.size(),
+            _ => pos,
+        }
+    }
+
+    /// `window_get_region_scale()`: [`Self::scaling`] as a factor rather than a percentage.
+    pub fn region_scale(&self) -> f64 {
+        match self.scaling {
+            0 => 0.0,
+            ..=-1 => -1.0,
+            percent => f64::from(percent) / 100.0,
+        }
+    }
+
+    pub fn set_region_scale(&mut self, scale: f64) {
+        self.scaling = if scale < 0.0 {
+            -1
+        } else {
+            (scale * 100.0).round() as i32
+        };
+    }
+
+    /// Resizes the window to fit the view of `size` at a fixed [`Self::scaling`].
+    pub fn fit_window(&self, size: UVec2) {
+        if self.scaling > 0 {
+            let window = size.as_vec2() * self.scaling as f32 / 100.0;
+            request_new_screen_size(window.x, window.y);
+        }
+    }
+
+    fn size(&self) -> Option<UVec2> {
+        let texture = self.target?.texture;
+        Some(vec2(texture.width(), texture.height()).as_uvec2())
+    }
+}
+
+/// A camera showing the part of the room in `view`.
+fn view_camera(view: &View) -> Camera2D {
+    let pos = view.offset.as_vec2();
+    let size = view.size.as_vec2();
+    let mut camera = Camera2D::from_display_rect(Rect::new(pos.x, pos.y, size.x, size.y));
+    // render targets are sampled bottom-up, so don't flip y
+    camera.zoom.y = camera.zoom.y.abs();
+    camera
+}
+
+/// Where to draw the view of `size` in a window of size `window`, centered, for GM's
+/// `scaling` setting: a percentage to scale by, 0 to stretch to the window, or negative to
+/// scale to fit the window keeping the aspect ratio.
+pub fn letterbox(window: Vec2, size: Vec2, scaling: i32) -> Rect {
+    let scaled = match scaling {
+        0 => window,
+        ..=-1 => size * (window / size).min_element(),
+        percent => size * percent as f32 / 100.0,
+    }
+    .round();
+    let pos = ((window - scaled) / 2.0).round();
+    Rect::new(pos.x, pos.y, scaled.x, scaled.y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_letterbox() {
+        let size = vec2(640.0, 480.0);
+
+        // keep aspect, bars on the sides of a wide window, or above and below a tall one
+        let rect = letterbox(vec2(1280.0, 720.0), size, -1);
+        assert_eq!(rect, Rect::new(160.0, 0.0, 960.0, 720.0));
+        let rect = letterbox(vec2(800.0, 800.0), size, -1);
+        assert_eq!(rect, Rect::new(0.0, 100.0, 800.0, 600.0));
+        assert_eq!(letterbox(size, size, -1), Rect::new(0.0, 0.0, 640.0, 480.0));
+
+        // stretch
+        let rect = letterbox(vec2(1280.0, 720.0), size, 0);
+        assert_eq!(rect, Rect::new(0.0, 0.0, 1280.0, 720.0));
+
+        // fixed percentage, which can be bigger than the window
+        let rect = letterbox(vec2(800.0, 600.0), size, 100);
+        assert_eq!(rect, Rect::new(80.0, 60.0, 640.0, 480.0));
+        let rect = letterbox(vec2(800.0, 600.0), size, 150);
+        assert_eq!(rect, Rect::new(-80.0, -60.0, 960.0, 720.0));
+    }
+
+    #[test]
+    fn test_view_camera() {
+        let view = View {
+            offset: ivec2(320, -40),
+            size: uvec2(640, 480),
+        };
+        let matrix = view_camera(&view).matrix();
+        let project = |x: f32, y: f32| matrix.project_point3(vec3(x, y, 0.0)).truncate();
+        // the view's corners in the room are the texture's corners, with y up
+        assert_eq!(project(320.0, -40.0), vec2(-1.0, -1.0));
+        assert_eq!(project(960.0, 440.0), vec2(1.0, 1.0));
+        assert_eq!(project(640.0, 200.0), Vec2::ZERO);
+    }
+
+    #[test]
+    fn test_region_scale() {
+        let mut screen = Screen::new(-1);
+        assert_eq!(screen.region_scale(), -1.0);
+        screen.set_region_scale(1.5);
+        assert_eq!(screen.scaling, 150);
+        assert_eq!(screen.region_scale(), 1.5);
+        screen.set_region_scale(-3.0);
+        assert_eq!(screen.scaling, -1);
+        screen.set_region_scale(0.0);
+        assert_eq!(screen.region_scale(), 0.0);
+        // not drawn yet, so positions are unchanged
+        assert_eq!(screen.window_to_view(vec2(10.0, 20.0)), vec2(10.0, 20.0));
+    }
+}
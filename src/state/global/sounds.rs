@@ -0,0 +1,170 @@
+use std::collections::{HashMap, HashSet};
+
+/// How to play a sound: `sound_volume()` and `sound_pan()` change these for later plays and
+/// any that are already playing.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SoundParams {
+    pub looping: bool,
+    /// From 0 to 1.
+    pub volume: f64,
+    /// From -1 (left) to 1 (right).
+    pub pan: f64,
+}
+
+/// Outputs sound resources, by their index.
+pub trait Audio {
+    fn play(&mut self, index: u32, params: SoundParams);
+    /// Changes the volume or pan of the sound if it's playing.
+    fn update(&mut self, index: u32, params: SoundParams);
+    fn stop(&mut self, index: u32);
+    /// Whether the sound is still playing, e.g. it's looping or hasn't reached the end.
+    fn is_playing(&self, index: u32) -> bool;
+}
+
+/// Nothing is output, so sounds play until they're stopped. GM sounds are mostly formats
+/// macroquad can't decode, so for now this is also used when not running headless.
+#[derive(Default, Debug)]
+pub struct NullAudio {
+    playing: HashSet<u32>,
+}
+
+impl Audio for NullAudio {
+    fn play(&mut self, index: u32, _params: SoundParams) {
+        self.playing.insert(index);
+    }
+
+    fn update(&mut self, _index: u32, _params: SoundParams) {}
+
+    fn stop(&mut self, index: u32) {
+        self.playing.remove(&index);
+    }
+
+    fn is_playing(&self, index: u32) -> bool {
+        self.playing.contains(&index)
+    }
+}
+
+/// The sound resources' current params, starting from the resource's volume and pan, and
+/// the [`Audio`] playing them.
+pub struct SoundMap {
+    params: HashMap<u32, SoundParams>,
+    audio: Box<dyn Audio>,
+}
+
+impl std::fmt::Debug for SoundMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SoundMap")
+            .field("params", &self.params)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for SoundMap {
+    fn default() -> Self {
+        Self::new([])
+    }
+}
+
+impl SoundMap {
+    /// `sounds` are the index, volume and pan of each sound resource.
+    pub fn new(sounds: impl IntoIterator<Item = (u32, f64, f64)>) -> Self {
+        let params = sounds
+            .into_iter()
+            .map(|(index, volume, pan)| {
+                let params = SoundParams {
+                    looping: false,
+                    volume,
+                    pan,
+                };
+                (index, params)
+            })
+            .collect();
+        Self {
+            params,
+            audio: Box::<NullAudio>::default(),
+        }
+    }
+
+    /// `sound_play()` and `sound_loop()`, returning false if the sound doesn't exist.
+    pub fn play(&mut self, index: u32, looping: bool) -> bool {
+        let Some(params) = self.params.get_mut(&index) else {
+            return false;
+        };
+        params.looping = looping;
+        self.audio.play(index, *params);
+        true
+    }
+
+    pub fn stop(&mut self, index: u32) {
+        self.audio.stop(index);
+    }
+
+    pub fn stop_all(&mut self) {
+        for index in self.params.keys() {
+            self.audio.stop(*index);
+        }
+    }
+
+    pub fn is_playing(&self, index: u32) -> bool {
+        self.audio.is_playing(index)
+    }
+
+    pub fn set_volume(&mut self, index: u32, volume: f64) {
+        self.update(index, |params| params.volume = volume.clamp(0.0, 1.0));
+    }
+
+    pub fn set_pan(&mut self, index: u32, pan: f64) {
+        self.update(index, |params| params.pan = pan.clamp(-1.0, 1.0));
+    }
+
+    fn update(&mut self, index: u32, f: impl FnOnce(&mut SoundParams)) {
+        if let Some(params) = self.params.get_mut(&index) {
+            f(params);
+            self.audio.update(index, *params);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sound_playing() {
+        let mut sounds = SoundMap::new([(0, 1.0, 0.0), (2, 0.5, -0.5)]);
+
+        assert!(sounds.play(2, false));
+        assert!(sounds.is_playing(2));
+        assert!(!sounds.is_playing(0));
+        sounds.stop(2);
+        assert!(!sounds.is_playing(2));
+
+        assert!(sounds.play(0, true) && sounds.play(2, false));
+        sounds.stop_all();
+        assert!(!sounds.is_playing(0) && !sounds.is_playing(2));
+
+        // missing sounds never play
+        assert!(!sounds.play(1, false));
+        assert!(!sounds.is_playing(1));
+    }
+
+    #[test]
+    fn test_sound_params() {
+        let mut sounds = SoundMap::new([(0, 0.5, -0.5)]);
+        let params = |sounds: &SoundMap| sounds.params[&0];
+        assert_eq!(params(&sounds).volume, 0.5);
+        assert_eq!(params(&sounds).pan, -0.5);
+
+        sounds.set_volume(0, 2.0);
+        sounds.set_pan(0, 0.25);
+        sounds.play(0, true);
+        let expected = SoundParams {
+            looping: true,
+            volume: 1.0,
+            pan: 0.25,
+        };
+        assert_eq!(params(&sounds), expected);
+        // ignored, like playing them
+        sounds.set_volume(1, 0.0);
+    }
+}
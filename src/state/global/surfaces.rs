@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+
+use macroquad::prelude::*;
+
+/// Creates and draws to the render targets behind surfaces, by surface index.
+pub trait Targets {
+    fn create(&mut self, index: i32, size: UVec2);
+    /// Redirects all following draws to the surface.
+    fn set(&mut self, index: i32);
+    /// Draws with `camera` again, or to the window without one.
+    fn reset(&mut self, camera: Option<&Camera2D>);
+    fn free(&mut self, index: i32);
+    fn texture(&self, index: i32) -> Option<Texture2D>;
+}
+
+/// macroquad render targets, which need a GL context once a surface is created.
+#[derive(Default)]
+pub struct GlTargets {
+    items: HashMap<i32, RenderTarget>,
+}
+
+impl Targets for GlTargets {
+    fn create(&mut self, index: i32, size: UVec2) {
+        let target = render_target(size.x, size.y);
+        target.texture.set_filter(FilterMode::Nearest);
+        self.items.insert(index, target);
+    }
+
+    fn set(&mut self, index: i32) {
+        let Some(target) = self.items.get(&index) else {
+            return;
+        };
+        let size = vec2(target.texture.width(), target.texture.height());
+        let mut camera = Camera2D::from_display_rect(Rect::new(0.0, 0.0, size.x, size.y));
+        // render targets are sampled bottom-up, so don't flip y
+        camera.zoom.y = camera.zoom.y.abs();
+        camera.render_target = Some(*target);
+        set_camera(&camera);
+    }
+
+    fn reset(&mut self, camera: Option<&Camera2D>) {
+        match camera {
+            Some(camera) => set_camera(camera),
+            None => set_default_camera(),
+        }
+    }
+
+    fn free(&mut self, index: i32) {
+        if let Some(target) = self.items.remove(&index) {
+            target.texture.delete();
+        }
+    }
+
+    fn texture(&self, index: i32) -> Option<Texture2D> {
+        self.items.get(&index).map(|target| target.texture)
+    }
+}
+
+/// Surfaces created by `surface_create()`, and the one being drawn to, if any.
+pub struct SurfaceMap {
+    last_index: i32,
+    sizes: HashMap<i32, UVec2>,
+    target: Option<i32>,
+    /// Draws to the [`Screen`](super::Screen) when not drawing to a surface.
+    screen_camera: Option<Camera2D>,
+    targets: Box<dyn Targets>,
+}
+
+impl std::fmt::Debug for SurfaceMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SurfaceMap")
+            .field("sizes", &self.sizes)
+            .field("target", &self.target)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for SurfaceMap {
+    fn default() -> Self {
+        Self::new(Box::<GlTargets>::default())
+    }
+}
+
+impl SurfaceMap {
+    pub fn new(targets: Box<dyn Targets>) -> Self {
+        Self {
+            last_index: 0,
+            sizes: HashMap::new(),
+            target: None,
+            screen_camera: None,
+            targets,
+        }
+    }
+
+    pub fn create(&mut self, size: UVec2) -> i32 {
+        self.last_index += 1;
+        self.sizes.insert(self.last_index, size);
+        self.targets.create(self.last_index, size);
+        self.last_index
+    }
+
+    pub fn exists(&self, index: i32) -> bool {
+        self.sizes.contains_key(&index)
+    }
+
+    pub fn texture(&self, index: i32) -> Option<Texture2D> {
+        self.targets.texture(index)
+    }
+
+    pub fn target(&self) -> Option<i32> {
+        self.target
+    }
+
+    /// Redirects all following draws to the surface, until `reset_target()`.
+    pub fn set_target(&mut self, index: i32) -> bool {
+        if !self.exists(index) {
+            return false;
+        }
+        self.targets.set(index);
+        self.target = Some(index);
+        true
+    }
+
+    pub fn reset_target(&mut self) {
+        if self.target.take().is_some() {
+            self.targets.reset(self.screen_camera.as_ref());
+        }
+    }
+
+    pub fn set_screen_camera(&mut self, camera: Camera2D) {
+        self.screen_camera = Some(camera);
+    }
+
+    pub fn free(&mut self, index: i32) {
+        if self.target == Some(index) {
+            self.reset_target();
+        }
+        if self.sizes.remove(&index).is_some() {
+            self.targets.free(index);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+
+    /// Records the calls, as creating a real render target needs a GL context.
+    #[derive(Clone, Default)]
+    struct LogTargets(Rc<RefCell<Vec<String>>>);
+
+    impl Targets for LogTargets {
+        fn create(&mut self, index: i32, size: UVec2) {
+            self.0.borrow_mut().push(format!("create {index} {size}"));
+        }
+
+        fn set(&mut self, index: i32) {
+            self.0.borrow_mut().push(format!("set {index}"));
+        }
+
+        fn reset(&mut self, camera: Option<&Camera2D>) {
+            let to = if camera.is_some() { "screen" } else { "window" };
+            self.0.borrow_mut().push(format!("reset to {to}"));
+        }
+
+        fn free(&mut self, index: i32) {
+            self.0.borrow_mut().push(format!("free {index}"));
+        }
+
+        fn texture(&self, _index: i32) -> Option<Texture2D> {
+            None
+        }
+    }
+
+    #[test]
+    fn test_surface_lifecycle() {
+        let targets = LogTargets::default();
+        let mut surfaces = SurfaceMap::new(Box::new(targets.clone()));
+        let first = surfaces.create(uvec2(32, 16));
+        let second = surfaces.create(uvec2(8, 8));
+        assert_eq!((first, second), (1, 2));
+        assert!(surfaces.exists(first) && surfaces.exists(second));
+
+        assert!(surfaces.set_target(first));
+        assert_eq!(surfaces.target(), Some(first));
+        surfaces.reset_target();
+        assert_eq!(surfaces.target(), None);
+        // nothing to reset
+        surfaces.reset_target();
+
+        // freeing the target draws to the view again
+        surfaces.set_screen_camera(Camera2D::default());
+        assert!(surfaces.set_target(second));
+        surfaces.free(second);
+        assert_eq!(surfaces.target(), None);
+        assert!(!surfaces.exists(second));
+        surfaces.free(first);
+        assert!(!surfaces.exists(first));
+
+        assert_eq!(
+            *targets.0.borrow(),
+            [
+                "create 1 [32, 16]",
+                "create 2 [8, 8]",
+                "set 1",
+                "reset to window",
+                "set 2",
+                "reset to screen",
+                "free 2",
+                "free 1",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_surface() {
+        let targets = LogTargets::default();
+        let mut surfaces = SurfaceMap::new(Box::new(targets.clone()));
+        assert!(!surfaces.exists(1));
+        assert!(surfaces.texture(1).is_none());
+        assert!(!surfaces.set_target(1));
+        assert_eq!(surfaces.target(), None);
+        surfaces.free(1);
+        surfaces.reset_target();
+        assert!(targets.0.borrow().is_empty());
+    }
+}
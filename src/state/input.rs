@@ -0,0 +1,303 @@
+use std::collections::HashMap;
+
+use gmk_file::Key;
+use macroquad::prelude::*;
+use serde::Serialize;
+
+use super::{key_code, KEY_CODES};
+
+/// GM `mb_*` mouse button constants.
+pub const MB_ANY: i32 = -1;
+pub const MB_NONE: i32 = 0;
+pub const MB_LEFT: i32 = 1;
+pub const MB_RIGHT: i32 = 2;
+pub const MB_MIDDLE: i32 = 3;
+
+/// Typed as a character by backspace.
+const BACKSPACE: char = '\u{8}';
+/// GM only keeps the last 1024 typed characters in `keyboard_string`.
+const KEYBOARD_STRING_MAX: usize = 1024;
+
+const MOUSE_BUTTONS: [MouseButton; 3] =
+    [MouseButton::Left, MouseButton::Right, MouseButton::Middle];
+
+#[derive(Copy, Clone, Default, Debug, Serialize)]
+pub struct ButtonState {
+    pub down: bool,
+    pub pressed: bool,
+    pub released: bool,
+}
+
+/// Snapshot of the input state for a frame, so scripts see consistent values
+/// and tests can provide synthetic input.
+#[derive(Default, Debug, Serialize)]
+pub struct Input {
+    /// Indexed by `mb_* - 1`.
+    pub mouse_buttons: [ButtonState; 3],
+    pub mouse_wheel: f32,
+    /// In window pixels, see [`Global::screen_to_room()`](super::Global::screen_to_room).
+    pub mouse_pos: Vec2,
+    /// Only keys with some state set are present, after applying `key_map`.
+    #[serde(skip)]
+    pub keys: HashMap<KeyCode, ButtonState>,
+    /// Physical keys to the key they act as, from `keyboard_set_map()`.
+    #[serde(skip)]
+    pub key_map: HashMap<KeyCode, KeyCode>,
+    /// Text typed so far, which scripts read and clear as `keyboard_string`.
+    pub keyboard_string: String,
+    /// The last character typed, which scripts read and clear as `keyboard_lastchar`.
+    pub keyboard_lastchar: String,
+}
+
+impl Input {
+    /// Updates to the current frame's state, keeping the key map.
+    pub fn poll(&mut self) {
+        self.mouse_buttons = MOUSE_BUTTONS.map(|button| ButtonState {
+            down: is_mouse_button_down(button),
+            pressed: is_mouse_button_pressed(button),
+            released: is_mouse_button_released(button),
+        });
+        self.mouse_wheel = mouse_wheel().1;
+        self.mouse_pos = mouse_position().into();
+        self.set_keys(KEY_CODES.iter().map(|&key| {
+            let state = ButtonState {
+                down: is_key_down(key),
+                pressed: is_key_pressed(key),
+                released: is_key_released(key),
+            };
+            (key, state)
+        }));
+        let mut chars = std::iter::from_fn(get_char_pressed)
+            .filter(|char| !char.is_control())
+            .collect::<Vec<_>>();
+        if is_key_pressed(KeyCode::Backspace) {
+            chars.push(BACKSPACE);
+        }
+        self.type_chars(chars);
+    }
+
+    /// Updates `keyboard_string` and `keyboard_lastchar` for typed `chars`. Backspace removes
+    /// the last character, but is still the last character typed.
+    pub fn type_chars(&mut self, chars: impl IntoIterator<Item = char>) {
+        for char in chars {
+            if char == BACKSPACE {
+                self.keyboard_string.pop();
+            } else {
+                self.keyboard_string.push(char);
+            }
+            self.keyboard_lastchar = char.to_string();
+        }
+        let excess = self
+            .keyboard_string
+            .chars()
+            .count()
+            .saturating_sub(KEYBOARD_STRING_MAX);
+        if excess > 0 {
+            self.keyboard_string = self.keyboard_string.chars().skip(excess).collect();
+        }
+    }
+
+    /// Sets the state of the physical keys, mapped by `key_map`.
+    pub fn set_keys(&mut self, keys: impl IntoIterator<Item = (KeyCode, ButtonState)>) {
+        self.keys.clear();
+        for (key, state) in keys {
+            if !(state.down || state.pressed || state.released) {
+                continue;
+            }
+            let key = self.key_map.get(&key).copied().unwrap_or(key);
+            let entry = self.keys.entry(key).or_default();
+            entry.down |= state.down;
+            entry.pressed |= state.pressed;
+            entry.released |= state.released;
+        }
+    }
+
+    /// Pressing `from` acts as pressing `to`, from the next poll.
+    pub fn keyboard_set_map(&mut self, from: i32, to: i32) {
+        let (Ok(from), Ok(to)) = (Key::try_from(from), Key::try_from(to)) else {
+            return;
+        };
+        self.key_map.insert(key_code(from), key_code(to));
+    }
+
+    pub fn keyboard_unset_map(&mut self) {
+        self.key_map.clear();
+    }
+
+    fn key_check(&self, vk: i32, f: impl Fn(&ButtonState) -> bool) -> bool {
+        match Key::try_from(vk) {
+            Ok(Key::AnyKey) => self.keys.values().any(f),
+            Ok(Key::NoKey) => !self.keys.values().any(f),
+            Ok(key) => self.keys.get(&key_code(key)).is_some_and(f),
+            Err(_) => false,
+        }
+    }
+
+    pub fn keyboard_check(&self, vk: i32) -> bool {
+        self.key_check(vk, |state| state.down)
+    }
+
+    pub fn keyboard_check_pressed(&self, vk: i32) -> bool {
+        self.key_check(vk, |state| state.pressed)
+    }
+
+    pub fn keyboard_check_released(&self, vk: i32) -> bool {
+        self.key_check(vk, |state| state.released)
+    }
+
+    fn mouse_check(&self, button: i32, f: impl Fn(&ButtonState) -> bool) -> bool {
+        match button {
+            MB_ANY => self.mouse_buttons.iter().any(f),
+            MB_NONE => !self.mouse_buttons.iter().any(f),
+            _ => usize::try_from(button - 1)
+                .ok()
+                .and_then(|index| self.mouse_buttons.get(index))
+                .is_some_and(f),
+        }
+    }
+
+    pub fn mouse_check_button(&self, button: i32) -> bool {
+        self.mouse_check(button, |state| state.down)
+    }
+
+    pub fn mouse_check_button_pressed(&self, button: i32) -> bool {
+        self.mouse_check(button, |state| state.pressed)
+    }
+
+    pub fn mouse_check_button_released(&self, button: i32) -> bool {
+        self.mouse_check(button, |state| state.released)
+    }
+
+    pub fn mouse_wheel_up(&self) -> bool {
+        self.mouse_wheel > 0.0
+    }
+
+    pub fn mouse_wheel_down(&self) -> bool {
+        self.mouse_wheel < 0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gml::eval::{Global as _, ObjectId};
+
+    use super::*;
+    use crate::state::global::tests::TestGame;
+    use crate::state::Event;
+
+    #[test]
+    fn test_mouse_check_button() {
+        let mut input = Input::default();
+        assert!(!input.mouse_check_button(MB_LEFT));
+        assert!(input.mouse_check_button(MB_NONE));
+
+        input.mouse_buttons[0].down = true;
+        assert!(input.mouse_check_button(MB_LEFT));
+        assert!(!input.mouse_check_button(MB_RIGHT));
+        assert!(input.mouse_check_button(MB_ANY));
+        assert!(!input.mouse_check_button(MB_NONE));
+        assert!(!input.mouse_check_button_pressed(MB_LEFT));
+        assert!(!input.mouse_check_button(42));
+    }
+
+    #[test]
+    fn test_keyboard_check() {
+        let mut input = Input::default();
+        let vk_anykey = i32::from(Key::AnyKey);
+        let vk_nokey = i32::from(Key::NoKey);
+        let vk_space = i32::from(Key::Space);
+        assert!(!input.keyboard_check(vk_anykey));
+        assert!(input.keyboard_check(vk_nokey));
+        assert!(!input.keyboard_check(vk_space));
+
+        input.keys.insert(
+            KeyCode::Space,
+            ButtonState {
+                down: true,
+                pressed: true,
+                released: false,
+            },
+        );
+        assert!(input.keyboard_check(vk_anykey));
+        assert!(!input.keyboard_check(vk_nokey));
+        assert!(input.keyboard_check(vk_space));
+        assert!(input.keyboard_check_pressed(vk_anykey));
+        assert!(!input.keyboard_check_released(vk_anykey));
+        assert!(input.keyboard_check_released(vk_nokey));
+    }
+
+    #[test]
+    fn test_keyboard_set_map() {
+        let mut input = Input::default();
+        let vk_left = i32::from(Key::Left);
+        let a_down = ButtonState {
+            down: true,
+            pressed: false,
+            released: false,
+        };
+        input.keyboard_set_map('A' as i32, vk_left);
+
+        input.set_keys([(KeyCode::A, a_down)]);
+        assert!(input.keyboard_check(vk_left));
+        assert!(!input.keyboard_check('A' as i32));
+
+        input.keyboard_unset_map();
+        input.set_keys([(KeyCode::A, a_down)]);
+        assert!(!input.keyboard_check(vk_left));
+        assert!(input.keyboard_check('A' as i32));
+    }
+
+    #[test]
+    fn test_text_input_field() {
+        let step = r#"
+            if keyboard_lastchar != "" {
+                // `!` submits the text
+                if keyboard_lastchar == "!" {
+                    submitted = keyboard_string;
+                    keyboard_string = "";
+                }
+                keyboard_lastchar = "";
+            }
+            shown += keyboard_string + "_,";
+            "#;
+        let mut game = TestGame::default();
+        let object_index = game.object(
+            "obj_text_input",
+            &[
+                (gmk_file::EventId::Create, r#"shown = "";"#),
+                (gmk_file::EventId::Step(gmk_file::StepEventId::Normal), step),
+            ],
+        );
+        game.room("rm_test", &[(object_index, 0, 0)]);
+        let global = game.start();
+        let frame = |chars: &str| {
+            global.input.borrow_mut().type_chars(chars.chars());
+            global.dispatch(Event::StepNormal);
+        };
+
+        frame("hu");
+        frame("\u{8}i");
+        assert_eq!(global.input.borrow().keyboard_lastchar, "");
+        frame("");
+        frame(" there!");
+        let instance = global.instance(ObjectId(100001)).unwrap();
+        assert_eq!(
+            instance.member("shown").unwrap(),
+            Some("hu_,hi_,hi_,_,".to_string().into())
+        );
+        assert_eq!(
+            instance.member("submitted").unwrap(),
+            Some("hi there!".to_string().into())
+        );
+    }
+
+    #[test]
+    fn test_keyboard_string_limit() {
+        let mut input = Input::default();
+        input.type_chars("a".repeat(KEYBOARD_STRING_MAX).chars());
+        input.type_chars(['b']);
+        assert_eq!(input.keyboard_string.len(), KEYBOARD_STRING_MAX);
+        assert!(input.keyboard_string.ends_with("ab"));
+        assert_eq!(input.keyboard_lastchar, "b");
+    }
+}
@@ -1,5 +1,5 @@
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::ops::DerefMut;
 use std::rc::Rc;
 
@@ -7,13 +7,13 @@ use glam::{dvec2, DVec2};
 use gml::eval::{Object, ObjectId, Value};
 use gml::Context;
 use macroquad::color::Color;
-use macroquad::prelude::draw_texture;
-use serde::Serialize;
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
 
-use super::{default, serialize_color, Action, Draw, Event, Global, View};
+use super::{default, log_script_result, serialize_color, Action, Draw, Event, Global, View};
 use crate::assets::{AssetId, SpriteAsset};
 
-#[derive(Debug, Serialize)]
+#[derive(Debug)]
 pub struct Instance {
     pub id: ObjectId,
     pub state: RefCell<InstanceState>,
@@ -21,17 +21,92 @@ pub struct Instance {
     pub parent_object_index: Option<u32>,
     pub vars: gml::eval::Namespace,
     pub alarm_id: ObjectId,
-    #[serde(skip)]
     pub alarm: Rc<InstanceAlarm>,
 }
 
+/// Members handled by `Instance::member()` rather than stored in `vars`.
+pub const BUILTIN_MEMBERS: &[&str] = &[
+    "id",
+    "object_index",
+    "visible",
+    "solid",
+    "persistent",
+    "depth",
+    "x",
+    "y",
+    "xprevious",
+    "yprevious",
+    "speed",
+    "direction",
+    "hspeed",
+    "vspeed",
+    "friction",
+    "gravity",
+    "gravity_direction",
+    "alarm",
+    "sprite_index",
+    "mask_index",
+    "image_speed",
+    "image_index",
+    "image_single",
+    "image_blend",
+    "image_alpha",
+];
+
+impl Serialize for Instance {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut s = serializer.serialize_struct("Instance", 7)?;
+        s.serialize_field("id", &self.id)?;
+        s.serialize_field("state", &self.state)?;
+        s.serialize_field("object_index", &self.object_index)?;
+        s.serialize_field("parent_object_index", &self.parent_object_index)?;
+        s.serialize_field("vars", &self.vars)?;
+        s.serialize_field("members", &self.all_members())?;
+        s.serialize_field("alarm_id", &self.alarm_id)?;
+        s.skip_field("alarm")?;
+        s.end()
+    }
+}
+
 impl Instance {
+    /// A copy of this instance with a new id, that doesn't share any state with it.
+    pub fn copy(&self, id: ObjectId, alarm_id: ObjectId, alarm: Rc<InstanceAlarm>) -> Self {
+        Self {
+            id,
+            state: RefCell::new(self.state.borrow().clone()),
+            object_index: self.object_index,
+            parent_object_index: self.parent_object_index,
+            vars: self.vars.clone_values(),
+            alarm_id,
+            alarm,
+        }
+    }
+
+    /// Built-in and user variables merged into a single view, for debugging.
+    pub fn all_members(&self) -> BTreeMap<String, Value> {
+        let mut result = BTreeMap::new();
+        for name in self.vars.debug_member_names().unwrap_or_default() {
+            if let Some(value) = self.vars.get(&name) {
+                result.insert(name, value);
+            }
+        }
+        for &name in BUILTIN_MEMBERS {
+            if let Ok(Some(value)) = self.member(name) {
+                result.insert(name.to_string(), value);
+            }
+        }
+        result
+    }
+
     pub fn step(self: Rc<Self>, global: &Global) {
         {
             let mut state = self.state.borrow_mut();
             let state = state.deref_mut();
             state.image_index += state.image_speed;
-            state.pos += state.velocity.cartesian();
+            state.apply_motion();
         }
 
         let mut alarm_ids = vec![];
@@ -50,8 +125,99 @@ impl Instance {
         }
     }
 
+    pub fn move_towards_point(&self, target: DVec2, speed: f64) {
+        let mut state = self.state.borrow_mut();
+        let direction = Polar::from(target - state.pos).direction;
+        state.velocity = InstanceVelocity::Polar(Polar {
+            length: speed,
+            direction,
+        });
+    }
+
+    /// Rounds the position to the nearest multiple of `snap`, axes with a snap of 0 or
+    /// less are left alone.
+    pub fn move_snap(&self, snap: DVec2) {
+        let mut state = self.state.borrow_mut();
+        state.pos = snap_pos(state.pos, snap);
+    }
+
+    /// Wraps to the opposite edge of a room of `room_size` when moving more than `margin`
+    /// past an edge, for each axis enabled in `wrap`.
+    pub fn move_wrap(&self, wrap: [bool; 2], room_size: DVec2, margin: f64) {
+        let mut state = self.state.borrow_mut();
+        let velocity = state.velocity.cartesian();
+        let span = room_size + 2.0 * margin;
+        for (axis, wrap) in wrap.into_iter().enumerate() {
+            if !wrap {
+                continue;
+            }
+            if velocity[axis] < 0.0 && state.pos[axis] < -margin {
+                state.pos[axis] += span[axis];
+            } else if velocity[axis] > 0.0 && state.pos[axis] >= room_size[axis] + margin {
+                state.pos[axis] -= span[axis];
+            }
+        }
+    }
+
+    /// Whether the position is already a multiple of `snap`.
+    pub fn place_snapped(&self, snap: DVec2) -> bool {
+        let pos = self.state.borrow().pos;
+        snap_pos(pos, snap) == pos
+    }
+
+    /// Moves up to `max_distance` in `direction` until touching a solid instance.
+    pub fn move_contact_solid(&self, global: &Global, direction: f64, max_distance: f64) {
+        let step = DVec2::from(Polar {
+            length: 1.0,
+            direction,
+        });
+        let mut pos = self.state.borrow().pos;
+        if global.place_solid(self, pos) {
+            return;
+        }
+        let mut distance = 0.0;
+        while distance < max_distance && !global.place_solid(self, pos + step) {
+            pos += step;
+            distance += 1.0;
+        }
+        self.state.borrow_mut().pos = pos;
+    }
+
+    /// Reflects velocity off any solid instance that would be hit this step.
+    pub fn move_bounce_solid(&self, global: &Global) {
+        let (pos, velocity) = {
+            let state = self.state.borrow();
+            (state.pos, state.velocity.cartesian())
+        };
+        if !global.place_solid(self, pos + velocity) {
+            return;
+        }
+        let mut velocity = velocity;
+        let hit_x = global.place_solid(self, pos + dvec2(velocity.x, 0.0));
+        let hit_y = global.place_solid(self, pos + dvec2(0.0, velocity.y));
+        if hit_x {
+            velocity.x = -velocity.x;
+        }
+        if hit_y {
+            velocity.y = -velocity.y;
+        }
+        if !hit_x && !hit_y {
+            // only hit a corner
+            velocity = -velocity;
+        }
+        self.state.borrow_mut().velocity = InstanceVelocity::Cartesian(velocity);
+    }
+
     pub fn dispatch(self: Rc<Self>, global: &Global, event: Event) {
+        let id = self.id;
+        self.dispatch_other(global, event, id);
+    }
+
+    /// Dispatches `event` with `other` referring to `other_id`, e.g. the instance collided with.
+    pub fn dispatch_other(self: Rc<Self>, global: &Global, event: Event, other_id: ObjectId) {
         let mut ctx = Context::new(global, self.id, self.clone());
+        ctx.other_id = other_id;
+        ctx.event = Some(event.gm_id());
 
         // implicit inheritance
         let mut object_index = self.object_index;
@@ -71,15 +237,11 @@ impl Instance {
         for action in actions {
             match action {
                 Action::ScriptInline(script) => {
-                    if let Err(error) = ctx.exec_script(script, &[]) {
-                        eprintln!("{error}");
-                    }
+                    log_script_result(ctx.exec_script(script, &[]));
                 }
                 Action::ScriptRes(index) => {
                     let script = &global.scripts.values[index];
-                    if let Err(error) = ctx.exec_script(script, &[]) {
-                        eprintln!("{error}");
-                    }
+                    log_script_result(ctx.exec_script(script, &[]));
                 }
                 Action::Bounce => {
                     unimplemented!("Action: Bounce");
@@ -103,37 +265,54 @@ impl Instance {
 }
 
 impl Draw for Instance {
-    fn draw(&self, global: &Global, view: &View) {
+    fn draw(&self, global: &Global, _view: &View) {
         let mut state = self.state.borrow_mut();
-        if let Some(&mut sprite_asset) = state.sprite_index.try_into().ok().map(|index| {
-            state
-                .sprite_asset
-                .get_or_insert_with(|| global.loader().get_sprite(index))
-        }) {
+        if state.sprite_asset.is_none() {
+            state.sprite_asset = global.loader().try_get_sprite(state.sprite_index);
+        }
+        if let Some(sprite_asset) = state.sprite_asset {
             let assets = global.assets.borrow();
             let sprite = assets.sprites.get(sprite_asset);
 
-            let sprite_frame = state.image_index % sprite.textures.len() as f64;
+            let sprite_frame = state.image_index % sprite.frames.len() as f64;
             state.image_index = sprite_frame;
 
-            let texture = sprite.textures[sprite_frame.floor() as usize];
-            let pos = state.pos.as_vec2() - sprite.origin.as_vec2() - view.offset.as_vec2();
-            draw_texture(texture, pos.x, pos.y, state.image_blend_alpha);
+            let frame = sprite.frames[sprite_frame.floor() as usize];
+            let pos = sprite.draw_pos(state.pos.as_vec2());
+            frame.draw(pos.x, pos.y, state.image_blend_alpha, Default::default());
         }
     }
 }
 
 impl Object for Instance {
+    fn debug_member_names(&self) -> Option<Vec<String>> {
+        Some(self.all_members().into_keys().collect())
+    }
+
     fn member(&self, name: &str) -> gml::eval::Result<Option<Value>> {
         // dbg!(name);
         let state = self.state.borrow();
         Ok(Some(match name {
+            "id" => self.id.into(),
+            "object_index" => (self.object_index as i32).into(),
             "visible" => state.visible.into(),
+            "solid" => state.solid.into(),
+            "persistent" => state.persistent.into(),
             "depth" => state.depth.into(),
             "x" => state.pos.x.into(),
             "y" => state.pos.y.into(),
+            "xprevious" => state.previous_pos.x.into(),
+            "yprevious" => state.previous_pos.y.into(),
+            "speed" => state.velocity.polar().length.into(),
+            "direction" => state.velocity.polar().direction.into(),
+            "hspeed" => state.velocity.cartesian().x.into(),
+            "vspeed" => state.velocity.cartesian().y.into(),
+            "friction" => state.friction.into(),
+            "gravity" => state.gravity.into(),
+            "gravity_direction" => state.gravity_direction.into(),
             "alarm" => self.alarm_id.into(),
             "sprite_index" => state.sprite_index.into(),
+            "mask_index" => state.mask_index.into(),
             "image_speed" => state.image_speed.into(),
             "image_index" => state.image_index.into(),
             "image_single" => if state.image_speed > 0.0 {
@@ -142,6 +321,12 @@ impl Object for Instance {
                 -1.0
             }
             .into(),
+            "image_blend" => {
+                // GM colors are 0xBBGGRR
+                let color = state.image_blend_alpha;
+                let [r, g, b] = [color.r, color.g, color.b].map(|c| (c * 255.0).round() as u8);
+                i32::from_le_bytes([r, g, b, 0]).into()
+            }
             "image_alpha" => (state.image_blend_alpha.a as f64).into(),
             _ => return self.vars.member(name),
         }))
@@ -151,10 +336,15 @@ impl Object for Instance {
         // dbg!(name);
         let mut state = self.state.borrow_mut();
         match name {
+            "id" | "object_index" => return Err(gml::eval::Error::AssignToValue),
             "visible" => state.visible = value.to_bool(),
+            "solid" => state.solid = value.to_bool(),
+            "persistent" => state.persistent = value.to_bool(),
             "depth" => state.depth = value.to_int(),
             "x" => state.pos.x = value.to_float(),
             "y" => state.pos.y = value.to_float(),
+            "xprevious" => state.previous_pos.x = value.to_float(),
+            "yprevious" => state.previous_pos.y = value.to_float(),
             "speed" => {
                 state.velocity.polar_mut().length = value.to_float();
             }
@@ -167,12 +357,16 @@ impl Object for Instance {
             "vspeed" => {
                 state.velocity.cartesian_mut().y = value.to_float();
             }
+            "friction" => state.friction = value.to_float(),
+            "gravity" => state.gravity = value.to_float(),
+            "gravity_direction" => state.gravity_direction = value.to_float(),
             "alarm" => return Err(gml::eval::Error::AssignToValue),
             "sprite_index" => {
                 state.sprite_index = value.to_int();
                 state.image_index = 0.0;
                 state.sprite_asset = None;
             }
+            "mask_index" => state.mask_index = value.to_int(),
             "image_speed" => state.image_speed = value.to_float(),
             "image_index" => state.image_index = value.to_float(),
             "image_single" => {
@@ -185,11 +379,8 @@ impl Object for Instance {
                 }
             }
             "image_blend" => {
-                let color = value.to_int();
-                let [r, g, b, _] = color.to_le_bytes();
-                state.image_blend_alpha.r = r as f32 / 255.0;
-                state.image_blend_alpha.g = g as f32 / 255.0;
-                state.image_blend_alpha.b = b as f32 / 255.0;
+                let alpha = state.image_blend_alpha.a;
+                state.image_blend_alpha = crate::scripts::gm_color(value.to_int(), alpha.into());
             }
             "image_alpha" => state.image_blend_alpha.a = value.to_float() as f32,
             _ => self.vars.set_member(name, value)?,
@@ -198,13 +389,24 @@ impl Object for Instance {
     }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Clone, Debug, Serialize)]
 pub struct InstanceState {
     pub pos: DVec2,
+    /// Before the last `apply_motion()`, solid collisions move instances back here.
+    pub previous_pos: DVec2,
     pub depth: i32,
+    #[serde(serialize_with = "serialize_velocity")]
     pub velocity: InstanceVelocity,
+    pub friction: f64,
+    pub gravity: f64,
+    pub gravity_direction: f64,
+    pub solid: bool,
     pub visible: bool,
+    /// Whether to carry the instance over to the next room, starting as the object's setting.
+    pub persistent: bool,
     pub sprite_index: i32,
+    /// Sprite used for collisions, or -1 to use `sprite_index`.
+    pub mask_index: i32,
     pub sprite_asset: Option<AssetId<SpriteAsset>>,
     pub image_speed: f64,
     pub image_index: f64,
@@ -212,7 +414,39 @@ pub struct InstanceState {
     pub image_blend_alpha: Color,
 }
 
-#[derive(Default, Debug, Serialize)]
+impl InstanceState {
+    pub fn collision_sprite_index(&self) -> i32 {
+        if self.mask_index >= 0 {
+            self.mask_index
+        } else {
+            self.sprite_index
+        }
+    }
+
+    /// Applies friction, then gravity to the velocity, then moves by it, in the
+    /// same order as GM.
+    pub fn apply_motion(&mut self) {
+        self.previous_pos = self.pos;
+        if self.friction != 0.0 {
+            let polar = self.velocity.polar_mut();
+            if polar.length.abs() <= self.friction {
+                polar.length = 0.0;
+            } else {
+                polar.length -= self.friction * polar.length.signum();
+            }
+        }
+        if self.gravity != 0.0 {
+            let gravity = DVec2::from(Polar {
+                length: self.gravity,
+                direction: self.gravity_direction,
+            });
+            *self.velocity.cartesian_mut() += gravity;
+        }
+        self.pos += self.velocity.cartesian();
+    }
+}
+
+#[derive(Clone, Default, Debug, Serialize)]
 pub struct InstanceAlarm {
     active: RefCell<HashMap<i32, i32>>,
 }
@@ -236,7 +470,22 @@ impl Object for InstanceAlarm {
     }
 }
 
-#[derive(Debug, Serialize)]
+/// Emits both representations, so the debugger doesn't see it flip between them.
+fn serialize_velocity<S>(value: &InstanceVelocity, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let cartesian = value.cartesian();
+    let polar = value.polar();
+    let mut s = serializer.serialize_struct("InstanceVelocity", 4)?;
+    s.serialize_field("hspeed", &cartesian.x)?;
+    s.serialize_field("vspeed", &cartesian.y)?;
+    s.serialize_field("speed", &polar.length)?;
+    s.serialize_field("direction", &polar.direction)?;
+    s.end()
+}
+
+#[derive(Clone, Debug, Serialize)]
 pub enum InstanceVelocity {
     Cartesian(DVec2),
     Polar(Polar),
@@ -254,11 +503,13 @@ pub struct Polar {
     pub direction: f64,
 }
 
+// GM directions are counter-clockwise degrees, but y is down, so y is flipped here.
+
 impl From<DVec2> for Polar {
     fn from(value: DVec2) -> Self {
         // f64 version of macroquad cartesian_to_polar()
         let length = (value.x.powi(2) + value.y.powi(2)).sqrt();
-        let direction = value.y.atan2(value.x).to_degrees();
+        let direction = (-value.y).atan2(value.x).to_degrees().rem_euclid(360.0);
         Self { length, direction }
     }
 }
@@ -266,7 +517,7 @@ impl From<DVec2> for Polar {
 impl From<Polar> for DVec2 {
     fn from(value: Polar) -> Self {
         let (y, x) = value.direction.to_radians().sin_cos();
-        dvec2(x, y) * value.length
+        dvec2(x, -y) * value.length
     }
 }
 
@@ -311,3 +562,257 @@ impl InstanceVelocity {
         }
     }
 }
+
+fn snap_pos(pos: DVec2, snap: DVec2) -> DVec2 {
+    let snapped = (pos / snap).round() * snap;
+    DVec2::select(snap.cmpgt(DVec2::ZERO), snapped, pos)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use gml::eval::Global as _;
+
+    use super::*;
+    use crate::state::global::tests::{run, TestGame};
+
+    pub(crate) fn test_instance() -> Instance {
+        Instance {
+            id: ObjectId(100001),
+            state: RefCell::new(InstanceState {
+                pos: default(),
+                previous_pos: default(),
+                depth: 0,
+                velocity: default(),
+                friction: 0.0,
+                gravity: 0.0,
+                gravity_direction: 270.0,
+                solid: false,
+                visible: true,
+                persistent: false,
+                sprite_index: -1,
+                mask_index: -1,
+                sprite_asset: None,
+                image_speed: 1.0,
+                image_index: 0.0,
+                image_blend_alpha: macroquad::color::WHITE,
+            }),
+            object_index: 0,
+            parent_object_index: None,
+            vars: default(),
+            alarm_id: ObjectId(100002),
+            alarm: default(),
+        }
+    }
+
+    fn member(instance: &Instance, name: &str) -> f64 {
+        instance.member(name).unwrap().unwrap().to_float()
+    }
+
+    #[test]
+    fn test_velocity_members() {
+        let instance = test_instance();
+        instance.set_member("hspeed", (-3.0).into()).unwrap();
+        assert_eq!(member(&instance, "speed"), 3.0);
+        assert_eq!(member(&instance, "direction"), 180.0);
+
+        instance.set_member("vspeed", (-4.0).into()).unwrap();
+        assert_eq!(member(&instance, "speed"), 5.0);
+
+        instance.set_member("direction", 90.0.into()).unwrap();
+        assert!((member(&instance, "hspeed")).abs() < 1e-9);
+        assert!((member(&instance, "vspeed") + 5.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_gravity() {
+        let instance = test_instance();
+        instance.set_member("gravity", 0.5.into()).unwrap();
+        instance.state.borrow_mut().apply_motion();
+        assert!((member(&instance, "vspeed") - 0.5).abs() < 1e-9);
+        instance.state.borrow_mut().apply_motion();
+        assert!((member(&instance, "vspeed") - 1.0).abs() < 1e-9);
+        assert!((member(&instance, "y") - 1.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_friction() {
+        let instance = test_instance();
+        instance.set_member("hspeed", 1.0.into()).unwrap();
+        instance.set_member("friction", 0.4.into()).unwrap();
+        instance.state.borrow_mut().apply_motion();
+        assert!((member(&instance, "speed") - 0.6).abs() < 1e-9);
+        instance.state.borrow_mut().apply_motion();
+        instance.state.borrow_mut().apply_motion();
+        assert_eq!(member(&instance, "speed"), 0.0);
+        assert!((member(&instance, "x") - 0.8).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_move_snap() {
+        let instance = test_instance();
+        instance.state.borrow_mut().pos = dvec2(17.0, 17.0);
+        assert!(!instance.place_snapped(dvec2(16.0, 16.0)));
+        instance.move_snap(dvec2(16.0, 16.0));
+        assert_eq!(instance.state.borrow().pos, dvec2(16.0, 16.0));
+        assert!(instance.place_snapped(dvec2(16.0, 16.0)));
+
+        // only snap vertically
+        instance.state.borrow_mut().pos = dvec2(17.0, 25.0);
+        instance.move_snap(dvec2(0.0, 16.0));
+        assert_eq!(instance.state.borrow().pos, dvec2(17.0, 32.0));
+    }
+
+    #[test]
+    fn test_move_wrap() {
+        let instance = test_instance();
+        let room_size = dvec2(640.0, 480.0);
+        instance.set_member("hspeed", 4.0.into()).unwrap();
+        instance.state.borrow_mut().pos = dvec2(650.0, 100.0);
+        // still within the margin
+        instance.move_wrap([true, true], room_size, 16.0);
+        assert_eq!(instance.state.borrow().pos, dvec2(650.0, 100.0));
+
+        instance.state.borrow_mut().pos = dvec2(660.0, 100.0);
+        instance.move_wrap([true, true], room_size, 16.0);
+        assert_eq!(instance.state.borrow().pos, dvec2(-12.0, 100.0));
+
+        // only wraps in the direction it's moving
+        instance.move_wrap([true, true], room_size, 16.0);
+        assert_eq!(instance.state.borrow().pos, dvec2(-12.0, 100.0));
+
+        instance.set_member("vspeed", (-4.0).into()).unwrap();
+        instance.state.borrow_mut().pos = dvec2(100.0, -20.0);
+        instance.move_wrap([false, true], room_size, 16.0);
+        assert_eq!(instance.state.borrow().pos, dvec2(100.0, 492.0));
+    }
+
+    #[test]
+    fn test_move_towards_point() {
+        let instance = test_instance();
+        instance.move_towards_point(dvec2(0.0, -10.0), 4.0);
+        assert_eq!(member(&instance, "speed"), 4.0);
+        assert_eq!(member(&instance, "direction"), 90.0);
+
+        instance.move_towards_point(dvec2(-10.0, 10.0), 2.0);
+        assert_eq!(member(&instance, "speed"), 2.0);
+        assert_eq!(member(&instance, "direction"), 225.0);
+    }
+
+    #[test]
+    fn test_image_blend() {
+        let instance = test_instance();
+        assert_eq!(
+            instance.member("image_blend").unwrap(),
+            Some(0xFFFFFF.into())
+        );
+
+        instance.set_member("image_alpha", 0.5.into()).unwrap();
+        // c_red
+        instance.set_member("image_blend", 255.into()).unwrap();
+        assert_eq!(instance.member("image_blend").unwrap(), Some(255.into()));
+        let color = instance.state.borrow().image_blend_alpha;
+        assert_eq!([color.r, color.g, color.b, color.a], [1.0, 0.0, 0.0, 0.5]);
+
+        // c_orange
+        instance.set_member("image_blend", 4235519.into()).unwrap();
+        assert_eq!(
+            instance.member("image_blend").unwrap(),
+            Some(4235519.into())
+        );
+    }
+
+    #[test]
+    fn test_all_members() {
+        let instance = test_instance();
+        instance.set_member("x", 12.0.into()).unwrap();
+        instance
+            .set_member("foo", "bar".to_string().into())
+            .unwrap();
+        let members = instance.all_members();
+        assert_eq!(members.get("x"), Some(&Value::Float(12.0)));
+        assert_eq!(members.get("foo"), Some(&Value::String("bar".into())));
+    }
+
+    #[test]
+    fn test_builtin_members_listed() {
+        let instance = test_instance();
+        for &name in BUILTIN_MEMBERS {
+            assert!(instance.member(name).unwrap().is_some(), "{name}");
+        }
+
+        // and nothing else shows up besides user variables
+        instance.set_member("foo", 1.into()).unwrap();
+        let vars = instance.vars.debug_member_names().unwrap();
+        assert_eq!(vars, ["foo"]);
+        for name in instance.all_members().keys() {
+            assert!(
+                BUILTIN_MEMBERS.contains(&name.as_str()) || vars.contains(name),
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_mask_index() {
+        let instance = test_instance();
+        instance.set_member("sprite_index", 3.into()).unwrap();
+        // collisions don't need the sprite to have been drawn
+        assert!(instance.state.borrow().sprite_asset.is_none());
+        assert_eq!(instance.state.borrow().collision_sprite_index(), 3);
+        instance.set_member("mask_index", 7.into()).unwrap();
+        assert_eq!(instance.state.borrow().collision_sprite_index(), 7);
+        assert_eq!(member(&instance, "sprite_index"), 3.0);
+        instance.set_member("mask_index", (-1).into()).unwrap();
+        assert_eq!(instance.state.borrow().collision_sprite_index(), 3);
+    }
+
+    #[test]
+    fn test_copy() {
+        let instance = test_instance();
+        instance.set_member("x", 5.0.into()).unwrap();
+        instance.set_member("foo", 1.into()).unwrap();
+
+        let copy = instance.copy(ObjectId(100003), ObjectId(100004), default());
+        assert_eq!(member(&copy, "x"), 5.0);
+        assert_eq!(member(&copy, "foo"), 1.0);
+
+        copy.set_member("foo", 2.into()).unwrap();
+        copy.set_member("x", 6.0.into()).unwrap();
+        assert_eq!(member(&instance, "foo"), 1.0);
+        assert_eq!(member(&instance, "x"), 5.0);
+    }
+
+    #[test]
+    fn test_id() {
+        let mut game = TestGame::default();
+        game.object("obj_other", &[]);
+        let object_index = game.object("obj_test", &[]);
+        game.room("rm_test", &[(object_index, 5, 0)]);
+        let global = game.start();
+        let id = ObjectId(100001);
+        let eval = |source: &str| run(&global, id, &format!("return {source};")).unwrap();
+
+        assert_eq!(eval("id"), id.into());
+        assert_eq!(eval("object_index"), Value::Int(object_index as i32));
+        assert_eq!(eval("id.x"), 5.0.into());
+        let instance = global.instance(id).unwrap();
+        assert!(instance.set_member("id", 1.into()).is_err());
+    }
+
+    #[test]
+    fn test_serialize_velocity() {
+        let serialize = |velocity| {
+            let json = serialize_velocity(&velocity, serde_json::value::Serializer).unwrap();
+            ["hspeed", "vspeed", "speed", "direction"].map(|name| json[name].as_f64().unwrap())
+        };
+        let cartesian = serialize(InstanceVelocity::Cartesian(dvec2(0.0, -2.0)));
+        let polar = serialize(InstanceVelocity::Polar(Polar {
+            length: 2.0,
+            direction: 90.0,
+        }));
+        for (cartesian, polar) in cartesian.into_iter().zip(polar) {
+            assert!((cartesian - polar).abs() < 1e-9);
+        }
+        assert_eq!(cartesian, [0.0, -2.0, 2.0, 90.0]);
+    }
+}
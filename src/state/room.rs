@@ -1,8 +1,8 @@
-use glam::{ivec2, vec2};
-use gml::eval::{Object, ObjectId};
+use glam::{ivec2, uvec2, UVec2};
+use gml::eval::{Object, ObjectId, Value};
 use macroquad::color::Color;
 use macroquad::math::Rect;
-use macroquad::prelude::{clear_background, get_frame_time, screen_height, screen_width};
+use macroquad::prelude::{clear_background, get_frame_time};
 use serde::ser::SerializeMap;
 use serde::{Serialize, Serializer};
 use std::cell::RefCell;
@@ -17,9 +17,13 @@ use super::{
 #[derive(Serialize)]
 pub struct Room {
     pub index: u32,
+    /// `room_width` and `room_height`.
+    pub size: UVec2,
     pub view: View,
     #[serde(serialize_with = "serialize_color")]
     pub background_color: Color,
+    /// Whether to clear to `background_color` before drawing.
+    pub draw_background_color: bool,
     pub background_layers: Vec<Layer>,
     pub tiles: Vec<Tile>,
     #[serde(serialize_with = "serialize_object_instances")]
@@ -31,6 +35,9 @@ pub struct Room {
     #[serde(serialize_with = "serialize_script_instances")]
     pub script_instances: RefCell<HashMap<ObjectId, Rc<dyn Object>>>,
 
+    /// Created since the last `cleanup()`. Queries like `instance_number()` and collisions
+    /// see these straight away, but events aren't dispatched to them until then, other than
+    /// the Create event `instance_create()` runs itself.
     #[serde(skip)]
     pub added_instances: RefCell<HashMap<u32, Rc<Instance>>>,
     #[serde(skip)]
@@ -41,7 +48,9 @@ impl std::fmt::Debug for Room {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Room")
             .field("view", &self.view)
+            .field("size", &self.size)
             .field("background_color", &self.background_color)
+            .field("draw_background_color", &self.draw_background_color)
             .field("background_layers", &self.background_layers)
             .field("tiles", &self.tiles)
             .field("object_instances", &self.object_instances)
@@ -55,14 +64,18 @@ impl std::fmt::Debug for Room {
 }
 
 impl Room {
+    /// An empty room of GM's default size, until [`Self::load()`].
     pub fn new(index: u32) -> Self {
+        let size = uvec2(640, 480);
         Self {
             index,
+            size,
             view: View {
                 offset: default(),
-                size: vec2(screen_width(), screen_height()).as_uvec2(),
+                size,
             },
             background_color: default(),
+            draw_background_color: true,
             background_layers: vec![],
             tiles: vec![],
             object_instances: default(),
@@ -78,14 +91,20 @@ impl Room {
     }
 
     pub fn load(&mut self, global: &Global, def: &gmk_file::Room) {
+        self.size = uvec2(def.size.0, def.size.1);
+        self.view = room_view(def);
         self.background_color = color_u32(def.background_color);
+        self.draw_background_color = def.draw_background_color.into();
 
         for b in &def.backgrounds {
             let Ok(index) = b.background_image_index.try_into() else {
                 continue;
             };
 
-            let asset = global.loader().get_background(index);
+            let Some(asset) = global.loader().get_background(index) else {
+                log::warn!("room {}: background {index} does not exist", self.index);
+                continue;
+            };
             let assets = global.assets.borrow();
             let bg = assets.backgrounds.get(asset);
 
@@ -99,6 +118,9 @@ impl Room {
                 pos: ivec2(b.pos.0, b.pos.1),
                 asset,
                 tile: b.tile.0 != 0,
+                // stored unsigned, but negative speeds scroll left or up
+                speed: ivec2(b.speed.0 as i32, b.speed.1 as i32),
+                scroll_offset: default(),
                 source: bg.tile_enabled.then_some({
                     let pos = bg.tile_pos.as_vec2();
                     let size = bg.tile_size.as_vec2();
@@ -108,9 +130,17 @@ impl Room {
         }
 
         for t in &def.tiles {
+            let Some(asset) = global.loader().get_background(t.background_index) else {
+                log::warn!(
+                    "room {}: tile background {} does not exist",
+                    self.index,
+                    t.background_index
+                );
+                continue;
+            };
             self.tiles.push(Tile {
                 depth: t.depth,
-                asset: global.loader().get_background(t.background_index),
+                asset,
                 pos: ivec2(t.pos.0, t.pos.1),
                 source: Rect {
                     x: t.tile.0 as f32,
@@ -126,7 +156,11 @@ impl Room {
 
     pub fn step(&self, global: &Global) {
         let mut elapsed = self.elapsed.borrow_mut();
-        *elapsed += get_frame_time() * self.speed;
+        *elapsed += if global.options.fixed_step {
+            1.0
+        } else {
+            get_frame_time() * self.speed
+        };
         while *elapsed >= 1.0 {
             *elapsed -= 1.0;
             self.dispatch(global, Event::StepBegin);
@@ -134,44 +168,77 @@ impl Room {
                 instance.clone().step(global);
             }
             self.dispatch(global, Event::StepNormal);
+            self.collide(global);
             self.dispatch(global, Event::StepEnd);
+            for layer in self.background_layers.iter().chain(&self.foreground_layers) {
+                layer.scroll();
+            }
+        }
+    }
+
+    /// Dispatches Collision events to each instance overlapping an instance its object has
+    /// an event for, with `other` as the instance it collided with. Like GM, if either
+    /// instance is solid they are both moved back to their previous positions first.
+    fn collide(&self, global: &Global) {
+        let bounds = |instance: &Instance| {
+            let pos = instance.state.borrow().pos;
+            global.instance_bounds(instance, pos)
+        };
+        let object_instances = self.object_instances.borrow();
+        let instance_bounds = object_instances
+            .values
+            .values()
+            .filter_map(|instance| Some((instance.clone(), bounds(instance)?)))
+            .collect::<Vec<_>>();
+        drop(object_instances);
+
+        let collisions = collision_pairs(&instance_bounds, |instance, other| {
+            global.collision_event(instance.object_index, other.object_index)
+        });
+        for (instance, other, event) in collisions {
+            // an earlier collision event may have destroyed or moved either of them
+            if self.is_destroyed(instance.id) || self.is_destroyed(other.id) {
+                continue;
+            }
+            match (bounds(&instance), bounds(&other)) {
+                (Some(a), Some(b)) if a.overlaps(&b) => {}
+                _ => continue,
+            }
+            if instance.state.borrow().solid || other.state.borrow().solid {
+                for instance in [&instance, &other] {
+                    let mut state = instance.state.borrow_mut();
+                    state.pos = state.previous_pos;
+                }
+            }
+            instance.dispatch_other(global, event, other.id);
         }
+        self.cleanup(global);
+    }
+
+    /// The color to clear to before drawing, if any.
+    pub fn clear_color(&self) -> Option<Color> {
+        self.draw_background_color.then_some(self.background_color)
     }
 
     pub fn draw(&self, global: &Global) {
-        clear_background(self.background_color);
+        if let Some(color) = self.clear_color() {
+            clear_background(color);
+        }
         for layer in &self.background_layers {
             layer.draw(global, &self.view);
         }
 
         let object_instances = self.object_instances.borrow();
-        enum DrawItem<'a> {
-            Tile(&'a Tile),
-            Instance(Rc<Instance>),
-        }
-        let mut depth_draws = Vec::new();
-        depth_draws.extend(self.tiles.iter().map(DrawItem::Tile));
-        depth_draws.extend(
-            object_instances
-                .values
-                .values()
-                .filter(|item| {
-                    let state = item.state.borrow();
-                    state.visible && (-16000..=16000).contains(&state.depth)
-                })
-                .map(|item| DrawItem::Instance(item.clone())),
-        );
-        depth_draws.sort_by_key(|item| match item {
-            DrawItem::Tile(tile) => -tile.depth,
-            DrawItem::Instance(instance) => -instance.state.borrow().depth,
-        });
-
-        for draw in depth_draws {
+        for draw in depth_draws(&self.tiles, object_instances.values.values()) {
             match draw {
                 DrawItem::Tile(tile) => tile.draw(global, &self.view),
+                // a Draw event replaces drawing the sprite
                 DrawItem::Instance(instance) => {
-                    instance.draw(global, &self.view);
-                    instance.dispatch(global, Event::Draw);
+                    if global.object_has_event(instance.object_index, Event::Draw) {
+                        instance.dispatch(global, Event::Draw);
+                    } else {
+                        instance.draw(global, &self.view);
+                    }
                 }
             }
         }
@@ -182,13 +249,65 @@ impl Room {
         }
     }
 
+    /// All instances, including those added since the last `cleanup()`.
+    pub fn instances(&self) -> Vec<Rc<Instance>> {
+        let object_instances = self.object_instances.borrow();
+        let added_instances = self.added_instances.borrow();
+        object_instances
+            .values
+            .values()
+            .chain(added_instances.values())
+            .cloned()
+            .collect()
+    }
+
+    /// Removes the persistent instances, to carry them over to the next room.
+    pub fn take_persistent_instances(&self) -> Vec<Rc<Instance>> {
+        let mut object_instances = self.object_instances.borrow_mut();
+        let ids = object_instances
+            .values
+            .iter()
+            .filter(|(_, instance)| instance.state.borrow().persistent)
+            .map(|(&id, _)| id)
+            .collect::<Vec<_>>();
+        ids.iter()
+            .flat_map(|id| object_instances.values.remove(id))
+            .collect()
+    }
+
     pub fn destroy_instance(&self, id: ObjectId) {
         self.destroyed_instances.borrow_mut().push(id);
     }
 
+    /// An object created by a script, like a data structure, without logging if it's missing.
+    pub fn script_instance(&self, id: ObjectId) -> Option<Rc<dyn Object>> {
+        self.script_instances.borrow().get(&id).cloned()
+    }
+
+    /// An instance or script object, including instances created this event, which are only
+    /// in `object_instances` after the next `cleanup()`.
+    pub fn instance(&self, id: ObjectId) -> Option<Rc<dyn Object>> {
+        if let Some(object) = self.added_instances.borrow().get(&id.instance_id()) {
+            Some(object.clone())
+        } else if let Some(object) = self.script_instances.borrow().get(&id) {
+            Some(object.clone())
+        } else {
+            let object_instances = self.object_instances.borrow();
+            let object = object_instances.values.get(&id.instance_id())?;
+            Some(object.clone())
+        }
+    }
+
+    /// Whether `id` was destroyed this event, so scripts should no longer find it.
+    pub fn is_destroyed(&self, id: ObjectId) -> bool {
+        self.destroyed_instances.borrow().contains(&id)
+    }
+
     pub fn dispatch(&self, global: &Global, event: Event) {
         for instance in self.object_instances.borrow().values.values() {
-            instance.clone().dispatch(global, event);
+            if global.object_types[&instance.object_index].handles(event) {
+                instance.clone().dispatch(global, event);
+            }
         }
         self.cleanup(global);
         // should also do global.cleanup_room_goto(), but this room is locked :(
@@ -197,7 +316,9 @@ impl Room {
     pub fn cleanup(&self, global: &Global) {
         let mut not_found_instances = 0;
 
-        for id in self.destroyed_instances.borrow_mut().drain(..) {
+        // destroy events may destroy more instances, which are handled by the next cleanup
+        let destroyed_instances = std::mem::take(&mut *self.destroyed_instances.borrow_mut());
+        for id in destroyed_instances {
             if let Some(instance) = self
                 .object_instances
                 .borrow_mut()
@@ -219,9 +340,75 @@ impl Room {
             .extend(self.added_instances.borrow_mut().drain());
 
         if not_found_instances != 0 {
-            println!("instances not found during script cleanup: {not_found_instances}");
+            log::warn!("instances not found during script cleanup: {not_found_instances}");
+        }
+    }
+}
+
+enum DrawItem<'a> {
+    Tile(&'a Tile),
+    Instance(Rc<Instance>),
+}
+
+/// Tiles and visible instances, from the highest depth to the lowest. Tiles are drawn
+/// before instances at the same depth.
+fn depth_draws<'a>(
+    tiles: &'a [Tile],
+    instances: impl Iterator<Item = &'a Rc<Instance>>,
+) -> Vec<DrawItem<'a>> {
+    let mut depth_draws = Vec::new();
+    depth_draws.extend(tiles.iter().map(DrawItem::Tile));
+    depth_draws.extend(
+        instances
+            .filter(|item| {
+                let state = item.state.borrow();
+                state.visible && (-16000..=16000).contains(&state.depth)
+            })
+            .map(|item| DrawItem::Instance(item.clone())),
+    );
+    depth_draws.sort_by_key(|item| match item {
+        DrawItem::Tile(tile) => -tile.depth,
+        DrawItem::Instance(instance) => -instance.state.borrow().depth,
+    });
+    depth_draws
+}
+
+/// The room's first visible view if views are enabled, otherwise the whole room. Only one
+/// view is drawn for now.
+fn room_view(def: &gmk_file::Room) -> View {
+    let view = bool::from(def.enable_views)
+        .then(|| def.views.iter().find(|view| view.visible.into()))
+        .flatten();
+    match view {
+        Some(view) => View {
+            offset: ivec2(view.view_pos.0 as i32, view.view_pos.1 as i32),
+            size: uvec2(view.view_size.0, view.view_size.1),
+        },
+        None => View {
+            offset: default(),
+            size: uvec2(def.size.0, def.size.1),
+        },
+    }
+}
+
+/// Each ordered pair of overlapping instances where `event` returns the event the first
+/// instance should get.
+fn collision_pairs(
+    bounds: &[(Rc<Instance>, Rect)],
+    event: impl Fn(&Instance, &Instance) -> Option<Event>,
+) -> Vec<(Rc<Instance>, Rc<Instance>, Event)> {
+    let mut pairs = vec![];
+    for (instance, instance_bounds) in bounds {
+        for (other, other_bounds) in bounds {
+            if other.id == instance.id || !instance_bounds.overlaps(other_bounds) {
+                continue;
+            }
+            if let Some(event) = event(instance, other) {
+                pairs.push((instance.clone(), other.clone(), event));
+            }
         }
     }
+    pairs
 }
 
 fn serialize_object_instances<S: Serializer>(
@@ -246,6 +433,19 @@ fn serialize_object_instances<S: Serializer>(
     map.end()
 }
 
+/// Serializes as a map in the same order, as keys aren't always sorted like numbers.
+struct DebugEntries<'a>(&'a [(String, Value)]);
+
+impl Serialize for DebugEntries<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (key, value) in self.0 {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
+
 fn serialize_script_instances<S: Serializer>(
     this: &RefCell<HashMap<ObjectId, Rc<dyn Object>>>,
     serializer: S,
@@ -260,6 +460,8 @@ fn serialize_script_instances<S: Serializer>(
                 .collect::<HashMap<_, _>>();
 
             map.serialize_entry(&key, &members)?;
+        } else if let Some(entries) = value.debug_entries() {
+            map.serialize_entry(&key, &DebugEntries(&entries))?;
         } else if let Some(length) = value.debug_index_length() {
             let values = (0..length)
                 .map(|index| value.index(&[(index as i32).into()]).unwrap_or_default())
@@ -272,3 +474,229 @@ fn serialize_script_instances<S: Serializer>(
     }
     map.end()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    use crate::state::global::tests::{run, TestGame};
+    use crate::state::instance::tests::test_instance;
+    use crate::state::AssetId;
+    use crate::state::InstanceState;
+
+    #[test]
+    fn test_depth_draws() {
+        let tiles = [Tile {
+            depth: 0,
+            asset: AssetId::new(0),
+            pos: default(),
+            source: Rect::new(0.0, 0.0, 16.0, 16.0),
+        }];
+        let instances = [-1, 1].map(|depth| {
+            let instance = test_instance();
+            instance.state.borrow_mut().depth = depth;
+            Rc::new(instance)
+        });
+
+        let depths = depth_draws(&tiles, instances.iter())
+            .iter()
+            .map(|item| match item {
+                DrawItem::Tile(tile) => ("tile", tile.depth),
+                DrawItem::Instance(instance) => ("instance", instance.state.borrow().depth),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(depths, [("instance", 1), ("tile", 0), ("instance", -1)]);
+    }
+
+    #[test]
+    fn test_collision_pairs() {
+        let instance = |id: i32, object_index: u32, x: f32| {
+            let instance = Instance {
+                id: ObjectId(id),
+                object_index,
+                ..test_instance()
+            };
+            (Rc::new(instance), Rect::new(x, 0.0, 16.0, 16.0))
+        };
+        // 1 and 2 overlap, 3 is off to the side
+        let bounds = [
+            instance(100001, 0, 0.0),
+            instance(100002, 1, 8.0),
+            instance(100003, 1, 100.0),
+        ];
+        // both objects have a collision event for the other
+        let pairs = collision_pairs(&bounds, |instance, other| {
+            (instance.object_index != other.object_index)
+                .then_some(Event::Collision(other.object_index as i32))
+        });
+
+        let pairs = pairs
+            .iter()
+            .map(|(instance, other, event)| (instance.id.0, other.id.0, *event))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            pairs,
+            [
+                (100001, 100002, Event::Collision(1)),
+                (100002, 100001, Event::Collision(0)),
+            ]
+        );
+    }
+
+    /// How many Collision events two overlapping instances got, when their events run
+    /// `code` after counting them.
+    fn collision_count(code: &str) -> Option<Value> {
+        use gmk_file::EventId;
+        use gml::eval::Global as _;
+
+        let mut game = TestGame::default();
+        let sprite_index = game.sprite("spr_block", (16, 16));
+        let code = format!("global.hits += 1; {code}");
+        let first = game.object(
+            "obj_first",
+            &[
+                (EventId::Create, "global.hits = 0;"),
+                (EventId::Collision(1), &code),
+            ],
+        );
+        let second = game.object("obj_second", &[(EventId::Collision(0), &code)]);
+        for object in &mut game.content.objects.items {
+            object.as_mut().unwrap().data.sprite_index = sprite_index as i32;
+        }
+        game.room("rm_test", &[(first, 0, 0), (second, 8, 0)]);
+        let global = game.start();
+
+        global.room.borrow().collide(&global);
+        global.get("hits").unwrap()
+    }
+
+    #[test]
+    fn test_collide() {
+        assert_eq!(collision_count(""), Some(Value::Int(2)));
+        // either one's event destroying the other means it doesn't get its event,
+        let destroy = "with (other) instance_destroy();";
+        assert_eq!(collision_count(destroy), Some(Value::Int(1)));
+        // and so does moving it away
+        assert_eq!(collision_count("other.x += 100;"), Some(Value::Int(1)));
+    }
+
+    #[test]
+    fn test_room_view() {
+        let view = |offset, size| gmk_file::RoomView {
+            visible: gmk_file::Bool32::True,
+            view_pos: gmk_file::Pair(offset, 0),
+            view_size: gmk_file::Pair(size, 240),
+            ..default()
+        };
+        let mut def = gmk_file::Room {
+            size: gmk_file::Pair(1000, 800),
+            views: vec![
+                gmk_file::RoomView {
+                    visible: gmk_file::Bool32::False,
+                    ..view(0, 100)
+                },
+                view(50, 320),
+            ],
+            ..default()
+        };
+
+        // the whole room when views are off
+        let whole = room_view(&def);
+        assert_eq!((whole.offset, whole.size), (ivec2(0, 0), uvec2(1000, 800)));
+
+        def.enable_views = gmk_file::Bool32::True;
+        let view = room_view(&def);
+        assert_eq!((view.offset, view.size), (ivec2(50, 0), uvec2(320, 240)));
+    }
+
+    #[test]
+    fn test_clear_color() {
+        let mut room = Room::new(0);
+        room.background_color = Color::from_rgba(0, 0, 255, 255);
+        assert_eq!(room.clear_color(), Some(room.background_color));
+
+        room.draw_background_color = false;
+        assert_eq!(room.clear_color(), None);
+    }
+
+    #[test]
+    fn test_take_persistent_instances() {
+        let room = Room::new(0);
+        // the second starts persistent from its object, the third is made persistent by a script
+        for (id, persistent) in [(100001, false), (100002, true), (100003, false)] {
+            let instance = test_instance();
+            let instance = Instance {
+                id: ObjectId(id),
+                state: RefCell::new(InstanceState {
+                    persistent,
+                    ..instance.state.into_inner()
+                }),
+                ..instance
+            };
+            instance.vars.insert("hp", id);
+            room.object_instances
+                .borrow_mut()
+                .values
+                .insert(instance.id.instance_id(), Rc::new(instance));
+        }
+        let third = room.instance(ObjectId(100003)).unwrap();
+        assert_eq!(
+            third.member("persistent").unwrap(),
+            Some(Value::Bool(false))
+        );
+        third.set_member("persistent", true.into()).unwrap();
+
+        let persistent = room.take_persistent_instances();
+        let ids = persistent
+            .iter()
+            .map(|instance| instance.id.0)
+            .collect::<HashSet<_>>();
+        assert_eq!(ids, HashSet::from([100002, 100003]));
+        assert_eq!(room.object_instances.borrow().values.len(), 1);
+    }
+
+    #[test]
+    fn test_instances_includes_added() {
+        let room = Room::new(0);
+        room.object_instances
+            .borrow_mut()
+            .values
+            .insert(100001, Rc::new(test_instance()));
+        // like instance_create() from a Create event
+        let added = Instance {
+            id: ObjectId(100002),
+            ..test_instance()
+        };
+        room.added_instances
+            .borrow_mut()
+            .insert(100002, Rc::new(added));
+
+        let mut ids = room
+            .instances()
+            .iter()
+            .map(|instance| instance.id.0)
+            .collect::<Vec<_>>();
+        ids.sort();
+        assert_eq!(ids, [100001, 100002]);
+    }
+
+    #[test]
+    fn test_with_added_instance() {
+        let mut game = TestGame::default();
+        let object_index = game.object("obj_test", &[]);
+        game.room("rm_test", &[(object_index, 0, 0)]);
+        let global = game.start();
+
+        let source = "new_id = instance_create(0, 0, obj_test); with (new_id) { x = 5; }";
+        run(&global, ObjectId(100001), source).unwrap();
+
+        // not dispatched to yet, but `with` still finds it
+        let room = global.room.borrow();
+        let instance = room.instance(ObjectId(100001)).unwrap();
+        let new_id = instance.member("new_id").unwrap().unwrap();
+        let added = room.added_instances.borrow()[&(new_id.to_int() as u32)].clone();
+        assert_eq!(added.member("x").unwrap(), Some(5.0.into()));
+        assert_eq!(instance.member("x").unwrap(), Some(0.0.into()));
+    }
+}